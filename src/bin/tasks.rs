@@ -2,25 +2,38 @@
 
 #![deny(clippy::all)]
 
-use anyhow::{Context, Result};
-use chrono::Months;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use clap::Parser;
-use log::{debug, error, info};
-use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use log::{debug, error, info, warn};
+use serde_json::json;
+use sqlx::{Row, SqlitePool};
 use std::{
     collections::HashMap,
     env,
+    fmt::Write,
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
-use tokio::time;
+use tokio::{sync::Mutex, time};
 use vatsim_utils::rest_api;
 use vzdv::{
-    load_config, load_db,
-    shared::{self, sql, Config},
+    init_logging, load_config, load_db,
+    shared::{
+        self,
+        sql::{self, Activity, Certification, Controller, Event, IpcMessage, SoloCert},
+        Config, IPC_ACTIVITY_SYNC, IPC_VATUSA_SYNC,
+    },
     utils::{
-        position_in_facility_airspace,
-        vatusa::{get_roster, MembershipType, RosterMember},
+        activity_fetch_start, activity_violation, api_metrics, certification_last_used,
+        certification_unused_until, certification_valid_until, clean_up_atis, digest_due,
+        email::send_mail_raw,
+        find_orphaned_assets, get_controller_cids_and_names, is_new_controller, is_on_loa,
+        parse_vatusa_date, position_in_facility_airspace, quarterly_minutes, record_log,
+        reminder_due, roster_diff, should_remind_of_activity, truncate_chars,
+        vatusa::{delete_solo_cert, get_controller_info, get_roster, MembershipType, RosterMember},
+        LogCategory, DISCORD_EMBED_FIELD_LIMIT, GENERAL_HTTP_CLIENT, QUARTERLY_ACTIVITY_MINUTES,
     },
 };
 
@@ -37,19 +50,31 @@ struct Cli {
     /// Enable debug logging
     #[arg(short, long)]
     debug: bool,
+
+    /// Write logs as JSON lines
+    #[arg(long)]
+    json_logs: bool,
 }
 
 /// Update a single controller's stored data.
-async fn update_controller_record(db: &SqlitePool, controller: &RosterMember) -> Result<()> {
+async fn update_controller_record(
+    config: &Config,
+    db: &SqlitePool,
+    controller: &RosterMember,
+) -> Result<()> {
     let roles = controller
         .roles
         .iter()
-        .filter(|role| role.facility == "ZDV")
+        .filter(|role| role.facility == config.facility.code)
         .map(|role| role.role.as_str())
         // there's 1 controller in ZDV who actually has an "INS" role in addition to their controller rating
         .filter(|&role| role != "INS")
         .collect::<Vec<_>>()
         .join(",");
+    let existing: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(controller.cid)
+        .fetch_optional(db)
+        .await?;
     sqlx::query(sql::UPSERT_USER_TASK)
         .bind(controller.cid)
         .bind(&controller.first_name)
@@ -59,9 +84,47 @@ async fn update_controller_record(db: &SqlitePool, controller: &RosterMember) ->
         .bind(&controller.facility)
         // controller *will* be on the roster since that's what the VATSIM API is showing
         .bind(true)
-        .bind(roles)
+        .bind(&roles)
+        .bind(controller.flag_name_privacy)
+        .bind(controller.promotion_eligible.unwrap_or_default())
+        .bind(parse_vatusa_date(&controller.facility_join))
         .execute(db)
         .await?;
+    match existing {
+        Some(existing) if existing.is_on_roster => {
+            if existing.rating != controller.rating as i8 {
+                record_log(
+                    db,
+                    LogCategory::Info,
+                    Some(controller.cid),
+                    &format!(
+                        "Rating changed from {} to {}",
+                        Controller::rating_name(existing.rating),
+                        Controller::rating_name(controller.rating as i8)
+                    ),
+                )
+                .await?;
+            }
+            if existing.roles != roles {
+                record_log(
+                    db,
+                    LogCategory::Security,
+                    Some(controller.cid),
+                    &format!("Roles changed from '{}' to '{roles}'", existing.roles),
+                )
+                .await?;
+            }
+        }
+        _ => {
+            record_log(
+                db,
+                LogCategory::Info,
+                Some(controller.cid),
+                &format!("Added to roster ({})", controller.facility),
+            )
+            .await?;
+        }
+    }
     debug!(
         "{} {} ({}) updated in DB",
         &controller.first_name, &controller.last_name, controller.cid
@@ -70,15 +133,23 @@ async fn update_controller_record(db: &SqlitePool, controller: &RosterMember) ->
 }
 
 /// Update the stored roster with fresh data from VATUSA.
-async fn update_roster(db: &SqlitePool) -> Result<()> {
+async fn update_roster(config: &Config, db: &SqlitePool) -> Result<()> {
     /*
      * Don't use a transaction here; instead, attempt to update every controller's
-     * data. Don't error-out unless VATSIM doesn't give any data.
+     * data. Don't error-out unless VATSIM doesn't give any data or the
+     * removals look suspicious.
      */
-    let roster_data = get_roster("ZDV", MembershipType::Both).await?;
+    let roster_data = get_roster(&config.facility.code, MembershipType::Both).await?;
     debug!("Got roster response");
+    // the stored roster from before this sync, to compare against
+    let previous_controllers = sqlx::query(sql::GET_ALL_ROSTER_CONTROLLER_CIDS)
+        .fetch_all(db)
+        .await?
+        .iter()
+        .map(|row| row.try_get("cid"))
+        .collect::<Result<Vec<u32>, _>>()?;
     for controller in &roster_data {
-        if let Err(e) = update_controller_record(db, controller).await {
+        if let Err(e) = update_controller_record(config, db, controller).await {
             error!("Error updating controller {} in DB: {e}", controller.cid);
         };
     }
@@ -88,45 +159,323 @@ async fn update_roster(db: &SqlitePool) -> Result<()> {
         .iter()
         .map(|controller| controller.cid)
         .collect();
-    let db_controllers: Vec<SqliteRow> = sqlx::query(sql::GET_ALL_CONTROLLER_CIDS)
+    let roster_size = previous_controllers.len();
+    let (added, removed) = roster_diff(&previous_controllers, &current_controllers);
+
+    /*
+     * VATUSA sometimes briefly leaves controllers out of the roster, so only
+     * remove those who've been missing from enough syncs in a row.
+     */
+    if let Err(e) = sqlx::query(sql::CLEAR_MISSING_FROM_ROSTER)
+        .bind(serde_json::to_string(&current_controllers)?)
+        .execute(db)
+        .await
+    {
+        error!("Error clearing returned controllers' missing flags: {e}");
+    }
+    let now = Utc::now();
+    let mut due_removals = Vec::new();
+    for cid in removed {
+        let missed_syncs: u32 = match sqlx::query_scalar(sql::UPDATE_MISSING_FROM_ROSTER)
+            .bind(cid)
+            .bind(now)
+            .fetch_one(db)
+            .await
+        {
+            Ok(missed_syncs) => missed_syncs,
+            Err(e) => {
+                error!("Error flagging controller {cid} as missing from the roster: {e}");
+                continue;
+            }
+        };
+        if config.roster.removal_due(missed_syncs) {
+            due_removals.push(cid);
+        } else {
+            debug!("Controller {cid} has been missing from {missed_syncs} roster syncs");
+            if missed_syncs == 1 {
+                if let Err(e) = record_log(
+                    db,
+                    LogCategory::Info,
+                    Some(cid),
+                    "Missing from the VATUSA roster; pending removal",
+                )
+                .await
+                {
+                    error!("Error recording {cid} missing from the roster: {e}")
+                }
+            }
+        }
+    }
+    let removed = due_removals;
+
+    /*
+     * A large drop usually means a bad response from VATUSA rather than a
+     * mass exodus, so leave the stored roster alone and let staff check it.
+     * Only removals that are due count; the rest are just being tracked.
+     */
+    if config
+        .roster
+        .removals_exceed_limit(removed.len(), roster_size)
+    {
+        let message = format!(
+            "Roster sync would remove {} of {roster_size} controllers; skipped removing: {}",
+            removed.len(),
+            removed
+                .iter()
+                .map(|cid| cid.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if !config.discord.webhooks.roster_alert.is_empty() {
+            let resp = GENERAL_HTTP_CLIENT
+                .post(&config.discord.webhooks.roster_alert)
+                .json(&json!({
+                    "content": "",
+                    "embeds": [{
+                        "title": "Roster sync aborted",
+                        "description": message
+                    }]
+                }))
+                .send()
+                .await;
+            match resp {
+                Ok(resp) if !resp.status().is_success() => warn!(
+                    "Got status {} from Discord posting roster alert",
+                    resp.status().as_u16()
+                ),
+                Err(e) => error!("Could not post roster alert: {e}"),
+                _ => {}
+            }
+        }
+        return Err(anyhow!(message));
+    }
+
+    post_roster_changes(config, db, &roster_data, &added, &removed).await;
+
+    for cid in removed {
+        debug!("Controller {cid} is not on the roster");
+        if let Err(e) = sqlx::query(sql::UPDATE_REMOVED_FROM_ROSTER)
+            .bind(cid)
+            .execute(db)
+            .await
+        {
+            error!("Error updating controller {cid} to show off-roster: {e}")
+        } else if let Err(e) =
+            record_log(db, LogCategory::Security, Some(cid), "Removed from roster").await
+        {
+            error!("Error recording roster removal of {cid}: {e}")
+        }
+        if let Err(e) = clean_up_removed_controller(config, db, cid).await {
+            error!("Error cleaning up records of {cid}: {e}");
+        }
+        if let Err(e) = remove_solo_certs(config, db, cid).await {
+            error!("Error removing solo certs of {cid}: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Clear a controller who left the roster out of upcoming events.
+///
+/// Their certifications are deleted too if configured. Staff notes are always
+/// kept for history.
+async fn clean_up_removed_controller(config: &Config, db: &SqlitePool, cid: u32) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = db.begin().await?;
+    let registrations = sqlx::query(sql::DELETE_FUTURE_EVENT_REGISTRATIONS_FOR)
+        .bind(cid)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let positions = sqlx::query(sql::CLEAR_FUTURE_EVENT_POSITIONS_FOR)
+        .bind(cid)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let certifications = if config.roster.delete_removed_certifications {
+        sqlx::query(sql::DELETE_CERTIFICATIONS_FOR)
+            .bind(cid)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+    } else {
+        0
+    };
+    if registrations > 0 || positions > 0 || certifications > 0 {
+        record_log(
+            &mut *tx,
+            LogCategory::Info,
+            Some(cid),
+            &format!(
+                "Cleaned up after roster removal: {registrations} event registrations, \
+                {positions} event positions, {certifications} certifications"
+            ),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Delete the solo certs of a controller who left the roster.
+///
+/// Certs that were reported to VATUSA are removed there first; if that
+/// fails, the cert is kept so staff can see it and clean it up by hand.
+async fn remove_solo_certs(config: &Config, db: &SqlitePool, cid: u32) -> Result<()> {
+    let solo_certs: Vec<SoloCert> = sqlx::query_as(sql::GET_SOLO_CERTS_FOR)
+        .bind(cid)
         .fetch_all(db)
         .await?;
-    for row in db_controllers {
-        let cid: u32 = row.try_get("cid")?;
-        if !current_controllers.contains(&cid) {
-            debug!("Controller {cid} is not on the roster");
-            if let Err(e) = sqlx::query(sql::UPDATE_REMOVED_FROM_ROSTER)
-                .bind(cid)
-                .execute(db)
-                .await
+    for cert in solo_certs {
+        if cert.reported {
+            if let Err(e) =
+                delete_solo_cert(&config.vatsim.vatusa_api_key, cid, &cert.position).await
             {
-                error!("Error updating controller {cid} to show off-roster: {e}")
+                warn!(
+                    "Could not remove solo cert for {cid} on {} from VATUSA: {e}",
+                    cert.position
+                );
+                record_log(
+                    db,
+                    LogCategory::Info,
+                    Some(cid),
+                    &format!(
+                        "Solo cert on {} left in place after roster removal; remove it from VATUSA by hand",
+                        cert.position
+                    ),
+                )
+                .await?;
+                continue;
             }
         }
+        sqlx::query(sql::DELETE_SOLO_CERT)
+            .bind(cert.id)
+            .execute(db)
+            .await?;
+        record_log(
+            db,
+            LogCategory::Info,
+            Some(cid),
+            &format!(
+                "Solo cert on {} removed after roster removal",
+                cert.position
+            ),
+        )
+        .await?;
     }
-
     Ok(())
 }
 
+/// Post who joined and left the roster to Discord, if the webhook is configured.
+///
+/// Errors are logged rather than returned so they don't stop the sync.
+async fn post_roster_changes(
+    config: &Config,
+    db: &SqlitePool,
+    roster_data: &[RosterMember],
+    added: &[u32],
+    removed: &[u32],
+) {
+    let webhook = &config.discord.webhooks.roster_changes;
+    if webhook.is_empty() || (added.is_empty() && removed.is_empty()) {
+        return;
+    }
+    let names = match get_controller_cids_and_names(db).await {
+        Ok(names) => names,
+        Err(e) => {
+            error!("Could not get controller names for roster changes: {e}");
+            return;
+        }
+    };
+    let added = added
+        .iter()
+        .map(|cid| match roster_data.iter().find(|c| c.cid == *cid) {
+            Some(c) => format!("{} {} ({cid})", c.first_name, c.last_name),
+            None => cid.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let removed = removed
+        .iter()
+        .map(|cid| match names.get(&(*cid as u64)) {
+            Some((first, last)) => format!("{first} {last} ({cid})"),
+            None => cid.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let field = |value: String| {
+        if value.is_empty() {
+            "None".to_owned()
+        } else {
+            truncate_chars(&value, DISCORD_EMBED_FIELD_LIMIT - 3)
+        }
+    };
+    let resp = GENERAL_HTTP_CLIENT
+        .post(webhook)
+        .json(&json!({
+            "content": "",
+            "embeds": [{
+                "title": "Roster changes",
+                "fields": [
+                    {
+                        "name": "Added",
+                        "value": field(added)
+                    },
+                    {
+                        "name": "Removed",
+                        "value": field(removed)
+                    }
+                ]
+            }]
+        }))
+        .send()
+        .await;
+    match resp {
+        Ok(resp) if !resp.status().is_success() => warn!(
+            "Got status {} from Discord posting roster changes",
+            resp.status().as_u16()
+        ),
+        Err(e) => error!("Could not post roster changes: {e}"),
+        _ => {}
+    }
+}
+
 /// Update the activity for a single controller.
 ///
 /// In a separate function to easily use the `?` operator.
 async fn update_single_activity(
     config: &Config,
     db: &SqlitePool,
-    five_months_ago: &str,
+    activity_start: &str,
     cid: u32,
 ) -> Result<()> {
     /*
-     * Get the last 5 months of the controller's activity.
+     * Get the controller's activity since the start of the oldest month kept.
      *
      * I'm not (currently) worried about pagination as even the facility's most
      * active controllers don't have enough sessions in this time range to go over
      * the endpoint's single-page response limit.
      */
-    let sessions = rest_api::get_atc_sessions(cid as u64, None, None, Some(five_months_ago), None)
-        .await
-        .with_context(|| format!("Processing CID {cid}"))?;
+    let sessions = api_metrics::track(
+        "vatsim_atc_sessions",
+        rest_api::get_atc_sessions(cid as u64, None, None, Some(activity_start), None),
+    )
+    .await
+    .with_context(|| format!("Processing CID {cid}"))?;
+    // note when usage-tracked certifications were last worked
+    let session_starts: Vec<(&str, &str)> = sessions
+        .results
+        .iter()
+        .map(|session| (session.callsign.as_str(), session.start.as_str()))
+        .collect();
+    let last_used: Vec<(String, DateTime<Utc>)> =
+        certification_last_used(&config.training.certification_usage, &session_starts)
+            .into_iter()
+            .map(|(name, used)| (name.to_owned(), used))
+            .collect();
     // group the controller's activity by month
     let mut seconds_map: HashMap<String, f32> = HashMap::new();
     for session in sessions.results {
@@ -145,16 +494,12 @@ async fn update_single_activity(
 
     // transaction for the ~6 queries
     let mut tx = db.begin().await?;
-    // clear the controller's existing records in prep for replacement
-    sqlx::query(sql::DELETE_ACTIVITY_FOR_CID)
-        .bind(cid)
-        .execute(&mut *tx)
-        .await
-        .with_context(|| format!("Processing CID {cid}"))?;
     // for each relevant month, store their total controlled minutes in the DB
-    for (month, seconds) in seconds_map {
+    let months: Vec<&String> = seconds_map.keys().collect();
+    let months = serde_json::to_string(&months)?;
+    for (month, seconds) in &seconds_map {
         let minutes = (seconds / 60.0).round() as u32;
-        sqlx::query(sql::INSERT_INTO_ACTIVITY)
+        sqlx::query(sql::UPSERT_ACTIVITY)
             .bind(cid)
             .bind(month)
             .bind(minutes)
@@ -162,31 +507,100 @@ async fn update_single_activity(
             .await
             .with_context(|| format!("Processing CID {cid}"))?;
     }
+    for (name, used) in last_used {
+        sqlx::query(sql::UPDATE_CERTIFICATION_LAST_USED)
+            .bind(used)
+            .bind(cid)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Processing CID {cid}"))?;
+    }
+    // drop months they no longer have activity in
+    sqlx::query(sql::DELETE_ACTIVITY_NOT_IN)
+        .bind(cid)
+        .bind(months)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Processing CID {cid}"))?;
     // commit the controller's changes
     tx.commit().await?;
 
     Ok(())
 }
 
+/// Act on the messages the site has queued for this binary.
+///
+/// Each message is deleted once handled, whether or not handling it succeeded,
+/// so that a bad message can't block the queue.
+async fn process_ipc_messages(
+    config: &Config,
+    db: &SqlitePool,
+    activity_lock: &Mutex<()>,
+) -> Result<()> {
+    let messages: Vec<IpcMessage> = sqlx::query_as(sql::GET_IPC_MESSAGES).fetch_all(db).await?;
+    for message in messages {
+        debug!("Processing IPC message {}: {}", message.id, message.action);
+        let result = match message.action.as_str() {
+            IPC_ACTIVITY_SYNC => {
+                info!("Updating activity, as requested from the site");
+                let _guard = activity_lock.lock().await;
+                update_activity(config, db).await
+            }
+            IPC_VATUSA_SYNC => match message.data.parse::<u32>() {
+                Ok(cid) => sync_single_controller(config, db, cid).await,
+                Err(_) => Err(anyhow!("Invalid CID '{}'", message.data)),
+            },
+            _ => {
+                warn!("Unknown IPC action: {}", message.action);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            error!(
+                "Error handling IPC message {} ({}): {e}",
+                message.id, message.action
+            );
+        }
+        sqlx::query(sql::DELETE_IPC_MESSAGE)
+            .bind(message.id)
+            .execute(db)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Update a single controller already on the roster with their data from VATUSA.
+async fn sync_single_controller(config: &Config, db: &SqlitePool, cid: u32) -> Result<()> {
+    let existing: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(db)
+        .await?;
+    if !existing.is_some_and(|c| c.is_on_roster) {
+        warn!("Skipping VATUSA sync of {cid}, who isn't on the roster");
+        return Ok(());
+    }
+    let controller = get_controller_info(cid).await?;
+    update_controller_record(config, db, &controller).await?;
+    info!("Synced {cid} from VATUSA");
+    Ok(())
+}
+
 /// Update all controllers' stored activity data with data from VATSIM.
 ///
 /// For each controller in the DB, their activity data will be cleared,
 /// and then (for on-roster controllers) fetched and stored in the DB as
 /// part of a transaction.
 async fn update_activity(config: &Config, db: &SqlitePool) -> Result<()> {
-    // prep cids for on-roster controllers and a 5-month-ago timestamp that the API recognizes
+    // prep cids for on-roster controllers and the start of the oldest month to keep
     let controllers = sqlx::query(sql::GET_ALL_ROSTER_CONTROLLER_CIDS)
         .fetch_all(db)
         .await?;
-    let five_months_ago = chrono::Utc::now()
-        .checked_sub_months(Months::new(5))
-        .unwrap()
-        .format("%Y-%m-%d")
-        .to_string();
+    let activity_start = activity_fetch_start(chrono::Utc::now());
     for row in controllers {
         let cid: u32 = row.try_get("cid")?;
         debug!("Getting activity for {cid}");
-        if let Err(e) = update_single_activity(config, db, &five_months_ago, cid).await {
+        if let Err(e) = update_single_activity(config, db, &activity_start, cid).await {
             error!("Error updating activity for {cid}: {e}");
         }
         // wait a second to be nice to the VATSIM API
@@ -195,6 +609,459 @@ async fn update_activity(config: &Config, db: &SqlitePool) -> Result<()> {
     Ok(())
 }
 
+/// The date a reminder was last sent for, kept in the kvs table under the key.
+async fn get_reminded_for(db: &SqlitePool, key: &str) -> Result<Option<DateTime<Utc>>> {
+    let value: Option<String> = sqlx::query_scalar(sql::GET_KVS_ENTRY)
+        .bind(key)
+        .fetch_optional(db)
+        .await?;
+    Ok(value
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|date| date.with_timezone(&Utc)))
+}
+
+/// Record that a reminder was sent for the date.
+async fn set_reminded_for(db: &SqlitePool, key: &str, date: DateTime<Utc>) -> Result<()> {
+    sqlx::query(sql::UPSERT_KVS_ENTRY)
+        .bind(key)
+        .bind(date.to_rfc3339())
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Track expiry dates for certifications with recurrency requirements.
+///
+/// Also emails the controller about each of their certifications that's
+/// within the configured reminder window. The expiry date each certification
+/// was reminded about is kept in the kvs table, so it's reminded of once per
+/// date however often this runs.
+async fn update_certification_expiry(config: &Config, db: &SqlitePool) -> Result<()> {
+    let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS)
+        .fetch_all(db)
+        .await?;
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(db)
+        .await?;
+    let reminder_days = config.training.expiry_reminder_days;
+    let now = Utc::now();
+
+    for cert in certifications {
+        let valid_until = certification_valid_until(config, &cert);
+        if valid_until != cert.valid_until {
+            sqlx::query(sql::UPDATE_CERTIFICATION_VALID_UNTIL)
+                .bind(valid_until)
+                .bind(cert.id)
+                .execute(db)
+                .await?;
+        }
+        let valid_until = match valid_until {
+            Some(until) => until,
+            None => continue,
+        };
+        let reminder_key = format!("certification_expiry_reminder_{}", cert.id);
+        let reminded_for = get_reminded_for(db, &reminder_key).await?;
+        if !reminder_due(valid_until, now, reminder_days, reminded_for) {
+            continue;
+        }
+
+        let controller = match controllers.iter().find(|c| c.cid == cert.cid) {
+            Some(controller) => controller,
+            None => continue,
+        };
+        let email: Option<String> = sqlx::query_scalar(sql::GET_CONTROLLER_EMAIL)
+            .bind(cert.cid)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+        let email = match email.filter(|email| !email.is_empty()) {
+            Some(email) => email,
+            None => {
+                debug!(
+                    "No email for {} to remind of expiring certification",
+                    cert.cid
+                );
+                continue;
+            }
+        };
+        let body = format!(
+            "Hi {},\n\nYour {} certification expires on {}. Reach out to the training staff to renew it before then.",
+            controller.first_name,
+            cert.name,
+            valid_until.format("%m/%d/%Y")
+        );
+        if let Err(e) = send_mail_raw(
+            config,
+            &email,
+            &format!("{} certification reminder", config.facility.site_name()),
+            &body,
+        )
+        .await
+        {
+            error!(
+                "Error sending certification expiry reminder to {}: {e}",
+                cert.cid
+            );
+            continue;
+        }
+        set_reminded_for(db, &reminder_key, valid_until).await?;
+        record_log(
+            db,
+            LogCategory::Info,
+            Some(cert.cid),
+            &format!(
+                "Reminded that the {} certification expires on {}",
+                cert.name,
+                valid_until.format("%m/%d/%Y")
+            ),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Email controllers whose certifications will lapse from not being used.
+///
+/// Like the recurrency reminders, each lapse date is only warned about once,
+/// when it's within the reminder window.
+async fn send_certification_usage_warnings(config: &Config, db: &SqlitePool) -> Result<()> {
+    if config.training.certification_usage.is_empty() {
+        return Ok(());
+    }
+    let now = Utc::now();
+    let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS)
+        .fetch_all(db)
+        .await?;
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(db)
+        .await?;
+    for cert in certifications {
+        let unused_until = match certification_unused_until(config, &cert) {
+            Some(until) => until,
+            None => continue,
+        };
+        let reminder_key = format!("certification_usage_reminder_{}", cert.id);
+        let reminded_for = get_reminded_for(db, &reminder_key).await?;
+        if !reminder_due(
+            unused_until,
+            now,
+            config.training.expiry_reminder_days,
+            reminded_for,
+        ) {
+            continue;
+        }
+        let controller = match controllers.iter().find(|c| c.cid == cert.cid) {
+            Some(controller) => controller,
+            None => continue,
+        };
+        let email: Option<String> = sqlx::query_scalar(sql::GET_CONTROLLER_EMAIL)
+            .bind(cert.cid)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+        let email = match email.filter(|email| !email.is_empty()) {
+            Some(email) => email,
+            None => {
+                debug!("No email for {} to warn of unused certification", cert.cid);
+                continue;
+            }
+        };
+        let body = format!(
+            "Hi {},\n\nYour {} certification will lapse on {} unless you work one of its positions before then.",
+            controller.first_name,
+            cert.name,
+            unused_until.format("%m/%d/%Y")
+        );
+        if let Err(e) = send_mail_raw(
+            config,
+            &email,
+            &format!("{} certification reminder", config.facility.site_name()),
+            &body,
+        )
+        .await
+        {
+            error!(
+                "Error sending unused certification warning to {}: {e}",
+                cert.cid
+            );
+            continue;
+        }
+        set_reminded_for(db, &reminder_key, unused_until).await?;
+        record_log(
+            db,
+            LogCategory::Info,
+            Some(cert.cid),
+            &format!(
+                "Warned that the {} certification lapses on {} if unused",
+                cert.name,
+                unused_until.format("%m/%d/%Y")
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Email controllers who will be short of the activity requirement at the end
+/// of the month unless they control more.
+///
+/// The last reminder for each controller is kept in the kvs table so they
+/// aren't sent one every day.
+async fn send_activity_reminders(config: &Config, db: &SqlitePool) -> Result<()> {
+    let now = Utc::now();
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(db)
+        .await?;
+    let activity: Vec<Activity> = sqlx::query_as(sql::GET_ALL_ACTIVITY).fetch_all(db).await?;
+    for controller in controllers {
+        if is_on_loa(&controller, now) || is_new_controller(&config.currency, &controller, now) {
+            continue;
+        }
+        let cid = controller.cid;
+        let minutes = quarterly_minutes(&activity, cid, now, 1, config.currency.quarter)[0];
+        let key = format!("activity_reminder_{cid}");
+        let last_reminded: Option<String> = sqlx::query_scalar(sql::GET_KVS_ENTRY)
+            .bind(&key)
+            .fetch_optional(db)
+            .await?;
+        let last_reminded = last_reminded
+            .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+            .map(|date| date.with_timezone(&Utc));
+        if !should_remind_of_activity(&config.currency, minutes, now, last_reminded) {
+            continue;
+        }
+        let email: Option<String> = sqlx::query_scalar(sql::GET_CONTROLLER_EMAIL)
+            .bind(cid)
+            .fetch_optional(db)
+            .await?
+            .flatten();
+        let email = match email.filter(|email| !email.is_empty()) {
+            Some(email) => email,
+            None => {
+                debug!("No email for {cid} to send activity reminder");
+                continue;
+            }
+        };
+        let body = format!(
+            "Hi {},\n\nYou've controlled {minutes} of the {QUARTERLY_ACTIVITY_MINUTES} minutes needed this quarter. \
+            Please get some more time on the scopes before the end of the month to stay current.",
+            controller.first_name
+        );
+        if let Err(e) = send_mail_raw(
+            config,
+            &email,
+            &format!("{} activity reminder", config.facility.site_name()),
+            &body,
+        )
+        .await
+        {
+            error!("Error sending activity reminder to {cid}: {e}");
+            continue;
+        }
+        sqlx::query(sql::UPSERT_KVS_ENTRY)
+            .bind(&key)
+            .bind(now.to_rfc3339())
+            .execute(db)
+            .await?;
+        record_log(db, LogCategory::Info, Some(cid), "Sent activity reminder").await?;
+    }
+    Ok(())
+}
+
+/// Delete files in the assets directory that no resource references.
+///
+/// Files modified in the last day are skipped so that an upload isn't removed
+/// before its resource has been saved.
+async fn clean_up_assets(db: &SqlitePool) -> Result<()> {
+    let assets_dir = Path::new("./assets");
+    if !assets_dir.exists() {
+        return Ok(());
+    }
+    let cutoff = std::time::SystemTime::now() - Duration::from_secs(60 * 60 * 24);
+    let mut file_names = Vec::new();
+    for entry in std::fs::read_dir(assets_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() || metadata.modified()? > cutoff {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            file_names.push(name.to_owned());
+        }
+    }
+    for name in find_orphaned_assets(db, &file_names).await? {
+        info!("Deleting orphaned asset {name}");
+        if let Err(e) = std::fs::remove_file(assets_dir.join(&name)) {
+            error!("Could not delete orphaned asset {name}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Compose the staff digest of recent and upcoming facility activity.
+async fn build_staff_digest(config: &Config, db: &SqlitePool) -> Result<String> {
+    let now = Utc::now();
+    let period = ChronoDuration::days(config.email.digest_days() as i64);
+    let since = now - period;
+    let until = now + period;
+
+    let visitor_requests: i64 = sqlx::query_scalar(sql::GET_VISITOR_REQ_COUNT_SINCE)
+        .bind(since)
+        .fetch_one(db)
+        .await?;
+    let feedback: i64 = sqlx::query_scalar(sql::GET_FEEDBACK_COUNT_SINCE)
+        .bind(since)
+        .fetch_one(db)
+        .await?;
+
+    // same quarter as the activity page, which isn't over yet
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(db)
+        .await?;
+    let activity: Vec<Activity> = sqlx::query_as(sql::GET_ALL_ACTIVITY).fetch_all(db).await?;
+    let low_activity: Vec<_> = controllers
+        .iter()
+        .filter_map(|controller| {
+            let minutes =
+                quarterly_minutes(&activity, controller.cid, now, 1, config.currency.quarter)[0];
+            activity_violation(&config.currency, controller, minutes, now)
+                .then_some((controller, minutes))
+        })
+        .collect();
+
+    let cid_name_map = get_controller_cids_and_names(db).await?;
+    let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS)
+        .fetch_all(db)
+        .await?;
+    let expiring: Vec<_> = certifications
+        .iter()
+        .filter(|cert| {
+            cert.valid_until
+                .is_some_and(|valid_until| valid_until >= now && valid_until < until)
+        })
+        .collect();
+
+    let solo_certs: Vec<SoloCert> = sqlx::query_as(sql::GET_ALL_SOLO_CERTS)
+        .fetch_all(db)
+        .await?;
+    let expiring_solo: Vec<_> = solo_certs
+        .iter()
+        .filter(|cert| cert.expiration_date >= now && cert.expiration_date < until)
+        .collect();
+
+    let events: Vec<Event> = sqlx::query_as(sql::GET_PUBLISHED_EVENTS_BETWEEN)
+        .bind(now)
+        .bind(until)
+        .fetch_all(db)
+        .await?;
+
+    let mut body = String::new();
+    writeln!(body, "Facility digest for {}", now.format("%m/%d/%Y"))?;
+    writeln!(body)?;
+    writeln!(body, "New visitor applications: {visitor_requests}")?;
+    writeln!(body, "Feedback submitted: {feedback}")?;
+    writeln!(body)?;
+    writeln!(
+        body,
+        "Controllers approaching an activity violation ({}):",
+        low_activity.len()
+    )?;
+    for (controller, minutes) in &low_activity {
+        writeln!(
+            body,
+            "  - {} {} ({}): {minutes} of {QUARTERLY_ACTIVITY_MINUTES} minutes this quarter",
+            controller.first_name, controller.last_name, controller.cid
+        )?;
+    }
+    writeln!(body)?;
+    writeln!(body, "Certifications expiring soon ({}):", expiring.len())?;
+    for cert in &expiring {
+        let name = cid_name_map
+            .get(&(cert.cid as u64))
+            .map(|s| format!("{} {}", s.0, s.1))
+            .unwrap_or(String::from("?"));
+        writeln!(
+            body,
+            "  - {name} ({}): {} on {}",
+            cert.cid,
+            cert.name,
+            cert.valid_until.unwrap().format("%m/%d/%Y")
+        )?;
+    }
+    writeln!(body)?;
+    writeln!(
+        body,
+        "Solo certifications expiring soon ({}):",
+        expiring_solo.len()
+    )?;
+    for cert in &expiring_solo {
+        let name = cid_name_map
+            .get(&(cert.cid as u64))
+            .map(|s| format!("{} {}", s.0, s.1))
+            .unwrap_or(String::from("?"));
+        writeln!(
+            body,
+            "  - {name} ({}): {} on {}",
+            cert.cid,
+            cert.position,
+            cert.expiration_date.format("%m/%d/%Y")
+        )?;
+    }
+    writeln!(body)?;
+    writeln!(body, "Upcoming events ({}):", events.len())?;
+    for event in &events {
+        writeln!(
+            body,
+            "  - {} on {}",
+            event.name,
+            event.start.format("%m/%d/%Y %H:%MZ")
+        )?;
+    }
+
+    Ok(body)
+}
+
+/// Key in the kvs table for when the staff digest was last sent.
+const STAFF_DIGEST_KEY: &str = "staff_digest_last_sent";
+
+/// Email the staff digest to each of the configured recipients, if it's due.
+///
+/// When it was last sent is kept in the kvs table so restarting the tasks
+/// doesn't send it again early. Returns whether it was sent.
+async fn send_staff_digest(config: &Config, db: &SqlitePool) -> Result<bool> {
+    let now = Utc::now();
+    let last_sent: Option<String> = sqlx::query_scalar(sql::GET_KVS_ENTRY)
+        .bind(STAFF_DIGEST_KEY)
+        .fetch_optional(db)
+        .await?;
+    let last_sent = last_sent
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|date| date.with_timezone(&Utc));
+    if !digest_due(last_sent, now, config.email.digest_days()) {
+        return Ok(false);
+    }
+    let body = build_staff_digest(config, db).await?;
+    for recipient in &config.email.digest_recipients {
+        if let Err(e) = send_mail_raw(
+            config,
+            recipient,
+            &format!("{} staff digest", config.facility.site_name()),
+            &body,
+        )
+        .await
+        {
+            error!("Error sending staff digest to {recipient}: {e}");
+        }
+    }
+    sqlx::query(sql::UPSERT_KVS_ENTRY)
+        .bind(STAFF_DIGEST_KEY)
+        .bind(now.to_rfc3339())
+        .execute(db)
+        .await?;
+    Ok(true)
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
@@ -203,7 +1070,7 @@ async fn main() {
     } else if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", "info");
     }
-    pretty_env_logger::init();
+    init_logging(cli.json_logs);
     debug!("Logging configured");
 
     debug!("Loading");
@@ -234,13 +1101,14 @@ async fn main() {
     info!("Starting tasks");
 
     let roster_handle = {
+        let config = config.clone();
         let db = db.clone();
         tokio::spawn(async move {
             debug!("Waiting 10 seconds before starting roster sync");
             time::sleep(time::Duration::from_secs(10)).await;
             loop {
                 info!("Querying roster");
-                match update_roster(&db).await {
+                match update_roster(&config, &db).await {
                     Ok(_) => {
                         info!("Roster update successful");
                     }
@@ -254,13 +1122,18 @@ async fn main() {
         })
     };
 
+    // held by whichever of the scheduled and requested activity syncs is running
+    let activity_lock = Arc::new(Mutex::new(()));
+
     let activity_handle = {
         let config = config.clone();
         let db = db.clone();
+        let activity_lock = activity_lock.clone();
         tokio::spawn(async move {
             debug!("Waiting 60 seconds before starting activity sync");
             time::sleep(time::Duration::from_secs(60)).await;
             loop {
+                let guard = activity_lock.lock().await;
                 info!("Updating activity");
                 match update_activity(&config, &db).await {
                     Ok(_) => {
@@ -270,14 +1143,177 @@ async fn main() {
                         error!("Error updating activity: {e}");
                     }
                 }
+                drop(guard);
                 debug!("Waiting 12 hours for next activity sync");
                 time::sleep(time::Duration::from_secs(60 * 60 * 12)).await;
             }
         })
     };
 
+    let ipc_handle = {
+        let config = config.clone();
+        let db = db.clone();
+        let activity_lock = activity_lock.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = process_ipc_messages(&config, &db, &activity_lock).await {
+                    error!("Error processing IPC messages: {e}");
+                }
+                time::sleep(time::Duration::from_secs(60)).await;
+            }
+        })
+    };
+
+    let certification_handle = {
+        let config = config.clone();
+        let db = db.clone();
+        tokio::spawn(async move {
+            debug!("Waiting 90 seconds before starting certification expiry check");
+            time::sleep(time::Duration::from_secs(90)).await;
+            loop {
+                info!("Checking certification expiry");
+                match update_certification_expiry(&config, &db).await {
+                    Ok(_) => {
+                        info!("Certification expiry check successful");
+                    }
+                    Err(e) => {
+                        error!("Error checking certification expiry: {e}");
+                    }
+                }
+                if let Err(e) = send_certification_usage_warnings(&config, &db).await {
+                    error!("Error sending unused certification warnings: {e}");
+                }
+                debug!("Waiting 24 hours for next certification expiry check");
+                time::sleep(time::Duration::from_secs(60 * 60 * 24)).await;
+            }
+        })
+    };
+
+    let reminder_handle = {
+        let config = config.clone();
+        let db = db.clone();
+        tokio::spawn(async move {
+            if config.currency.reminder_days == 0 {
+                debug!("Activity reminders disabled");
+                return;
+            }
+            debug!("Waiting 180 seconds before starting activity reminders");
+            time::sleep(time::Duration::from_secs(180)).await;
+            loop {
+                info!("Sending activity reminders");
+                match send_activity_reminders(&config, &db).await {
+                    Ok(_) => {
+                        info!("Activity reminders sent");
+                    }
+                    Err(e) => {
+                        error!("Error sending activity reminders: {e}");
+                    }
+                }
+                debug!("Waiting 24 hours for next activity reminders");
+                time::sleep(time::Duration::from_secs(60 * 60 * 24)).await;
+            }
+        })
+    };
+
+    let digest_handle = {
+        let config = config.clone();
+        let db = db.clone();
+        tokio::spawn(async move {
+            if config.email.digest_recipients.is_empty() {
+                debug!("No staff digest recipients configured");
+                return;
+            }
+            debug!("Waiting 120 seconds before starting staff digest");
+            time::sleep(time::Duration::from_secs(120)).await;
+            loop {
+                match send_staff_digest(&config, &db).await {
+                    Ok(true) => {
+                        info!("Staff digest sent");
+                    }
+                    Ok(false) => {
+                        debug!("Staff digest not due yet");
+                    }
+                    Err(e) => {
+                        error!("Error sending staff digest: {e}");
+                    }
+                }
+                debug!("Waiting 1 hour to check the staff digest again");
+                time::sleep(time::Duration::from_secs(60 * 60)).await;
+            }
+        })
+    };
+
+    let assets_handle = {
+        let db = db.clone();
+        tokio::spawn(async move {
+            debug!("Waiting 150 seconds before starting asset cleanup");
+            time::sleep(time::Duration::from_secs(150)).await;
+            loop {
+                info!("Cleaning up orphaned assets");
+                match clean_up_assets(&db).await {
+                    Ok(_) => {
+                        info!("Asset cleanup successful");
+                    }
+                    Err(e) => {
+                        error!("Error cleaning up assets: {e}");
+                    }
+                }
+                debug!("Waiting 24 hours for next asset cleanup");
+                time::sleep(time::Duration::from_secs(60 * 60 * 24)).await;
+            }
+        })
+    };
+
+    let atis_handle = {
+        let config = config.clone();
+        let db = db.clone();
+        tokio::spawn(async move {
+            loop {
+                match clean_up_atis(&config, &db, chrono::Utc::now()).await {
+                    Ok(removed) => {
+                        info!(
+                            "Removed {removed} ATIS entries older than {} hours",
+                            config.atis.retention_hours
+                        );
+                    }
+                    Err(e) => {
+                        error!("Error cleaning up ATIS entries: {e}");
+                    }
+                }
+                debug!("Waiting 5 minutes for next ATIS cleanup");
+                time::sleep(time::Duration::from_secs(60 * 5)).await;
+            }
+        })
+    };
+
+    let metrics_handle = tokio::spawn(async move {
+        loop {
+            time::sleep(time::Duration::from_secs(60 * 60)).await;
+            for (api, stats) in api_metrics::snapshot() {
+                info!(
+                    "API {api}: {} calls, {:.1}% failed ({} timeouts, {} 4xx, {} 5xx, {} other), {} ms average, {} ms max",
+                    stats.calls(),
+                    stats.failure_percent(),
+                    stats.timeouts,
+                    stats.client_errors,
+                    stats.server_errors,
+                    stats.other_failures,
+                    stats.average_millis(),
+                    stats.max_millis
+                );
+            }
+        }
+    });
+
     roster_handle.await.unwrap();
     activity_handle.await.unwrap();
+    ipc_handle.await.unwrap();
+    certification_handle.await.unwrap();
+    reminder_handle.await.unwrap();
+    digest_handle.await.unwrap();
+    assets_handle.await.unwrap();
+    atis_handle.await.unwrap();
+    metrics_handle.await.unwrap();
 
     db.close().await;
 }
@@ -3,7 +3,7 @@
 #![deny(clippy::all)]
 
 use anyhow::Result;
-use axum::{middleware as axum_middleware, response::Redirect, Router};
+use axum::{middleware as axum_middleware, Router};
 use clap::Parser;
 use log::{debug, error, info, warn};
 use mini_moka::sync::Cache;
@@ -21,8 +21,9 @@ use tower_http::timeout::TimeoutLayer;
 use tower_sessions::SessionManagerLayer;
 use tower_sessions_sqlx_store::SqliteStore;
 use vzdv::{
-    load_config, load_db,
-    shared::{self, AppState},
+    init_logging, load_config, load_db,
+    shared::{self, add_facility_globals, AppState, Config},
+    utils::{config_restart_changes, ping_webhook, webhook_problems},
 };
 
 /// vZDV website.
@@ -39,6 +40,14 @@ struct Cli {
     #[arg(short, long)]
     debug: bool,
 
+    /// Write logs as JSON lines
+    #[arg(long)]
+    json_logs: bool,
+
+    /// Check that Discord recognizes each configured webhook at startup
+    #[arg(long)]
+    check_webhooks: bool,
+
     /// Host to run on
     #[arg(long, default_value = "0.0.0.0")]
     host: String,
@@ -53,14 +62,16 @@ struct Cli {
 fn load_templates() -> Result<Environment<'static>> {
     let mut env = Environment::new();
     env.add_template("_layout", include_str!("../../templates/_layout.jinja"))?;
+    env.add_template(
+        "_pagination",
+        include_str!("../../templates/_pagination.jinja"),
+    )?;
     Ok(env)
 }
 
-/// Create all the endpoints and insert middleware.
-fn load_router(
-    sessions_layer: SessionManagerLayer<SqliteStore>,
-    env: &mut Environment,
-) -> Router<Arc<AppState>> {
+/// Create all the endpoints.
+fn load_router(env: &mut Environment, config: &Config) -> Router<Arc<AppState>> {
+    add_facility_globals(env, &config.facility);
     Router::new()
         .merge(vzdv::endpoints::router(env))
         .merge(vzdv::endpoints::homepage::router(env))
@@ -68,15 +79,32 @@ fn load_router(
         .merge(vzdv::endpoints::auth::router(env))
         .merge(vzdv::endpoints::airspace::router(env))
         .merge(vzdv::endpoints::facility::router(env))
-        .merge(vzdv::endpoints::admin::router(env))
+        .merge(vzdv::endpoints::admin::router(env, config))
         .merge(vzdv::endpoints::events::router(env))
+        .merge(vzdv::endpoints::news::router(env))
+        .merge(vzdv::endpoints::api::router(config))
+}
+
+/// Insert middleware around the endpoints.
+///
+/// Maintenance mode needs the app state, so this is done once the state is built.
+fn add_middleware(
+    router: Router<Arc<AppState>>,
+    sessions_layer: SessionManagerLayer<SqliteStore>,
+    state: Arc<AppState>,
+) -> Router<Arc<AppState>> {
+    router
         .layer(
             ServiceBuilder::new()
                 .layer(TimeoutLayer::new(Duration::from_secs(30)))
                 .layer(axum_middleware::from_fn(vzdv::middleware::logging))
-                .layer(sessions_layer),
+                .layer(sessions_layer)
+                .layer(axum_middleware::from_fn_with_state(
+                    state,
+                    vzdv::middleware::maintenance,
+                )),
         )
-        .fallback(|| async { Redirect::to("/404") })
+        .fallback(vzdv::endpoints::fallback)
 }
 
 // https://github.com/tokio-rs/axum/blob/main/examples/graceful-shutdown/src/main.rs
@@ -106,6 +134,45 @@ async fn shutdown_signal() {
     }
 }
 
+/// Reload the config from disk whenever the process gets a SIGHUP.
+///
+/// Settings that are only read at startup keep their current values until
+/// the next restart.
+#[cfg(unix)]
+async fn reload_config_on_hangup(state: Arc<AppState>, config_location: PathBuf) {
+    let mut hangups = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            error!("Could not install SIGHUP handler: {e}");
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        info!("Got SIGHUP, reloading config");
+        let mut config = match load_config(&config_location) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Could not reload config, keeping the current one: {e}");
+                continue;
+            }
+        };
+        let current = state.config();
+        for name in config_restart_changes(&current, &config) {
+            warn!("Config {name} changed; it needs a restart to take effect");
+        }
+        config.database = current.database.clone();
+        config.cache.capacity = current.cache.capacity;
+        config.resources.max_upload_mb = current.resources.max_upload_mb;
+        config.facility = current.facility.clone();
+        config.api = current.api.clone();
+        for problem in webhook_problems(&config.discord.webhooks) {
+            warn!("Config problem: {problem}");
+        }
+        state.set_config(config);
+        info!("Config reloaded");
+    }
+}
+
 /// Entrypoint.
 #[tokio::main]
 async fn main() {
@@ -115,7 +182,7 @@ async fn main() {
     } else if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", "tracing::span=warn,info");
     }
-    pretty_env_logger::init();
+    init_logging(cli.json_logs);
     debug!("Logging configured");
 
     debug!("Loading");
@@ -134,6 +201,20 @@ async fn main() {
             process::exit(1);
         }
     };
+    for problem in webhook_problems(&config.discord.webhooks) {
+        warn!("Config problem: {problem}");
+    }
+    if cli.check_webhooks {
+        for (name, url, _) in config.discord.webhooks.all() {
+            if url.is_empty() {
+                continue;
+            }
+            match ping_webhook(url).await {
+                Ok(_) => debug!("Webhook {name} is reachable"),
+                Err(e) => warn!("Webhook {name} could not be reached: {e}"),
+            }
+        }
+    }
     let db = match load_db(&config).await {
         Ok(db) => db,
         Err(e) => {
@@ -154,18 +235,18 @@ async fn main() {
             return;
         }
     };
-    let cache = Cache::new(10);
+    let cache = Cache::new(config.cache.capacity);
     debug!("Loaded");
 
     debug!("Setting up app");
-    let router = load_router(session_layer, &mut templates);
-    let app_state = Arc::new(AppState {
-        config,
-        db: db.clone(),
-        templates,
-        cache,
-    });
-    let app = router.with_state(app_state);
+    let router = load_router(&mut templates, &config);
+    let app_state = Arc::new(AppState::new(config, db.clone(), templates, cache));
+    #[cfg(unix)]
+    tokio::spawn(reload_config_on_hangup(
+        app_state.clone(),
+        config_location.clone(),
+    ));
+    let app = add_middleware(router, session_layer, app_state.clone()).with_state(app_state);
     let assets_dir = Path::new("./assets");
     if !assets_dir.exists() {
         if let Err(e) = fs::create_dir(assets_dir) {
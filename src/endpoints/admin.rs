@@ -1,23 +1,50 @@
 use crate::{
+    endpoints::api::ApiEvent,
+    middleware::in_maintenance_mode,
     shared::{
-        sql::{self, Controller, Feedback},
-        AppError, AppState, UserInfo, SESSION_USER_INFO_KEY,
+        sql::{
+            self, Activity, Certification, Controller, Event, EventPosition, EventRegistration,
+            Feedback, FeedbackCount, LogEntry, NewsEntry, RatingHistorySnapshot, Resource,
+            SoloCert, SopInitial, StaffNote, VisitorApplication,
+        },
+        AppError, AppState, CacheEntry, Config, UserInfo, IPC_ACTIVITY_SYNC, IPC_VATUSA_SYNC,
+        KVS_MAINTENANCE_MODE, SESSION_USER_INFO_KEY,
+    },
+    utils::{
+        asset_file_name, build_controller_timeline, certification_valid_until,
+        consecutive_inactive_quarters, controller_changes, csv_field,
+        email::send_mail_raw,
+        escape_xml, event_import_url, feedback_contact_blocked, feedback_stats, flashed_messages,
+        format_log_line, get_controller_cids_and_names, is_new_controller, is_on_loa,
+        pagination::{PageQuery, Paginated, Pagination},
+        post_feedback_to_discord, quarterly_minutes, read_last_lines, reconcile_solo_certs,
+        record_log, resource_category_order, roles_to_set, set_operating_initials,
+        solo_cert_matches, sop_coverage, upload_allowed, validate_banner_url, vatusa,
+        visitor_certification_checklist, with_role, FeedbackStats, LogCategory,
+        GENERAL_HTTP_CLIENT, QUARTERLY_ACTIVITY_MINUTES,
     },
-    utils::{flashed_messages, GENERAL_HTTP_CLIENT},
 };
+use anyhow::Result;
 use axum::{
-    extract::State,
+    extract::{multipart::MultipartError, DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, StatusCode},
     response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
-    Form, Router,
+    Form, Json, Router,
 };
-use log::{error, warn};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use log::{error, info, warn};
 use minijinja::{context, Environment};
-use serde::Deserialize;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use tower_sessions::Session;
 
+/// Certification values that training staff can set.
+const CERTIFICATION_VALUES: [&str; 3] = ["Training", "Solo", "Certified"];
+
 /// Access control by staff position.
 ///
 /// ## Limitations
@@ -32,7 +59,7 @@ use tower_sessions::Session;
 /// FE, AFE, and AWM are not granted any special access.
 ///
 #[allow(unused)]
-enum StaffRequirement {
+pub(crate) enum StaffRequirement {
     /// Training staff (Mentors, Instructors, TA) and admins (ATM, DATM, WM)
     TrainingStaff,
     /// Events staff (EC, AEC) and admins (ATM, DATM, WM)
@@ -64,7 +91,7 @@ impl StaffRequirement {
 /// still actually a staff member at the time of making the request.
 ///
 /// Also asserts that `user_info.is_some()`, so later unwrapping it is safe.
-async fn reject_if_not_staff(
+pub(crate) async fn reject_if_not_staff(
     state: &Arc<AppState>,
     user_info: &Option<UserInfo>,
     staff_type: StaffRequirement,
@@ -115,12 +142,31 @@ async fn reject_if_not_staff(
     }
 }
 
+/// Number of feedback entries shown per page when reviewing.
+const FEEDBACK_PAGE_SIZE: u32 = 20;
+
+#[derive(Debug, Default, Deserialize)]
+struct FeedbackReviewQuery {
+    /// "archived" for archived feedback, "uncredited" for pending feedback
+    /// without a controller, otherwise pending feedback is shown
+    #[serde(default)]
+    view: String,
+    /// Text to match against the controller and position
+    #[serde(default)]
+    search: String,
+    /// "oldest" to show the oldest feedback first
+    #[serde(default)]
+    sort: String,
+}
+
 /// Page for managing controller feedback.
 ///
 /// Feedback must be reviewed by staff before being posted to Discord.
 async fn page_feedback(
     State(state): State<Arc<AppState>>,
     session: Session,
+    Query(query): Query<FeedbackReviewQuery>,
+    Query(page_query): Query<PageQuery>,
 ) -> Result<Response, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
     if let Some(redirect) =
@@ -128,23 +174,88 @@ async fn page_feedback(
     {
         return Ok(redirect);
     }
-    let template = state.templates.get_template("admin/feedback")?;
-    let pending_feedback: Vec<Feedback> = sqlx::query_as(sql::GET_ALL_PENDING_FEEDBACK)
+    let archived = query.view == "archived";
+    let uncredited = query.view == "uncredited";
+    let action = if archived { "archive" } else { "pending" };
+    let search = query.search.trim();
+    let oldest_first = query.sort == "oldest";
+    let total: u32 = sqlx::query_scalar(sql::COUNT_FEEDBACK_FOR_REVIEW)
+        .bind(action)
+        .bind(search)
+        .bind(uncredited)
+        .fetch_one(&state.db)
+        .await?;
+    let pagination = Pagination::new(&page_query, FEEDBACK_PAGE_SIZE, total);
+    let feedback: Vec<Feedback> = sqlx::query_as(sql::GET_FEEDBACK_FOR_REVIEW_PAGE)
+        .bind(action)
+        .bind(search)
+        .bind(oldest_first)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .bind(uncredited)
         .fetch_all(&state.db)
         .await?;
+    let feedback = Paginated::new(feedback, pagination);
+    let template = state.templates.get_template("admin/feedback")?;
     let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
     let rendered = template.render(context! {
         user_info,
         flashed_messages,
-        pending_feedback,
+        feedback,
+        archived,
+        uncredited,
+        search,
+        oldest_first,
     })?;
     Ok(Html(rendered).into_response())
 }
 
+/// Number of positions shown in the feedback stats' ranking.
+const FEEDBACK_STATS_TOP_POSITIONS: usize = 10;
+
+/// Page of aggregate feedback numbers: ratings, volume by month, and positions.
+///
+/// The numbers are computed over all feedback and cached.
+async fn page_feedback_stats(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let cache_key = "FEEDBACK_STATS";
+    let cached = state.cache.get(&cache_key).filter(|cached| {
+        (Instant::now() - cached.inserted).as_secs() < state.config().cache.ttl(cache_key)
+    });
+    let stats: FeedbackStats = match cached {
+        Some(cached) => serde_json::from_str(&cached.data)?,
+        None => {
+            let counts: Vec<FeedbackCount> = sqlx::query_as(sql::GET_FEEDBACK_COUNTS)
+                .fetch_all(&state.db)
+                .await?;
+            let stats = feedback_stats(&counts, FEEDBACK_STATS_TOP_POSITIONS);
+            state
+                .cache
+                .insert(cache_key, CacheEntry::new(serde_json::to_string(&stats)?));
+            stats
+        }
+    };
+    let max_month = stats.by_month.iter().map(|month| month.total).max();
+    let template = state.templates.get_template("admin/feedback_stats")?;
+    let rendered = template.render(context! { user_info, stats, max_month })?;
+    Ok(Html(rendered).into_response())
+}
+
 #[derive(Debug, Deserialize)]
 struct FeedbackReviewForm {
     id: u32,
     action: String,
+    /// CID of the controller uncredited or misidentified feedback is for
+    #[serde(default)]
+    controller: String,
 }
 
 /// Handler for staff members taking action on feedback.
@@ -189,35 +300,123 @@ async fn post_feedback_form_handle(
                 "Feedback deleted",
             )
             .await?;
+        } else if feedback_form.action == "Attribute" {
+            let controller: Option<Controller> =
+                match feedback_form.controller.trim().parse::<u32>() {
+                    Ok(cid) => {
+                        sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+                            .bind(cid)
+                            .fetch_optional(&state.db)
+                            .await?
+                    }
+                    Err(_) => None,
+                };
+            let controller = match controller {
+                Some(controller) => controller,
+                None => {
+                    flashed_messages::push_flashed_message(
+                        session,
+                        flashed_messages::FlashedMessageLevel::Error,
+                        "Enter the CID of a known controller",
+                    )
+                    .await?;
+                    return Ok(Redirect::to("/admin/feedback?view=uncredited").into_response());
+                }
+            };
+            sqlx::query(sql::UPDATE_FEEDBACK_CONTROLLER)
+                .bind(controller.cid.to_string())
+                .bind(feedback_form.id)
+                .execute(&state.db)
+                .await?;
+            record_log(
+                &state.db,
+                LogCategory::Audit,
+                Some(controller.cid),
+                &format!(
+                    "Feedback {} attributed to {} by {}",
+                    feedback.id,
+                    controller.cid,
+                    user_info.unwrap().cid
+                ),
+            )
+            .await?;
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Success,
+                "Feedback attributed; it's now with the rest of the pending feedback",
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/feedback?view=uncredited").into_response());
+        } else if feedback_form.action == "Reassign" {
+            let controller: Option<Controller> =
+                match feedback_form.controller.trim().parse::<u32>() {
+                    Ok(cid) => {
+                        sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+                            .bind(cid)
+                            .fetch_optional(&state.db)
+                            .await?
+                    }
+                    Err(_) => None,
+                };
+            let controller = match controller {
+                Some(controller) => controller,
+                None => {
+                    flashed_messages::push_flashed_message(
+                        session,
+                        flashed_messages::FlashedMessageLevel::Error,
+                        "Enter the CID of a known controller",
+                    )
+                    .await?;
+                    return Ok(Redirect::to("/admin/feedback").into_response());
+                }
+            };
+            sqlx::query(sql::UPDATE_FEEDBACK_CONTROLLER)
+                .bind(controller.cid.to_string())
+                .bind(feedback_form.id)
+                .execute(&state.db)
+                .await?;
+            let message = format!(
+                "Feedback {} reassigned from {} to {} by {}",
+                feedback.id,
+                feedback.controller,
+                controller.cid,
+                user_info.unwrap().cid
+            );
+            if let Ok(previous_cid) = feedback.controller.parse::<u32>() {
+                if previous_cid != controller.cid {
+                    record_log(&state.db, LogCategory::Audit, Some(previous_cid), &message).await?;
+                }
+            }
+            record_log(
+                &state.db,
+                LogCategory::Audit,
+                Some(controller.cid),
+                &message,
+            )
+            .await?;
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Success,
+                &format!(
+                    "Feedback reassigned to {} {}",
+                    controller.first_name, controller.last_name
+                ),
+            )
+            .await?;
         } else if feedback_form.action == "Post to Discord" {
-            GENERAL_HTTP_CLIENT
-                .post(&state.config.discord.webhooks.feedback)
-                .json(&json!({
-                    "content": "",
-                    "embeds": [{
-                        "title": "Feedback received",
-                        "fields": [
-                            {
-                                "name": "Controller",
-                                "value": feedback.controller
-                            },
-                            {
-                                "name": "Position",
-                                "value": feedback.position
-                            },
-                            {
-                                "name": "Rating",
-                                "value": feedback.rating
-                            },
-                            {
-                                "name": "Comments",
-                                "value": feedback.comments
-                            }
-                        ]
-                    }]
-                }))
-                .send()
+            let blocked: Vec<Controller> = sqlx::query_as(sql::GET_NO_FEEDBACK_CONTACT_CONTROLLERS)
+                .fetch_all(&state.db)
                 .await?;
+            if feedback_contact_blocked(&blocked, &feedback.controller) {
+                flashed_messages::push_flashed_message(
+                    session,
+                    flashed_messages::FlashedMessageLevel::Error,
+                    "That controller isn't being contacted about feedback; archive it instead",
+                )
+                .await?;
+                return Ok(Redirect::to("/admin/feedback").into_response());
+            }
+            post_feedback_to_discord(&state.config(), &feedback).await?;
             sqlx::query(sql::UPDATE_FEEDBACK_TAKE_ACTION)
                 .bind(user_info.unwrap().cid)
                 .bind("post")
@@ -244,38 +443,3197 @@ async fn post_feedback_form_handle(
     Ok(Redirect::to("/admin/feedback").into_response())
 }
 
-/**
- * TODO manage a controller
- *
- * Things to do:
- *  - set controller rating
- *  - add to / remove from the roster
- *  - add / remove certifications
- *  - add / remove staff ranks (incl. mentor and assoc. positions)
- *  - add training note (do it on this site, then post to VATUSA)
- *
- * TODO allow managing the roster
- * TODO allow creating and modifying events
- * TODO allow managing visitor requests
- */
+/// Page for managing facility news.
+async fn page_news(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let template = state.templates.get_template("admin/news")?;
+    let news: Vec<NewsEntry> = sqlx::query_as(sql::GET_ALL_NEWS)
+        .fetch_all(&state.db)
+        .await?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        news,
+    })?;
+    Ok(Html(rendered).into_response())
+}
 
-/// This file's routes and templates.
-pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
-    templates
-        .add_template(
-            "admin/feedback",
-            include_str!("../../templates/admin/feedback.jinja"),
+#[derive(Debug, Deserialize)]
+struct NewNewsForm {
+    title: String,
+    body: String,
+}
+
+/// Handler for staff members posting a new news entry.
+async fn post_news_create(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(news_form): Form<NewNewsForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    sqlx::query(sql::INSERT_INTO_NEWS)
+        .bind(news_form.title)
+        .bind(news_form.body)
+        .bind(sqlx::types::chrono::Utc::now())
+        .bind(user_info.unwrap().cid)
+        .execute(&state.db)
+        .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "News posted",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/news").into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteNewsForm {
+    id: u32,
+}
+
+/// Handler for staff members deleting a news entry.
+async fn post_news_delete(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(news_form): Form<DeleteNewsForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    sqlx::query(sql::DELETE_FROM_NEWS)
+        .bind(news_form.id)
+        .execute(&state.db)
+        .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "News deleted",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/news").into_response())
+}
+
+/// Page for viewing archived events.
+///
+/// Archived events are hidden from the site's listings, but their
+/// positions and registrations are kept for history.
+async fn page_archived_events(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let template = state.templates.get_template("admin/archived_events")?;
+    let events: Vec<Event> = sqlx::query_as(sql::GET_ARCHIVED_EVENTS)
+        .fetch_all(&state.db)
+        .await?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        events,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct EventArchiveForm {
+    action: String,
+}
+
+/// Handler for staff members archiving or restoring an event.
+///
+/// This is the normal way to "delete" an event.
+async fn post_event_archive(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    Form(archive_form): Form<EventArchiveForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let archived = archive_form.action == "Archive";
+    let result = sqlx::query(sql::UPDATE_EVENT_ARCHIVED)
+        .bind(archived)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    let (level, message) = if result.rows_affected() == 0 {
+        (
+            flashed_messages::FlashedMessageLevel::Error,
+            "Event not found",
         )
-        .unwrap();
-    templates.add_filter("nice_date", |date: String| {
-        chrono::DateTime::parse_from_rfc3339(&date)
-            .unwrap()
-            .format("%m/%d/%Y %H:%M:%S")
-            .to_string()
-    });
+    } else if archived {
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            "Event archived",
+        )
+    } else {
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            "Event restored",
+        )
+    };
+    flashed_messages::push_flashed_message(session, level, message).await?;
+    Ok(Redirect::to("/admin/events/archived").into_response())
+}
 
-    Router::new()
-        .route("/admin/feedback", get(page_feedback))
-        .route("/admin/feedback", post(post_feedback_form_handle))
-    // .route("/admin/roster/:cid", get(page_controller))
+#[derive(Debug, Deserialize)]
+struct EventMinRatingForm {
+    /// Empty to allow any rating
+    min_rating: String,
+}
+
+/// Handler for event staff setting the lowest rating that can register for an event.
+async fn post_event_min_rating(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    Form(rating_form): Form<EventMinRatingForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let min_rating = match rating_form.min_rating.as_str() {
+        "" => None,
+        value => match value.parse::<i8>() {
+            Ok(rating) if (1..=12).contains(&rating) => Some(rating),
+            _ => {
+                flashed_messages::push_flashed_message(
+                    session,
+                    flashed_messages::FlashedMessageLevel::Error,
+                    "Unknown rating",
+                )
+                .await?;
+                return Ok(Redirect::to(&format!("/events/{id}")).into_response());
+            }
+        },
+    };
+    sqlx::query(sql::UPDATE_EVENT_MIN_RATING)
+        .bind(min_rating)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    info!(
+        "{} set minimum rating of event {id} to {min_rating:?}",
+        user_info.unwrap().cid
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Rating requirement updated",
+    )
+    .await?;
+    Ok(Redirect::to(&format!("/events/{id}")).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct EventRegistrationForm {
+    action: String,
+}
+
+/// Handler for event staff opening or closing registration for an event.
+async fn post_event_registration(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    Form(registration_form): Form<EventRegistrationForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let open = registration_form.action == "Open registration";
+    let result = sqlx::query(sql::UPDATE_EVENT_REGISTRATION_OPEN)
+        .bind(open)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    let (level, message) = if result.rows_affected() == 0 {
+        (
+            flashed_messages::FlashedMessageLevel::Error,
+            "Event not found",
+        )
+    } else if open {
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            "Registration opened",
+        )
+    } else {
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            "Registration closed",
+        )
+    };
+    flashed_messages::push_flashed_message(session, level, message).await?;
+    Ok(Redirect::to(&format!("/events/{id}")).into_response())
+}
+
+/// Handler for admins permanently deleting an event.
+///
+/// Removes the event's positions and registrations as well.
+async fn post_event_delete(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let mut tx = state.db.begin().await?;
+    sqlx::query(sql::DELETE_EVENT_REGISTRATIONS)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(sql::DELETE_EVENT_POSITIONS)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(sql::DELETE_EVENT)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Event permanently deleted",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/events/archived").into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct DuplicateEventForm {
+    /// From a "datetime-local" input, in UTC
+    start: String,
+    /// From a "datetime-local" input, in UTC
+    end: String,
+}
+
+/// Handler for events staff copying an event to new times.
+///
+/// The copy is unpublished and has the same positions, but none of the
+/// original's registrations or assignments.
+async fn post_event_duplicate(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    Form(duplicate_form): Form<DuplicateEventForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let event: Option<Event> = sqlx::query_as(sql::GET_EVENT)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let event = match event {
+        Some(event) => event,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Event not found",
+            )
+            .await?;
+            return Ok(Redirect::to("/").into_response());
+        }
+    };
+    let parse = |s: &str| {
+        chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
+            .ok()
+            .map(|date| date.and_utc())
+    };
+    let (start, end) = match (parse(&duplicate_form.start), parse(&duplicate_form.end)) {
+        (Some(start), Some(end)) if start < end => (start, end),
+        _ => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "A start and a later end are required",
+            )
+            .await?;
+            return Ok(Redirect::to(&format!("/events/{id}")).into_response());
+        }
+    };
+    let cid = user_info.unwrap().cid;
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let positions: Vec<EventPosition> = sqlx::query_as(sql::GET_EVENT_POSITIONS)
+        .bind(id)
+        .fetch_all(&state.db)
+        .await?;
+
+    let mut tx = state.db.begin().await?;
+    let new_id = sqlx::query(sql::INSERT_EVENT)
+        .bind(controller.map(|c| c.id).unwrap_or_default())
+        .bind(&event.name)
+        .bind(start)
+        .bind(end)
+        .bind(&event.description)
+        .bind(&event.image_url)
+        .bind(event.min_rating)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+    for position in &positions {
+        sqlx::query(sql::INSERT_EVENT_POSITION)
+            .bind(new_id)
+            .bind(&position.name)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    info!("{cid} copied event {id} to new event {new_id}");
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Event copied; it's unpublished until reviewed",
+    )
+    .await?;
+    Ok(Redirect::to(&format!("/events/{new_id}")).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct SetPositionForm {
+    /// Blank to unassign the position
+    cid: String,
+    /// Checkbox; present when checked
+    notify: Option<String>,
+}
+
+/// Handler for events staff assigning a controller to an event position.
+///
+/// If enabled in the config, the assigned controller is emailed unless the
+/// staff member opted out for this assignment.
+async fn post_set_position(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path((event_id, position_id)): Path<(u32, u32)>,
+    Form(position_form): Form<SetPositionForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let redirect = Redirect::to(&format!("/events/{event_id}")).into_response();
+    let event: Option<Event> = sqlx::query_as(sql::GET_EVENT)
+        .bind(event_id)
+        .fetch_optional(&state.db)
+        .await?;
+    let position: Option<EventPosition> = sqlx::query_as(sql::GET_EVENT_POSITION)
+        .bind(position_id)
+        .fetch_optional(&state.db)
+        .await?;
+    let (event, position) = match (event, position) {
+        (Some(event), Some(position)) if position.event_id == event.id => (event, position),
+        _ => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Event position not found",
+            )
+            .await?;
+            return Ok(redirect);
+        }
+    };
+    let cid: Option<u32> = match position_form.cid.trim() {
+        "" => None,
+        cid => match cid.parse() {
+            Ok(cid) => Some(cid),
+            Err(_) => {
+                flashed_messages::push_flashed_message(
+                    session,
+                    flashed_messages::FlashedMessageLevel::Error,
+                    "Invalid CID",
+                )
+                .await?;
+                return Ok(redirect);
+            }
+        },
+    };
+    if let Some(cid) = cid {
+        let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+            .bind(cid)
+            .fetch_optional(&state.db)
+            .await?;
+        if controller.is_none() {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Controller not found",
+            )
+            .await?;
+            return Ok(redirect);
+        }
+    }
+    sqlx::query(sql::UPDATE_EVENT_POSITION_CONTROLLER)
+        .bind(cid)
+        .bind(position.id)
+        .execute(&state.db)
+        .await?;
+    let by = user_info.unwrap().cid;
+    info!(
+        "{by} set {} for event {} to {cid:?}",
+        position.name, event.id
+    );
+
+    if let Some(cid) = cid {
+        record_log(
+            &state.db,
+            LogCategory::Audit,
+            Some(cid),
+            &format!("Assigned to {} for '{}' by {by}", position.name, event.name),
+        )
+        .await?;
+        if state.config().email.notify_event_assignments && position_form.notify.is_some() {
+            let email: Option<String> = sqlx::query_scalar(sql::GET_CONTROLLER_EMAIL)
+                .bind(cid)
+                .fetch_optional(&state.db)
+                .await?
+                .flatten();
+            match email.filter(|email| !email.is_empty()) {
+                Some(email) => {
+                    let body = format!(
+                        "You've been assigned to {} for {}, from {} to {} UTC.",
+                        position.name,
+                        event.name,
+                        event.start.format("%m/%d/%Y %H:%M"),
+                        event.end.format("%m/%d/%Y %H:%M"),
+                    );
+                    let subject = format!("Position assignment for {}", event.name);
+                    match send_mail_raw(&state.config(), &email, &subject, &body).await {
+                        Ok(_) => {
+                            record_log(
+                                &state.db,
+                                LogCategory::Info,
+                                Some(cid),
+                                &format!(
+                                    "Emailed about {} assignment for '{}'",
+                                    position.name, event.name
+                                ),
+                            )
+                            .await?;
+                        }
+                        Err(e) => error!("Error emailing {cid} about event assignment: {e}"),
+                    }
+                }
+                None => warn!("No email for {cid} to notify of event assignment"),
+            }
+        }
+    }
+
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Position updated",
+    )
+    .await?;
+    Ok(redirect)
+}
+
+/// Page listing home controllers who have missed the activity requirement
+/// for multiple consecutive quarters and aren't on an LOA.
+///
+/// Activity is only stored for the last several months, so only the last 2
+/// quarters can be checked.
+async fn page_removal_report(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+
+    #[derive(Debug, Serialize)]
+    struct RemovalCandidate {
+        cid: u32,
+        name: String,
+        rating: &'static str,
+        loa_until: Option<DateTime<Utc>>,
+        quarters: Vec<u32>,
+        inactive_quarters: usize,
+        /// Positions of solo certs to clean up along with the controller
+        solo_certs: Vec<String>,
+    }
+
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let activity: Vec<Activity> = sqlx::query_as(sql::GET_ALL_ACTIVITY)
+        .fetch_all(&state.db)
+        .await?;
+    let solo_certs: Vec<SoloCert> = sqlx::query_as(sql::GET_ALL_SOLO_CERTS)
+        .fetch_all(&state.db)
+        .await?;
+    let now = Utc::now();
+    let candidates: Vec<_> = controllers
+        .iter()
+        .filter(|controller| controller.home_facility == state.config().facility.code)
+        .filter(|controller| !is_on_loa(controller, now))
+        .filter(|controller| !is_new_controller(&state.config().currency, controller, now))
+        .filter_map(|controller| {
+            let quarters = quarterly_minutes(
+                &activity,
+                controller.cid,
+                now,
+                2,
+                state.config().currency.quarter,
+            );
+            let inactive_quarters = consecutive_inactive_quarters(&quarters);
+            if inactive_quarters < 2 {
+                return None;
+            }
+            Some(RemovalCandidate {
+                cid: controller.cid,
+                name: format!("{} {}", controller.first_name, controller.last_name),
+                rating: Controller::rating_name(controller.rating),
+                loa_until: controller.loa_until,
+                quarters,
+                inactive_quarters,
+                solo_certs: solo_certs
+                    .iter()
+                    .filter(|cert| cert.cid == controller.cid)
+                    .map(|cert| cert.position.clone())
+                    .collect(),
+            })
+        })
+        .sorted_by(|a, b| Ord::cmp(&a.cid, &b.cid))
+        .collect();
+
+    let template = state.templates.get_template("admin/removal_report")?;
+    let rendered = template.render(context! {
+        user_info,
+        candidates,
+        minimum_minutes => QUARTERLY_ACTIVITY_MINUTES,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Page for admins to see what's in the server-side cache.
+async fn page_cache(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+
+    #[derive(Debug, Serialize)]
+    struct CacheKey {
+        key: &'static str,
+        age: u64,
+        ttl: u64,
+    }
+
+    let entries: Vec<_> = state
+        .cache
+        .iter()
+        .map(|entry| CacheKey {
+            key: entry.key(),
+            age: entry.value().inserted.elapsed().as_secs(),
+            ttl: state.config().cache.ttl(entry.key()),
+        })
+        .sorted_by(|a, b| Ord::cmp(&a.key, &b.key))
+        .collect();
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/cache")?;
+    let rendered = template.render(context! { user_info, flashed_messages, entries })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct CacheInvalidateForm {
+    /// Key to clear; all keys are cleared if missing
+    key: Option<String>,
+}
+
+/// Handler for admins clearing one or all keys in the server-side cache.
+async fn post_cache_invalidate(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(invalidate_form): Form<CacheInvalidateForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let cid = user_info.unwrap().cid;
+    match invalidate_form.key {
+        Some(key) => {
+            // keys are static strings, so find the matching one
+            let found = state
+                .cache
+                .iter()
+                .map(|entry| *entry.key())
+                .find(|existing| *existing == key);
+            if let Some(existing) = found {
+                state.cache.invalidate(&existing);
+                info!("{cid} cleared cache key {existing}");
+            }
+        }
+        None => {
+            state.cache.invalidate_all();
+            info!("{cid} cleared the cache");
+        }
+    }
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Cache cleared",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/cache").into_response())
+}
+
+/// Page for admins to turn maintenance mode on and off.
+async fn page_maintenance(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let enabled = in_maintenance_mode(&state).await;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/maintenance")?;
+    let rendered = template.render(context! { user_info, flashed_messages, enabled })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceForm {
+    enabled: Option<String>,
+}
+
+/// Handler for admins turning maintenance mode on and off.
+async fn post_maintenance(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(maintenance_form): Form<MaintenanceForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let enabled = maintenance_form.enabled.is_some();
+    sqlx::query(sql::UPSERT_KVS_ENTRY)
+        .bind(KVS_MAINTENANCE_MODE)
+        .bind(enabled.to_string())
+        .execute(&state.db)
+        .await?;
+    let message = format!(
+        "Maintenance mode turned {} by {}",
+        if enabled { "on" } else { "off" },
+        user_info.unwrap().cid
+    );
+    info!("{message}");
+    record_log(&state.db, LogCategory::Audit, None, &message).await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        if enabled {
+            "Maintenance mode is on"
+        } else {
+            "Maintenance mode is off"
+        },
+    )
+    .await?;
+    Ok(Redirect::to("/admin/maintenance").into_response())
+}
+
+/// Page for admins to set the order of the resource categories.
+async fn page_resource_categories(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let ordering = resource_category_order(&state.config(), &state.db).await?;
+    let in_use: Vec<String> = sqlx::query_scalar(sql::GET_RESOURCE_CATEGORIES_IN_USE)
+        .fetch_all(&state.db)
+        .await?;
+    let unlisted: Vec<_> = in_use
+        .into_iter()
+        .filter(|category| !ordering.contains(category))
+        .collect();
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/resource_categories")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        ordering,
+        unlisted,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceCategoriesForm {
+    /// One category per line, in order
+    ordering: String,
+}
+
+/// Handler for admins saving the order of the resource categories.
+async fn post_resource_categories(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(categories_form): Form<ResourceCategoriesForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let categories: Vec<_> = categories_form
+        .ordering
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .unique()
+        .collect();
+    let mut tx = state.db.begin().await?;
+    sqlx::query(sql::DELETE_RESOURCE_CATEGORIES)
+        .execute(&mut *tx)
+        .await?;
+    for (index, category) in categories.iter().enumerate() {
+        sqlx::query(sql::INSERT_RESOURCE_CATEGORY)
+            .bind(category)
+            .bind(index as u32)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    info!(
+        "{} set resource category order to {categories:?}",
+        user_info.unwrap().cid
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Category order saved",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/resources/categories").into_response())
+}
+
+/// Page for staff to add, change, and remove resources.
+async fn page_resources(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let resources: Vec<Resource> = sqlx::query_as(sql::GET_ALL_RESOURCES)
+        .fetch_all(&state.db)
+        .await?;
+    let resources: Vec<_> = resources
+        .into_iter()
+        .sorted_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)))
+        .collect();
+    let categories = resource_category_order(&state.config(), &state.db).await?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/resources")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        resources,
+        categories,
+        max_upload_mb => state.config().resources.max_upload_mb,
+        allowed_extensions => state.config().resources.allowed_extensions,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Download which roster controllers have initialed each SOP as a CSV.
+async fn get_sop_coverage_csv(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let resources: Vec<Resource> = sqlx::query_as(sql::GET_ALL_RESOURCES)
+        .fetch_all(&state.db)
+        .await?;
+    let sops: Vec<_> = resources
+        .into_iter()
+        .filter(|resource| {
+            state
+                .config()
+                .resources
+                .sop_categories
+                .contains(&resource.category)
+        })
+        .collect();
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let initials: Vec<SopInitial> = sqlx::query_as(sql::GET_ALL_SOP_INITIALS)
+        .fetch_all(&state.db)
+        .await?;
+
+    let mut csv = String::from("resource,cid,name,initialed,date\r\n");
+    for row in sop_coverage(&sops, &controllers, &initials) {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\r\n",
+            csv_field(row.resource),
+            row.cid,
+            csv_field(&row.name),
+            if row.initialed.is_some() { "yes" } else { "no" },
+            row.initialed
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        ));
+    }
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"sop_coverage.csv\"",
+            ),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Fields of the multipart form for creating or editing a resource.
+#[derive(Debug, Default)]
+struct ResourceUpload {
+    name: String,
+    category: String,
+    link: String,
+    /// Original file name, its declared content type, and its contents, if a file was attached
+    file: Option<(String, Option<String>, Vec<u8>)>,
+}
+
+/// Read the resource form from the request body.
+///
+/// Returns `None` if the body was larger than the configured upload limit.
+async fn read_resource_upload(
+    mut multipart: Multipart,
+) -> Result<Option<ResourceUpload>, AppError> {
+    fn too_large(e: &MultipartError) -> bool {
+        e.status() == StatusCode::PAYLOAD_TOO_LARGE
+    }
+
+    let mut upload = ResourceUpload::default();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) if too_large(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let field_name = field.name().unwrap_or_default().to_owned();
+        if field_name == "file" {
+            let file_name = field.file_name().unwrap_or_default().to_owned();
+            let content_type = field.content_type().map(str::to_owned);
+            let data = match field.bytes().await {
+                Ok(data) => data,
+                Err(e) if too_large(&e) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            if !file_name.is_empty() && !data.is_empty() {
+                upload.file = Some((file_name, content_type, data.to_vec()));
+            }
+            continue;
+        }
+        let text = match field.text().await {
+            Ok(text) => text.trim().to_owned(),
+            Err(e) if too_large(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match field_name.as_str() {
+            "name" => upload.name = text,
+            "category" => upload.category = text,
+            "link" => upload.link = text,
+            _ => {}
+        }
+    }
+    Ok(Some(upload))
+}
+
+/// Write an uploaded file to the assets directory, returning its stored name.
+async fn save_asset(original_name: &str, data: &[u8]) -> Result<String> {
+    let file_name = asset_file_name(original_name);
+    tokio::fs::write(std::path::Path::new("./assets").join(&file_name), data).await?;
+    Ok(file_name)
+}
+
+/// Remove a resource's file from the assets directory, logging any failure.
+async fn remove_asset(file_name: &str) {
+    if let Err(e) = tokio::fs::remove_file(std::path::Path::new("./assets").join(file_name)).await {
+        warn!("Could not delete asset {file_name}: {e}");
+    }
+}
+
+/// Flash the error for an upload that was over the size limit.
+async fn flash_upload_too_large(state: &AppState, session: Session) -> Result<(), AppError> {
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Error,
+        &format!(
+            "That file is too large; uploads are limited to {} MB",
+            state.config().resources.max_upload_mb
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Flash the error for a file that isn't an allowed type, if the upload has one.
+///
+/// Returns whether the upload can be saved.
+async fn check_upload_type(
+    state: &AppState,
+    session: Session,
+    upload: &ResourceUpload,
+) -> Result<bool, AppError> {
+    let (file_name, content_type, _) = match &upload.file {
+        Some(file) => file,
+        None => return Ok(true),
+    };
+    let allowed = &state.config().resources.allowed_extensions;
+    if upload_allowed(file_name, content_type.as_deref(), allowed) {
+        return Ok(true);
+    }
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Error,
+        &format!(
+            "That type of file can't be uploaded; allowed types are: {}",
+            allowed.join(", ")
+        ),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Handler for staff adding a new resource, either an uploaded file or a link.
+async fn post_new_resource(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let upload = match read_resource_upload(multipart).await? {
+        Some(upload) => upload,
+        None => {
+            flash_upload_too_large(&state, session).await?;
+            return Ok(Redirect::to("/admin/resources").into_response());
+        }
+    };
+    if upload.name.is_empty()
+        || upload.category.is_empty()
+        || (upload.file.is_none() && upload.link.is_empty())
+    {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Resources need a name, category, and either a file or a link",
+        )
+        .await?;
+        return Ok(Redirect::to("/admin/resources").into_response());
+    }
+    if !check_upload_type(&state, session.clone(), &upload).await? {
+        return Ok(Redirect::to("/admin/resources").into_response());
+    }
+    let (file_name, link) = match &upload.file {
+        Some((original_name, _, data)) => (Some(save_asset(original_name, data).await?), None),
+        None => (None, Some(upload.link)),
+    };
+    let result = sqlx::query(sql::CREATE_NEW_RESOURCE)
+        .bind(&upload.category)
+        .bind(&upload.name)
+        .bind(&file_name)
+        .bind(link)
+        .bind(Utc::now())
+        .execute(&state.db)
+        .await;
+    if let Err(e) = result {
+        // don't leave the file behind without a resource pointing to it
+        if let Some(file_name) = &file_name {
+            remove_asset(file_name).await;
+        }
+        return Err(e.into());
+    }
+    info!(
+        "{} created resource \"{}\"",
+        user_info.unwrap().cid,
+        upload.name
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Resource added",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/resources").into_response())
+}
+
+/// Handler for staff editing a resource.
+///
+/// A new file replaces the existing file or link; a link without a file
+/// replaces an existing file. Otherwise the resource keeps what it had.
+async fn post_edit_resource(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let resource: Option<Resource> = sqlx::query_as(sql::GET_RESOURCE_BY_ID)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let resource = match resource {
+        Some(resource) => resource,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Resource not found",
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/resources").into_response());
+        }
+    };
+    let upload = match read_resource_upload(multipart).await? {
+        Some(upload) => upload,
+        None => {
+            flash_upload_too_large(&state, session).await?;
+            return Ok(Redirect::to("/admin/resources").into_response());
+        }
+    };
+    if upload.name.is_empty() || upload.category.is_empty() {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Resources need a name and category",
+        )
+        .await?;
+        return Ok(Redirect::to("/admin/resources").into_response());
+    }
+    if !check_upload_type(&state, session.clone(), &upload).await? {
+        return Ok(Redirect::to("/admin/resources").into_response());
+    }
+    let (file_name, link) = match (&upload.file, upload.link.is_empty()) {
+        (Some((original_name, _, data)), _) => (Some(save_asset(original_name, data).await?), None),
+        (None, false) => (None, Some(upload.link)),
+        (None, true) => (resource.file_name.clone(), resource.link.clone()),
+    };
+    let result = sqlx::query(sql::UPDATE_RESOURCE)
+        .bind(&upload.category)
+        .bind(&upload.name)
+        .bind(&file_name)
+        .bind(link)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&state.db)
+        .await;
+    if let Err(e) = result {
+        // the resource still points to its old file, so only remove a new one
+        if let Some(new_file_name) = &file_name {
+            if resource.file_name.as_ref() != Some(new_file_name) {
+                remove_asset(new_file_name).await;
+            }
+        }
+        return Err(e.into());
+    }
+    // only remove the replaced file once the resource no longer points to it
+    if let Some(old_file_name) = resource.file_name {
+        if file_name.as_ref() != Some(&old_file_name) {
+            remove_asset(&old_file_name).await;
+        }
+    }
+    info!(
+        "{} updated resource \"{}\"",
+        user_info.unwrap().cid,
+        upload.name
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Resource updated",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/resources").into_response())
+}
+
+/// Handler for staff deleting a resource and its file.
+async fn post_delete_resource(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let resource: Option<Resource> = sqlx::query_as(sql::GET_RESOURCE_BY_ID)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    if let Some(resource) = resource {
+        sqlx::query(sql::DELETE_RESOURCE)
+            .bind(id)
+            .execute(&state.db)
+            .await?;
+        if let Some(file_name) = &resource.file_name {
+            remove_asset(file_name).await;
+        }
+        info!(
+            "{} deleted resource \"{}\"",
+            user_info.unwrap().cid,
+            resource.name
+        );
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Success,
+            "Resource deleted",
+        )
+        .await?;
+    }
+    Ok(Redirect::to("/admin/resources").into_response())
+}
+
+/// Page for events staff to import an event from another facility.
+async fn page_event_import(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/event_import")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        allowed_hosts => state.config().api.event_import_hosts.clone(),
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct EventImportForm {
+    /// Base URL of the other facility's site
+    url: String,
+    id: u32,
+}
+
+/// Fetch a single event from another vZDV-based site's events API.
+///
+/// Redirects aren't followed, so the request can't be bounced off an allowed
+/// host to somewhere else.
+async fn fetch_remote_event(
+    allowed_hosts: &[String],
+    base_url: &str,
+    id: u32,
+) -> Result<ApiEvent, String> {
+    static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+        reqwest::ClientBuilder::new()
+            .user_agent("github.com/celeo/vzdv")
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Could not construct HTTP client")
+    });
+    let url = event_import_url(allowed_hosts, base_url)?;
+    let resp = CLIENT.get(url.clone()).send().await.map_err(|e| {
+        warn!("Error fetching events from {url}: {e}");
+        "Could not reach the other site".to_owned()
+    })?;
+    if !resp.status().is_success() {
+        return Err(format!(
+            "Got status {} from the other site",
+            resp.status().as_u16()
+        ));
+    }
+    let events: Vec<ApiEvent> = resp
+        .json()
+        .await
+        .map_err(|_| "The other site's response wasn't a list of events".to_owned())?;
+    events
+        .into_iter()
+        .find(|event| event.id == id)
+        .ok_or_else(|| format!("The other site has no upcoming event with ID {id}"))
+}
+
+/// Handler for events staff copying another facility's event.
+///
+/// The copy is unpublished so it can be reviewed before being shown.
+async fn post_event_import(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(import_form): Form<EventImportForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff).await
+    {
+        return Ok(redirect);
+    }
+    let event = match fetch_remote_event(
+        &state.config().api.event_import_hosts,
+        &import_form.url,
+        import_form.id,
+    )
+    .await
+    {
+        Ok(event) => event,
+        Err(message) => {
+            warn!("Could not import event from {}: {message}", import_form.url);
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                &message,
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/events/import").into_response());
+        }
+    };
+    let cid = user_info.unwrap().cid;
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let image_url = event
+        .image_url
+        .filter(|url| !url.is_empty() && validate_banner_url(url));
+    // templates aren't autoescaped, so the other site's text is escaped here
+    sqlx::query(sql::INSERT_EVENT)
+        .bind(controller.map(|c| c.id).unwrap_or_default())
+        .bind(escape_xml(&event.name))
+        .bind(event.start)
+        .bind(event.end)
+        .bind(event.description.as_deref().map(escape_xml))
+        .bind(image_url)
+        .bind(None::<i8>)
+        .execute(&state.db)
+        .await?;
+    info!(
+        "{cid} imported event '{}' from {}",
+        event.name, import_form.url
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Event imported; it's unpublished until reviewed",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/events/import").into_response())
+}
+
+/// Page for setting a single certification across several controllers.
+///
+/// Useful after group training, where a cohort all need the same change.
+async fn page_bulk_certifications(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let controllers: Vec<_> = controllers
+        .into_iter()
+        .sorted_by(|a, b| Ord::cmp(&a.last_name, &b.last_name))
+        .collect();
+    let template = state.templates.get_template("admin/bulk_certifications")?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        controllers,
+        certifications => &state.config().training.certifications,
+        values => CERTIFICATION_VALUES,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Handler for setting a single certification across several controllers.
+///
+/// The form is read as a list of pairs as it contains a "cid" entry for each
+/// selected controller. All changes are made in a single transaction.
+async fn post_bulk_certifications(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(form): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let mut name = String::new();
+    let mut value = String::new();
+    let mut cids: Vec<u32> = Vec::new();
+    for (key, val) in form {
+        match key.as_str() {
+            "name" => name = val,
+            "value" => value = val,
+            "cid" => cids.push(val.parse()?),
+            _ => {}
+        }
+    }
+    if !state.config().training.certifications.contains(&name)
+        || !CERTIFICATION_VALUES.contains(&value.as_str())
+        || cids.is_empty()
+    {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Select a certification, a value, and at least one controller",
+        )
+        .await?;
+        return Ok(Redirect::to("/admin/certifications/bulk").into_response());
+    }
+
+    let set_by = user_info.unwrap().cid;
+    let now = sqlx::types::chrono::Utc::now();
+    let mut tx = state.db.begin().await?;
+    for &cid in &cids {
+        let existing: Option<Certification> = sqlx::query_as(sql::GET_CERTIFICATION_FOR)
+            .bind(cid)
+            .bind(&name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let updated = Certification {
+            id: existing.as_ref().map(|cert| cert.id).unwrap_or_default(),
+            cid,
+            name: name.clone(),
+            value: value.clone(),
+            changed_on: now,
+            set_by,
+            valid_until: None,
+            last_used: None,
+        };
+        let valid_until = certification_valid_until(&state.config(), &updated);
+        match existing {
+            Some(existing) => {
+                sqlx::query(sql::UPDATE_CERTIFICATION)
+                    .bind(&value)
+                    .bind(now)
+                    .bind(set_by)
+                    .bind(valid_until)
+                    .bind(existing.id)
+                    .execute(&mut *tx)
+                    .await?;
+                info!(
+                    "{set_by} changed certification {name} for {cid} from {} to {value}",
+                    existing.value
+                );
+                if existing.value != value {
+                    record_log(
+                        &mut *tx,
+                        LogCategory::Certification,
+                        Some(cid),
+                        &format!(
+                            "Certification {name} changed from {} to {value} by {set_by}",
+                            existing.value
+                        ),
+                    )
+                    .await?;
+                }
+            }
+            None => {
+                sqlx::query(sql::INSERT_CERTIFICATION)
+                    .bind(cid)
+                    .bind(&name)
+                    .bind(&value)
+                    .bind(now)
+                    .bind(set_by)
+                    .bind(valid_until)
+                    .execute(&mut *tx)
+                    .await?;
+                info!("{set_by} set certification {name} for {cid} to {value}");
+                record_log(
+                    &mut *tx,
+                    LogCategory::Certification,
+                    Some(cid),
+                    &format!("Certification {name} set to {value} by {set_by}"),
+                )
+                .await?;
+            }
+        }
+    }
+    tx.commit().await?;
+
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        &format!("Set {name} to {value} for {} controllers", cids.len()),
+    )
+    .await?;
+    Ok(Redirect::to("/admin/certifications/bulk").into_response())
+}
+
+/// Roles the logged-in user can assign, checked against their stored roles.
+///
+/// Empty for anyone who can't manage roles.
+async fn assignable_roles(
+    state: &Arc<AppState>,
+    user_info: &Option<UserInfo>,
+) -> Result<Vec<&'static str>> {
+    let user_info = match user_info {
+        Some(user_info) if user_info.is_staff => user_info,
+        _ => return Ok(Vec::new()),
+    };
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(user_info.cid)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(controller
+        .map(|controller| roles_to_set(&controller.roles))
+        .unwrap_or_default())
+}
+
+/// Page for adding or removing a single role across several controllers.
+async fn page_bulk_roles(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let roles = assignable_roles(&state, &user_info).await?;
+    if roles.is_empty() {
+        return Ok(Redirect::to("/").into_response());
+    }
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let controllers: Vec<_> = controllers
+        .into_iter()
+        .sorted_by(|a, b| Ord::cmp(&a.last_name, &b.last_name))
+        .collect();
+    let template = state.templates.get_template("admin/bulk_roles")?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        controllers,
+        roles,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Handler for adding or removing a single role across several controllers.
+///
+/// Each change is sent to VATUSA, which the roster sync reads roles from. The
+/// changes VATUSA accepts are then stored and logged in a single transaction.
+async fn post_bulk_roles(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(form): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let allowed = assignable_roles(&state, &user_info).await?;
+    if allowed.is_empty() {
+        return Ok(Redirect::to("/").into_response());
+    }
+    let mut role = String::new();
+    let mut action = String::new();
+    let mut cids: Vec<u32> = Vec::new();
+    for (key, val) in form {
+        match key.as_str() {
+            "role" => role = val,
+            "action" => action = val,
+            "cid" => match val.parse() {
+                Ok(cid) => cids.push(cid),
+                Err(_) => {
+                    flashed_messages::push_flashed_message(
+                        session,
+                        flashed_messages::FlashedMessageLevel::Error,
+                        "Select controllers from the list",
+                    )
+                    .await?;
+                    return Ok(Redirect::to("/admin/roles/bulk").into_response());
+                }
+            },
+            _ => {}
+        }
+    }
+    let add = action == "add";
+    if !allowed.contains(&role.as_str())
+        || !["add", "remove"].contains(&action.as_str())
+        || cids.is_empty()
+    {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Select a role you can assign, an action, and at least one controller",
+        )
+        .await?;
+        return Ok(Redirect::to("/admin/roles/bulk").into_response());
+    }
+
+    let by = user_info.unwrap().cid;
+    let mut updated = Vec::new();
+    let mut failed = Vec::new();
+    for &cid in &cids {
+        match vatusa::set_role(
+            &state.config().vatsim.vatusa_api_key,
+            cid,
+            &state.config().facility.code,
+            &role,
+            add,
+        )
+        .await
+        {
+            Ok(_) => updated.push(cid),
+            Err(e) => {
+                error!("Could not {action} role {role} for {cid} at VATUSA: {e}");
+                failed.push(cid);
+            }
+        }
+    }
+    let mut tx = state.db.begin().await?;
+    for &cid in &updated {
+        let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+            .bind(cid)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let roles = with_role(
+            controller
+                .as_ref()
+                .map(|c| c.roles.as_str())
+                .unwrap_or_default(),
+            &role,
+            add,
+        );
+        sqlx::query(sql::UPDATE_CONTROLLER_ROLES)
+            .bind(&roles)
+            .bind(cid)
+            .execute(&mut *tx)
+            .await?;
+        let message = if add {
+            format!("Role {role} added by {by}")
+        } else {
+            format!("Role {role} removed by {by}")
+        };
+        record_log(&mut *tx, LogCategory::Security, Some(cid), &message).await?;
+    }
+    tx.commit().await?;
+
+    if !updated.is_empty() {
+        flashed_messages::push_flashed_message(
+            session.clone(),
+            flashed_messages::FlashedMessageLevel::Success,
+            &format!("Updated {role} for {} controllers", updated.len()),
+        )
+        .await?;
+    }
+    if !failed.is_empty() {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            &format!(
+                "VATUSA rejected the change for {}",
+                failed.iter().map(|cid| cid.to_string()).join(", ")
+            ),
+        )
+        .await?;
+    }
+    Ok(Redirect::to("/admin/roles/bulk").into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SoloCertFilter {
+    #[serde(default)]
+    position: String,
+    /// "yes", "no", or blank for either
+    #[serde(default)]
+    reported: String,
+    /// Number of days, or blank for any expiration
+    #[serde(default)]
+    expiring_within: String,
+}
+
+/// Page listing solo certs, soonest to expire first.
+///
+/// Filterable by position prefix, VATUSA reporting status, and upcoming expiration.
+async fn page_solo_certs(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Query(filter): Query<SoloCertFilter>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let reported = match filter.reported.as_str() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    };
+    let expiring_within: Option<i64> = filter.expiring_within.trim().parse().ok();
+    let now = sqlx::types::chrono::Utc::now();
+    let solo_certs: Vec<SoloCert> = sqlx::query_as(sql::GET_ALL_SOLO_CERTS)
+        .fetch_all(&state.db)
+        .await?;
+    let solo_certs: Vec<_> = solo_certs
+        .into_iter()
+        .filter(|cert| solo_cert_matches(cert, &filter.position, reported, expiring_within, now))
+        .collect();
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let controllers: Vec<_> = controllers
+        .into_iter()
+        .sorted_by(|a, b| Ord::cmp(&a.last_name, &b.last_name))
+        .collect();
+
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/solo_cert_list")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        solo_certs,
+        controllers,
+        filter_position => filter.position,
+        filter_reported => filter.reported,
+        filter_expiring_within => filter.expiring_within,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Page comparing the site's solo certs with VATUSA's.
+///
+/// Catches certs where reporting to VATUSA silently failed or that were
+/// issued outside of this site.
+async fn page_solo_cert_reconcile(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let local: Vec<SoloCert> = sqlx::query_as(sql::GET_ALL_SOLO_CERTS)
+        .fetch_all(&state.db)
+        .await?;
+    let remote = vatusa::get_facility_solo_certs().await?;
+    let drift = reconcile_solo_certs(
+        &local,
+        &remote,
+        &state.config().stats.position_prefixes,
+        sqlx::types::chrono::Utc::now(),
+    );
+    let template = state.templates.get_template("admin/solo_cert_reconcile")?;
+    let rendered = template.render(context! { user_info, drift })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct NewSoloCertForm {
+    cid: u32,
+    position: String,
+    /// "YYYY-MM-DD"
+    expiration: String,
+}
+
+/// Handler for training staff issuing a solo cert.
+///
+/// The cert is reported to VATUSA; if that fails, it's still stored locally
+/// but shown as unreported.
+async fn post_new_solo_cert(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(cert_form): Form<NewSoloCertForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let position = cert_form.position.trim().to_uppercase();
+    let expiration = chrono::NaiveDate::parse_from_str(&cert_form.expiration, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|date| date.and_utc());
+    let now = sqlx::types::chrono::Utc::now();
+    let expiration = match expiration {
+        Some(e) if e > now && !position.is_empty() => e,
+        _ => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "A position and a future expiration date are required",
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/solo_certs").into_response());
+        }
+    };
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cert_form.cid)
+        .fetch_optional(&state.db)
+        .await?;
+    if controller.is_none() {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Controller not found",
+        )
+        .await?;
+        return Ok(Redirect::to("/admin/solo_certs").into_response());
+    }
+    let reported = match vatusa::report_solo_cert(
+        &state.config().vatsim.vatusa_api_key,
+        cert_form.cid,
+        &position,
+        expiration,
+    )
+    .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            error!(
+                "Could not report solo cert for {} on {position}: {e}",
+                cert_form.cid
+            );
+            false
+        }
+    };
+    let issued_by = user_info.unwrap().cid;
+    sqlx::query(sql::INSERT_INTO_SOLO_CERT)
+        .bind(cert_form.cid)
+        .bind(issued_by)
+        .bind(&position)
+        .bind(reported)
+        .bind(now)
+        .bind(expiration)
+        .execute(&state.db)
+        .await?;
+    record_log(
+        &state.db,
+        LogCategory::Audit,
+        Some(cert_form.cid),
+        &format!(
+            "Solo cert on {position} until {} issued by {issued_by}",
+            expiration.format("%m/%d/%Y")
+        ),
+    )
+    .await?;
+    let (level, message) = if reported {
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            "Solo cert issued",
+        )
+    } else {
+        (
+            flashed_messages::FlashedMessageLevel::Error,
+            "Solo cert saved, but could not be reported to VATUSA",
+        )
+    };
+    flashed_messages::push_flashed_message(session, level, message).await?;
+    Ok(Redirect::to("/admin/solo_certs").into_response())
+}
+
+/// Handler for training staff removing a solo cert from the site.
+async fn post_delete_solo_cert(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let cert: Option<SoloCert> = sqlx::query_as(sql::GET_SOLO_CERT_BY_ID)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let cert = match cert {
+        Some(cert) => cert,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Solo cert not found",
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/solo_certs").into_response());
+        }
+    };
+    // the local record is kept until VATUSA drops the cert, so it isn't orphaned there
+    if cert.reported {
+        if let Err(e) = vatusa::delete_solo_cert(
+            &state.config().vatsim.vatusa_api_key,
+            cert.cid,
+            &cert.position,
+        )
+        .await
+        {
+            error!(
+                "Could not remove solo cert for {} on {} from VATUSA: {e}",
+                cert.cid, cert.position
+            );
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Could not remove the solo cert from VATUSA, so it was kept",
+            )
+            .await?;
+            return Ok(Redirect::to("/admin/solo_certs").into_response());
+        }
+    }
+    sqlx::query(sql::DELETE_SOLO_CERT)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    record_log(
+        &state.db,
+        LogCategory::Audit,
+        Some(cert.cid),
+        &format!(
+            "Solo cert on {} deleted by {}",
+            cert.position,
+            user_info.unwrap().cid
+        ),
+    )
+    .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Solo cert deleted",
+    )
+    .await?;
+    Ok(Redirect::to("/admin/solo_certs").into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct FeedbackContactForm {
+    no_feedback_contact: Option<String>,
+}
+
+/// Handler for admins setting whether a controller can be contacted about feedback.
+async fn post_feedback_contact(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+    Form(contact_form): Form<FeedbackContactForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let no_feedback_contact = contact_form.no_feedback_contact.is_some();
+    let result = sqlx::query(sql::UPDATE_CONTROLLER_NO_FEEDBACK_CONTACT)
+        .bind(no_feedback_contact)
+        .bind(cid)
+        .execute(&state.db)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let message = format!(
+        "Feedback contact {} by {}",
+        if no_feedback_contact {
+            "disabled"
+        } else {
+            "enabled"
+        },
+        user_info.unwrap().cid
+    );
+    info!("{message} for {cid}");
+    record_log(&state.db, LogCategory::Audit, Some(cid), &message).await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Feedback contact updated",
+    )
+    .await?;
+    Ok(Redirect::to(&format!("/admin/roster/{cid}")).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct OperatingInitialsForm {
+    ois: String,
+}
+
+/// Handler for staff members setting a controller's operating initials.
+///
+/// Submitting blank OIs clears the controller's.
+async fn post_change_ois(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+    Form(ois_form): Form<OperatingInitialsForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let ois = ois_form.ois.trim().to_uppercase();
+    if !ois.is_empty() && (ois.len() != 2 || !ois.chars().all(|c| c.is_ascii_alphabetic())) {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Operating initials must be two letters",
+        )
+        .await?;
+        return Ok(Redirect::to("/facility/roster").into_response());
+    }
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let (level, message) = if controller.is_none() {
+        (
+            flashed_messages::FlashedMessageLevel::Error,
+            String::from("Controller not found"),
+        )
+    } else if set_operating_initials(&state.db, cid, &ois).await? {
+        let message = format!(
+            "Operating initials set to '{ois}' by {}",
+            user_info.unwrap().cid
+        );
+        info!("{message} for {cid}");
+        record_log(&state.db, LogCategory::Audit, Some(cid), &message).await?;
+        (
+            flashed_messages::FlashedMessageLevel::Success,
+            String::from("Operating initials updated"),
+        )
+    } else {
+        (
+            flashed_messages::FlashedMessageLevel::Error,
+            format!("Operating initials {ois} are already in use"),
+        )
+    };
+    flashed_messages::push_flashed_message(session, level, &message).await?;
+    Ok(Redirect::to("/facility/roster").into_response())
+}
+
+/// Page for staff to view a single controller and their history.
+async fn page_controller(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let controller = match controller {
+        Some(c) => c,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Controller not found",
+            )
+            .await?;
+            return Ok(Redirect::to("/facility/roster").into_response());
+        }
+    };
+    let logs: Vec<LogEntry> = sqlx::query_as(sql::GET_LOGS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let feedback: Vec<Feedback> = sqlx::query_as(sql::GET_FEEDBACK_FOR)
+        .bind(cid.to_string())
+        .bind(format!(
+            "{} {}",
+            controller.first_name, controller.last_name
+        ))
+        .fetch_all(&state.db)
+        .await?;
+    let visitor_requests: Vec<VisitorApplication> =
+        sqlx::query_as(sql::GET_PENDING_VISITOR_REQ_FOR)
+            .bind(cid)
+            .fetch_all(&state.db)
+            .await?;
+    let now = Utc::now();
+    let expired_certs: Vec<_> = certifications
+        .iter()
+        .filter(|cert| cert.valid_until.is_some_and(|until| until < now))
+        .map(|cert| cert.name.clone())
+        .collect();
+    let timeline = build_controller_timeline(&logs, &certifications, &feedback, &visitor_requests);
+    let visitor_checklist = if visitor_requests.is_empty() {
+        Vec::new()
+    } else {
+        visitor_certification_checklist(&state.config(), &certifications)
+    };
+    let staff_notes: Vec<StaffNote> = sqlx::query_as(sql::GET_STAFF_NOTES_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+
+    let last_training = last_training_session(&state, cid).await?;
+
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/controller")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        rating_name => Controller::rating_name(controller.rating),
+        controller,
+        certifications,
+        expired_certs,
+        timeline,
+        staff_notes,
+        last_training,
+        visitor_checklist,
+        notify_notes => !state.config().discord.webhooks.staff_notes.is_empty(),
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Date of a controller's most recent ZDV training session.
+#[derive(Debug, Serialize, Deserialize)]
+struct LastTraining {
+    session_date: Option<String>,
+    refreshed: DateTime<Utc>,
+}
+
+/// Look up when the controller last had a ZDV training session.
+///
+/// The result is kept in the kvs table and reused until it's older than the
+/// "LAST_TRAINING" cache lifetime. If VATUSA can't be reached, the stored copy
+/// is used, and `None` is returned if there isn't one.
+async fn last_training_session(state: &AppState, cid: u32) -> Result<Option<LastTraining>> {
+    let key = format!("last_training_{cid}");
+    let stored: Option<String> = sqlx::query_scalar(sql::GET_KVS_ENTRY)
+        .bind(&key)
+        .fetch_optional(&state.db)
+        .await?;
+    let stored: Option<LastTraining> = stored.and_then(|value| serde_json::from_str(&value).ok());
+    let now = Utc::now();
+    let ttl = state.config().cache.ttl("LAST_TRAINING") as i64;
+    if stored
+        .as_ref()
+        .is_some_and(|s| (now - s.refreshed).num_seconds() < ttl)
+    {
+        return Ok(stored);
+    }
+    match vatusa::get_training_records(&state.config().vatsim.vatusa_api_key, cid).await {
+        Ok(records) => {
+            let last = LastTraining {
+                session_date: vatusa::last_session_date(&records, &state.config().facility.code)
+                    .map(str::to_owned),
+                refreshed: now,
+            };
+            sqlx::query(sql::UPSERT_KVS_ENTRY)
+                .bind(&key)
+                .bind(serde_json::to_string(&last)?)
+                .execute(&state.db)
+                .await?;
+            Ok(Some(last))
+        }
+        Err(e) => {
+            warn!("Could not get training records for {cid}: {e}");
+            Ok(stored)
+        }
+    }
+}
+
+/// Handler for staff queuing a sync of a single controller from VATUSA.
+async fn post_vatusa_sync(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    sqlx::query(sql::INSERT_INTO_IPC)
+        .bind(IPC_VATUSA_SYNC)
+        .bind(cid.to_string())
+        .bind(sqlx::types::chrono::Utc::now())
+        .execute(&state.db)
+        .await?;
+    info!("{} queued a VATUSA sync of {cid}", user_info.unwrap().cid);
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Info,
+        "Sync from VATUSA queued; it should finish within a minute",
+    )
+    .await?;
+    Ok(Redirect::to(&format!("/admin/roster/{cid}")).into_response())
+}
+
+/// Handler for admins overwriting a controller's record with fresh VATUSA data.
+///
+/// Unlike the queued sync, this runs immediately and also updates controllers
+/// who have left the roster, so it can fix records left stale by transfers.
+async fn post_vatusa_refresh(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let redirect = Redirect::to(&format!("/admin/roster/{cid}")).into_response();
+    let before: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let before = match before {
+        Some(c) => c,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    let config = state.config();
+    let fetched = match tokio::try_join!(
+        vatusa::get_controller_info(cid),
+        vatusa::get_roster(&config.facility.code, vatusa::MembershipType::Visit)
+    ) {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            warn!("Could not refresh {cid} from VATUSA: {e}");
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Could not get the controller's data from VATUSA",
+            )
+            .await?;
+            return Ok(redirect);
+        }
+    };
+    let (info, visitors) = fetched;
+    let after = Controller {
+        first_name: info.first_name,
+        last_name: info.last_name,
+        rating: info.rating as i8,
+        is_on_roster: info.facility == state.config().facility.code
+            || visitors.iter().any(|v| v.cid == cid),
+        home_facility: info.facility,
+        ..before.clone()
+    };
+    sqlx::query(sql::UPDATE_CONTROLLER_FROM_VATUSA)
+        .bind(&after.first_name)
+        .bind(&after.last_name)
+        .bind(after.rating)
+        .bind(&after.home_facility)
+        .bind(after.is_on_roster)
+        .bind(cid)
+        .execute(&state.db)
+        .await?;
+    let changes = controller_changes(&before, &after);
+    let by = user_info.unwrap().cid;
+    let summary = if changes.is_empty() {
+        "no changes".to_owned()
+    } else {
+        changes.join(", ")
+    };
+    record_log(
+        &state.db,
+        LogCategory::Audit,
+        Some(cid),
+        &format!("Refreshed from VATUSA by {by}: {summary}"),
+    )
+    .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        &format!("Refreshed from VATUSA: {summary}"),
+    )
+    .await?;
+    Ok(redirect)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RatingHistoryQuery {
+    #[serde(default)]
+    refresh: bool,
+}
+
+/// Render a controller's rating history from VATUSA.
+///
+/// The last response is stored per controller and reused until it's older than
+/// the "RATING_HISTORY" cache lifetime, or the viewer asks for a refresh. If
+/// VATUSA can't be reached, the stored copy is shown instead.
+async fn snippet_rating_history(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+    Query(query): Query<RatingHistoryQuery>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let stored: Option<RatingHistorySnapshot> = sqlx::query_as(sql::GET_RATING_HISTORY_FOR)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let now = sqlx::types::chrono::Utc::now();
+    let ttl = state.config().cache.ttl("RATING_HISTORY") as i64;
+    let is_fresh = stored
+        .as_ref()
+        .is_some_and(|s| (now - s.refreshed).num_seconds() < ttl);
+
+    let mut fetch_failed = false;
+    let snapshot = if is_fresh && !query.refresh {
+        stored
+    } else {
+        match vatusa::get_rating_history(cid).await {
+            Ok(history) => {
+                let data = serde_json::to_string(&history)?;
+                sqlx::query(sql::UPSERT_RATING_HISTORY)
+                    .bind(cid)
+                    .bind(&data)
+                    .bind(now)
+                    .execute(&state.db)
+                    .await?;
+                Some(RatingHistorySnapshot {
+                    cid,
+                    data,
+                    refreshed: now,
+                })
+            }
+            Err(e) => {
+                warn!("Could not get rating history for {cid}: {e}");
+                fetch_failed = true;
+                stored
+            }
+        }
+    };
+    let (history, refreshed) = match snapshot {
+        Some(snapshot) => {
+            let history: Vec<vatusa::RatingChange> = serde_json::from_str(&snapshot.data)?;
+            let history: Vec<_> = history
+                .iter()
+                .map(|change| {
+                    json!({
+                        "from": Controller::rating_name(change.from),
+                        "to": Controller::rating_name(change.to),
+                        "date": change.created_at,
+                    })
+                })
+                .collect();
+            (history, Some(snapshot.refreshed))
+        }
+        None => (Vec::new(), None),
+    };
+    let template = state.templates.get_template("admin/rating_history")?;
+    let rendered = template.render(context! { cid, history, refreshed, fetch_failed })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Render a controller's training records at the facility from VATUSA.
+///
+/// Records can be narrowed down by position, instructor, and date range.
+async fn snippet_get_training_records(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+    Query(filter): Query<vatusa::TrainingRecordFilter>,
+) -> Result<Response, AppError> {
+    use voca_rs::Voca;
+
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let records = vatusa::get_training_records(&state.config().vatsim.vatusa_api_key, cid).await?;
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|record| record.facility_id == state.config().facility.code)
+        .sorted_by(|a, b| b.session_date.cmp(&a.session_date))
+        .collect();
+    let cid_name_map = get_controller_cids_and_names(&state.db).await?;
+    let instructor_name = |instructor_id: u32| match cid_name_map.get(&(instructor_id as u64)) {
+        Some((first, last)) => format!("{first} {last}"),
+        None => instructor_id.to_string(),
+    };
+    // offered in the filter, so only instructors who appear in the records
+    let instructors: Vec<_> = records
+        .iter()
+        .map(|record| record.instructor_id)
+        .unique()
+        .map(|instructor_id| (instructor_id, instructor_name(instructor_id)))
+        .sorted_by(|a, b| a.1.cmp(&b.1))
+        .collect();
+    let total = records.len();
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|record| filter.matches(record))
+        .map(|record| {
+            json!({
+                "date": record.session_date,
+                "position": record.position,
+                "instructor": instructor_name(record.instructor_id),
+                "duration": record.duration,
+                "notes": record.notes._strip_tags(),
+            })
+        })
+        .collect();
+    let filter_active = filter.is_active();
+    let template = state.templates.get_template("admin/training_records")?;
+    let rendered = template.render(context! {
+        cid,
+        records,
+        total,
+        instructors,
+        filter,
+        filter_active,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Page for training staff to see the training sessions they've given.
+async fn page_my_training_given(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let cid = user_info.as_ref().unwrap().cid;
+    let records = vatusa::get_facility_training_records(
+        &state.config().vatsim.vatusa_api_key,
+        &state.config().facility.code,
+    )
+    .await?;
+    let cid_name_map = get_controller_cids_and_names(&state.db).await?;
+    let sessions: Vec<_> = records
+        .iter()
+        .filter(|record| record.instructor_id == cid)
+        .sorted_by(|a, b| b.session_date.cmp(&a.session_date))
+        .map(|record| {
+            let student = match cid_name_map.get(&(record.student_id as u64)) {
+                Some((first, last)) => format!("{first} {last}"),
+                None => record.student_id.to_string(),
+            };
+            json!({
+                "student": student,
+                "student_cid": record.student_id,
+                "date": record.session_date,
+                "position": record.position,
+                "duration": record.duration,
+            })
+        })
+        .collect();
+    let students = records
+        .iter()
+        .filter(|record| record.instructor_id == cid)
+        .map(|record| record.student_id)
+        .unique()
+        .count();
+    let template = state.templates.get_template("admin/training_given")?;
+    let rendered = template.render(context! { user_info, sessions, students })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct NewStaffNoteForm {
+    note: String,
+    /// Present if the staff Discord channel should be told about the note
+    notify: Option<String>,
+}
+
+/// Handler for staff members adding a note to a controller's record.
+async fn post_new_staff_note(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+    Form(note_form): Form<NewStaffNoteForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff).await
+    {
+        return Ok(redirect);
+    }
+    let note = note_form.note.trim();
+    let min_length = state.config().staff.min_note_length;
+    if note.chars().count() < min_length {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            &format!("Notes must be at least {min_length} characters"),
+        )
+        .await?;
+        return Ok(Redirect::to(&format!("/admin/roster/{cid}")).into_response());
+    }
+    let user_info = user_info.unwrap();
+    sqlx::query(sql::INSERT_INTO_STAFF_NOTE)
+        .bind(cid)
+        .bind(user_info.cid)
+        .bind(note)
+        .bind(sqlx::types::chrono::Utc::now())
+        .execute(&state.db)
+        .await?;
+
+    // the note itself isn't sent, as it can contain sensitive information
+    let webhook = &state.config().discord.webhooks.staff_notes;
+    if note_form.notify.is_some() && !webhook.is_empty() {
+        let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+            .bind(cid)
+            .fetch_optional(&state.db)
+            .await?;
+        let controller_name = match controller {
+            Some(c) => format!("{} {} ({cid})", c.first_name, c.last_name),
+            None => cid.to_string(),
+        };
+        let sent = GENERAL_HTTP_CLIENT
+            .post(webhook)
+            .json(&json!({
+                "content": "",
+                "embeds": [{
+                    "title": "Staff note added",
+                    "fields": [
+                        {
+                            "name": "Controller",
+                            "value": controller_name
+                        },
+                        {
+                            "name": "By",
+                            "value": format!("{} {}", user_info.first_name, user_info.last_name)
+                        }
+                    ]
+                }]
+            }))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match sent {
+            Ok(_) => {
+                record_log(
+                    &state.db,
+                    LogCategory::Info,
+                    Some(cid),
+                    &format!("Staff note by {} posted to Discord", user_info.cid),
+                )
+                .await?;
+            }
+            Err(e) => error!("Error posting staff note notification for {cid}: {e}"),
+        }
+    }
+
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Note added",
+    )
+    .await?;
+    Ok(Redirect::to(&format!("/admin/roster/{cid}")).into_response())
+}
+
+/// Everything stored about a single controller.
+#[derive(Serialize)]
+struct ControllerExport {
+    controller: Controller,
+    certifications: Vec<Certification>,
+    solo_certs: Vec<SoloCert>,
+    activity: Vec<Activity>,
+    feedback_received: Vec<ReceivedFeedback>,
+    feedback_submitted: Vec<Feedback>,
+    visitor_requests: Vec<VisitorApplication>,
+    event_registrations: Vec<EventRegistration>,
+    sop_initials: Vec<SopInitial>,
+    /// Staff-only; omitted from a controller's export of their own data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    staff_notes: Option<Vec<StaffNote>>,
+    /// Staff-only; omitted from a controller's export of their own data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logs: Option<Vec<LogEntry>>,
+}
+
+/// Feedback about the controller, as included in an export.
+#[derive(Serialize)]
+struct ReceivedFeedback {
+    id: u32,
+    controller: String,
+    position: String,
+    rating: String,
+    comments: String,
+    created_date: DateTime<Utc>,
+    /// Staff-only; omitted from a controller's export of their own data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submitter_cid: Option<u32>,
+    reviewer_action: String,
+    posted_to_discord: bool,
+}
+
+impl ReceivedFeedback {
+    fn new(feedback: Feedback, include_submitter: bool) -> Self {
+        Self {
+            id: feedback.id,
+            controller: feedback.controller,
+            position: feedback.position,
+            rating: feedback.rating,
+            comments: feedback.comments,
+            created_date: feedback.created_date,
+            submitter_cid: include_submitter.then_some(feedback.submitter_cid),
+            reviewer_action: feedback.reviewer_action,
+            posted_to_discord: feedback.posted_to_discord,
+        }
+    }
+}
+
+/// Export all of a controller's stored data as JSON, for data requests.
+///
+/// Controllers can export their own data; admins can export anyone's,
+/// and their exports include staff notes, audit log entries, unreviewed
+/// feedback, and who submitted each piece of feedback.
+async fn get_controller_export(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let is_self = user_info.as_ref().is_some_and(|info| info.cid == cid);
+    let is_admin = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins)
+        .await
+        .is_none();
+    if !is_self && !is_admin {
+        return Ok(Redirect::to("/").into_response());
+    }
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let controller = match controller {
+        Some(c) => c,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    let certifications = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let solo_certs = sqlx::query_as(sql::GET_SOLO_CERTS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let activity = sqlx::query_as(sql::GET_ACTIVITY_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let feedback_received: Vec<Feedback> = sqlx::query_as(if is_admin {
+        sql::GET_FEEDBACK_FOR
+    } else {
+        sql::GET_POSTED_FEEDBACK_FOR
+    })
+    .bind(cid.to_string())
+    .bind(format!(
+        "{} {}",
+        controller.first_name, controller.last_name
+    ))
+    .fetch_all(&state.db)
+    .await?;
+    let feedback_received = feedback_received
+        .into_iter()
+        .map(|feedback| ReceivedFeedback::new(feedback, is_admin))
+        .collect();
+    let feedback_submitted = sqlx::query_as(sql::GET_FEEDBACK_SUBMITTED_BY)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let visitor_requests = sqlx::query_as(sql::GET_PENDING_VISITOR_REQ_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let event_registrations = sqlx::query_as(sql::GET_EVENT_REGISTRATIONS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let sop_initials = sqlx::query_as(sql::GET_SOP_INITIALS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let (staff_notes, logs) = if is_admin {
+        (
+            Some(
+                sqlx::query_as(sql::GET_STAFF_NOTES_FOR)
+                    .bind(cid)
+                    .fetch_all(&state.db)
+                    .await?,
+            ),
+            Some(
+                sqlx::query_as(sql::GET_LOGS_FOR)
+                    .bind(cid)
+                    .fetch_all(&state.db)
+                    .await?,
+            ),
+        )
+    } else {
+        (None, None)
+    };
+    info!(
+        "{} exported the data of {cid}",
+        user_info.as_ref().unwrap().cid
+    );
+    Ok(Json(ControllerExport {
+        controller,
+        certifications,
+        solo_certs,
+        activity,
+        feedback_received,
+        feedback_submitted,
+        visitor_requests,
+        event_registrations,
+        sop_initials,
+        staff_notes,
+        logs,
+    })
+    .into_response())
+}
+
+/// Page for admins to check the email configuration.
+async fn page_emails(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("admin/emails")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        host => &state.config().email.host,
+        from => &state.config().email.from,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct TestEmailForm {
+    recipient: String,
+}
+
+/// Handler for admins sending a test email.
+///
+/// The SMTP error, if any, is shown to the admin.
+async fn post_test_email(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(email_form): Form<TestEmailForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let recipient = email_form.recipient.trim();
+    let result = send_mail_raw(
+        &state.config(),
+        recipient,
+        &format!("{} test email", state.config().facility.site_name()),
+        "This is a test email to confirm the site's email settings.",
+    )
+    .await;
+    let (level, message) = match result {
+        Ok(_) => {
+            info!(
+                "{} sent a test email to {recipient}",
+                user_info.unwrap().cid
+            );
+            (
+                flashed_messages::FlashedMessageLevel::Success,
+                format!("Test email sent to {recipient}"),
+            )
+        }
+        Err(e) => {
+            warn!("Could not send test email to {recipient}: {e}");
+            (
+                flashed_messages::FlashedMessageLevel::Error,
+                format!("Could not send test email: {e}"),
+            )
+        }
+    };
+    flashed_messages::push_flashed_message(session, level, &message).await?;
+    Ok(Redirect::to("/admin/emails").into_response())
+}
+
+/// Handler for admins queuing an activity sync.
+///
+/// The sync itself is run by the tasks binary, which checks for IPC messages
+/// every minute. Only one request can be queued at a time.
+async fn post_sync_activity(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let cid = user_info.unwrap().cid;
+    let result = sqlx::query(sql::INSERT_INTO_IPC)
+        .bind(IPC_ACTIVITY_SYNC)
+        .bind("")
+        .bind(sqlx::types::chrono::Utc::now())
+        .execute(&state.db)
+        .await?;
+    let message = if result.rows_affected() > 0 {
+        info!("{cid} queued an activity sync");
+        record_log(
+            &state.db,
+            LogCategory::Audit,
+            None,
+            &format!("Activity sync queued by {cid}"),
+        )
+        .await?;
+        "Activity sync queued; it should start within a minute"
+    } else {
+        "An activity sync is already queued"
+    };
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Info,
+        message,
+    )
+    .await?;
+    Ok(Redirect::to("/facility/activity").into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LogFilter {
+    /// Category name, or blank for all
+    #[serde(default)]
+    category: String,
+}
+
+/// Number of audit log entries shown per page by default.
+const LOGS_PAGE_SIZE: u32 = 50;
+
+/// Page showing the most recent audit log entries.
+async fn page_logs(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Query(filter): Query<LogFilter>,
+    Query(page_query): Query<PageQuery>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let category = LogCategory::parse(&filter.category)
+        .map(|c| c.as_str())
+        .unwrap_or_default();
+    let total: u32 = sqlx::query_scalar(sql::COUNT_LOGS)
+        .bind(category)
+        .fetch_one(&state.db)
+        .await?;
+    let pagination = Pagination::new(&page_query, LOGS_PAGE_SIZE, total);
+    let logs: Vec<LogEntry> = sqlx::query_as(sql::GET_RECENT_LOGS)
+        .bind(category)
+        .bind(pagination.limit())
+        .bind(pagination.offset())
+        .fetch_all(&state.db)
+        .await?;
+    let logs = Paginated::new(logs, pagination);
+    let categories: Vec<_> = LogCategory::ALL.iter().map(|c| c.as_str()).collect();
+    let template = state.templates.get_template("admin/logs")?;
+    let rendered = template.render(context! { user_info, logs, categories, category })?;
+    Ok(Html(rendered).into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LogFileQuery {
+    #[serde(default)]
+    file: String,
+    /// Number of lines to show; clamped to the configured maximum
+    lines: Option<usize>,
+}
+
+/// Page showing the end of one of the configured log files.
+async fn page_log_files(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Query(query): Query<LogFileQuery>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    let files = &state.config().logs.files;
+    let selected = state
+        .config()
+        .logs
+        .path_for(&query.file)
+        .map(|path| (query.file.as_str(), path));
+    let line_count = query
+        .lines
+        .unwrap_or(100)
+        .clamp(1, state.config().logs.max_lines.max(1));
+    let lines: Option<Vec<String>> = match &selected {
+        Some((_, path)) => match read_last_lines(path, line_count).await {
+            Ok(lines) => Some(lines.iter().map(|line| format_log_line(line)).collect()),
+            Err(e) => {
+                warn!("Could not read log file {}: {e}", path.display());
+                None
+            }
+        },
+        None => None,
+    };
+    let template = state.templates.get_template("admin/log_files")?;
+    let rendered = template.render(context! {
+        user_info,
+        files,
+        selected => selected.map(|(name, _)| name),
+        line_count,
+        lines,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Loaded site config as JSON, with secrets redacted.
+///
+/// Lets admins confirm which values are active without shell access.
+async fn get_site_config(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) = reject_if_not_staff(&state, &user_info, StaffRequirement::Admins).await
+    {
+        return Ok(redirect);
+    }
+    Ok(Json(state.config().redacted()).into_response())
+}
+
+/*
+ * TODO manage a controller
+ *
+ * Things to do:
+ *  - set controller rating
+ *  - add to / remove from the roster
+ *  - add / remove certifications
+ *  - add / remove staff ranks (incl. mentor and assoc. positions)
+ *  - add training note (do it on this site, then post to VATUSA)
+ *
+ * TODO allow managing the roster
+ * TODO allow creating and modifying events
+ * TODO allow managing visitor requests
+ */
+
+/// This file's routes and templates.
+pub fn router(templates: &mut Environment, config: &Config) -> Router<Arc<AppState>> {
+    templates
+        .add_template(
+            "admin/feedback",
+            include_str!("../../templates/admin/feedback.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/feedback_stats",
+            include_str!("../../templates/admin/feedback_stats.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/news",
+            include_str!("../../templates/admin/news.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/archived_events",
+            include_str!("../../templates/admin/archived_events.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/bulk_roles",
+            include_str!("../../templates/admin/bulk_roles.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/bulk_certifications",
+            include_str!("../../templates/admin/bulk_certifications.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/controller",
+            include_str!("../../templates/admin/controller.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/solo_cert_list",
+            include_str!("../../templates/admin/solo_cert_list.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/solo_cert_reconcile",
+            include_str!("../../templates/admin/solo_cert_reconcile.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/emails",
+            include_str!("../../templates/admin/emails.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/rating_history",
+            include_str!("../../templates/admin/rating_history.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/training_given",
+            include_str!("../../templates/admin/training_given.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/training_records",
+            include_str!("../../templates/admin/training_records.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/resource_categories",
+            include_str!("../../templates/admin/resource_categories.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/removal_report",
+            include_str!("../../templates/admin/removal_report.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/cache",
+            include_str!("../../templates/admin/cache.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/maintenance",
+            include_str!("../../templates/admin/maintenance.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/log_files",
+            include_str!("../../templates/admin/log_files.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/logs",
+            include_str!("../../templates/admin/logs.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/resources",
+            include_str!("../../templates/admin/resources.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "admin/event_import",
+            include_str!("../../templates/admin/event_import.jinja"),
+        )
+        .unwrap();
+
+    let upload_limit = DefaultBodyLimit::max(config.resources.max_upload_mb * 1024 * 1024);
+
+    Router::new()
+        .route("/admin/feedback", get(page_feedback))
+        .route("/admin/feedback", post(post_feedback_form_handle))
+        .route("/admin/feedback/stats", get(page_feedback_stats))
+        .route("/admin/news", get(page_news))
+        .route("/admin/news", post(post_news_create))
+        .route("/admin/news/delete", post(post_news_delete))
+        .route("/admin/events/archived", get(page_archived_events))
+        .route("/admin/events/import", get(page_event_import))
+        .route("/events/import", post(post_event_import))
+        .route("/events/:id/duplicate", post(post_event_duplicate))
+        .route("/admin/events/:id/archive", post(post_event_archive))
+        .route("/admin/events/:id/min_rating", post(post_event_min_rating))
+        .route(
+            "/admin/events/:id/registration",
+            post(post_event_registration),
+        )
+        .route("/admin/events/:id/delete", post(post_event_delete))
+        .route(
+            "/admin/events/:id/positions/:position_id",
+            post(post_set_position),
+        )
+        .route(
+            "/admin/solo_certs",
+            get(page_solo_certs).post(post_new_solo_cert),
+        )
+        .route("/admin/solo_certs/:id/delete", post(post_delete_solo_cert))
+        .route("/admin/solo_certs/reconcile", get(page_solo_cert_reconcile))
+        .route("/admin/roster/:cid", get(page_controller))
+        .route("/admin/roster/:cid/ois", post(post_change_ois))
+        .route(
+            "/admin/roster/:cid/feedback_contact",
+            post(post_feedback_contact),
+        )
+        .route("/admin/roster/:cid/note", post(post_new_staff_note))
+        .route("/admin/roster/:cid/history", get(snippet_rating_history))
+        .route(
+            "/admin/roster/:cid/training",
+            get(snippet_get_training_records),
+        )
+        .route("/admin/roster/:cid/sync", post(post_vatusa_sync))
+        .route("/admin/roster/:cid/refresh", post(post_vatusa_refresh))
+        .route("/controller/:cid/export", get(get_controller_export))
+        .route("/controller/my_training_given", get(page_my_training_given))
+        .route("/admin/config", get(get_site_config))
+        .route("/admin/logs", get(page_logs))
+        .route("/admin/log_files", get(page_log_files))
+        .route("/admin/cache", get(page_cache))
+        .route("/admin/cache/invalidate", post(post_cache_invalidate))
+        .route(
+            "/admin/maintenance",
+            get(page_maintenance).post(post_maintenance),
+        )
+        .route("/admin/removal_report", get(page_removal_report))
+        .route(
+            "/admin/resources",
+            get(page_resources)
+                .post(post_new_resource)
+                .layer(upload_limit.clone()),
+        )
+        .route(
+            "/admin/resources/:id",
+            post(post_edit_resource).layer(upload_limit),
+        )
+        .route("/admin/resources/:id/delete", post(post_delete_resource))
+        .route(
+            "/admin/resources/sop_coverage.csv",
+            get(get_sop_coverage_csv),
+        )
+        .route(
+            "/admin/resources/categories",
+            get(page_resource_categories).post(post_resource_categories),
+        )
+        .route("/admin/sync/activity", post(post_sync_activity))
+        .route("/admin/emails", get(page_emails))
+        .route("/admin/emails/test", post(post_test_email))
+        .route(
+            "/admin/certifications/bulk",
+            get(page_bulk_certifications).post(post_bulk_certifications),
+        )
+        .route(
+            "/admin/roles/bulk",
+            get(page_bulk_roles).post(post_bulk_roles),
+        )
 }
@@ -1,34 +1,56 @@
 //! Endpoints for getting information on the airspace.
 
 use crate::{
-    shared::{AppError, AppState, CacheEntry, UserInfo, SESSION_USER_INFO_KEY},
-    utils::{flashed_messages, get_simaware_data, parse_metar, GENERAL_HTTP_CLIENT},
+    shared::{config::Airport, AppError, AppState, CacheEntry, UserInfo, SESSION_USER_INFO_KEY},
+    utils::{
+        airport_coordinates, compare_hours, distance_nm, flashed_messages, flight_relevance,
+        get_member_stats, get_metars, get_online_facility_controllers, get_simaware_data,
+        get_vatsim_data, glance_distance, parse_metar, validate_banner_url, FlightRelevance,
+        GlanceSort, MemberStats, GENERAL_HTTP_CLIENT, GLANCE_MAX_DISTANCE,
+    },
 };
-use anyhow::anyhow;
 use axum::{
-    extract::State,
-    response::{Html, Redirect},
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
     Form, Router,
 };
 use itertools::Itertools;
-use log::warn;
+use log::{error, warn};
 use minijinja::{context, Environment};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{sync::Arc, time::Instant};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 use thousands::Separable;
+use tokio::task::JoinSet;
 use tower_sessions::Session;
-use vatsim_utils::live_api::Vatsim;
 
 /// Table of all the airspace's airports.
+///
+/// Airports without coordinates in the config get them from the bundled
+/// airport data, if it has them.
 async fn page_airports(
     State(state): State<Arc<AppState>>,
     session: Session,
 ) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
     let template = state.templates.get_template("airspace/airports")?;
-    let airports = &state.config.airports.all;
+    let config = state.config();
+    let airports: Vec<_> = config
+        .airports
+        .all
+        .iter()
+        .map(|airport| {
+            let mut airport = airport.clone();
+            if let Some((latitude, longitude)) =
+                airport_coordinates(&config.airports.all, &airport.code)
+            {
+                airport.latitude = Some(latitude);
+                airport.longitude = Some(longitude);
+            }
+            airport
+        })
+        .collect();
     let rendered = template.render(context! { user_info, airports })?;
     Ok(Html(rendered))
 }
@@ -48,60 +70,326 @@ async fn page_flights(
         altitude: String,
         speed: String,
         simaware_id: &'a str,
+        relevance: Option<FlightRelevance>,
     }
 
-    // cache this endpoint's returned data for 60 seconds
+    // cache this endpoint's returned data
     let cache_key = "ONLINE_FLIGHTS_FULL";
     if let Some(cached) = state.cache.get(&cache_key) {
         let elapsed = Instant::now() - cached.inserted;
-        if elapsed.as_secs() < 60 {
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
             return Ok(Html(cached.data));
         }
         state.cache.invalidate(&cache_key);
     }
 
-    let artcc_fields: Vec<_> = state
-        .config
-        .airports
-        .all
-        .iter()
-        .map(|airport| &airport.code)
-        .collect();
-    let vatsim_data = Vatsim::new().await?.get_v3_data().await?;
+    let vatsim_data = get_vatsim_data().await?;
     let simaware_data = get_simaware_data().await?;
     let flights: Vec<OnlineFlight> = vatsim_data
         .pilots
         .iter()
         .flat_map(|flight| {
-            if let Some(plan) = &flight.flight_plan {
-                let from = artcc_fields.contains(&&plan.departure);
-                let to = artcc_fields.contains(&&plan.arrival);
-                if from || to {
-                    Some(OnlineFlight {
-                        pilot_name: &flight.name,
+            let plan = flight.flight_plan.as_ref();
+            let departure = plan.map(|plan| plan.departure.as_str());
+            let arrival = plan.map(|plan| plan.arrival.as_str());
+            let relevance = flight_relevance(
+                &state.config(),
+                departure,
+                arrival,
+                (flight.latitude, flight.longitude),
+            )?;
+            Some(OnlineFlight {
+                pilot_name: &flight.name,
+                pilot_cid: flight.cid,
+                callsign: &flight.callsign,
+                departure: departure.unwrap_or_default(),
+                arrival: arrival.unwrap_or_default(),
+                altitude: flight.altitude.separate_with_commas(),
+                speed: flight.groundspeed.separate_with_commas(),
+                simaware_id: match simaware_data.get(&flight.cid) {
+                    Some(id) => id,
+                    None => "",
+                },
+                relevance: Some(relevance),
+            })
+        })
+        .collect();
+
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let template = state.templates.get_template("airspace/flights")?;
+    let rendered = template.render(context! { user_info, flights })?;
+    state
+        .cache
+        .insert(cache_key, CacheEntry::new(rendered.clone()));
+    Ok(Html(rendered))
+}
+
+/// A pilot connected to VATSIM, for the glance around an airport.
+#[derive(Serialize, Deserialize)]
+struct GlancePilot {
+    callsign: String,
+    pilot_name: String,
+    pilot_cid: u64,
+    departure: String,
+    arrival: String,
+    aircraft: String,
+    altitude: String,
+    speed: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GlanceQuery {
+    /// Radius in nautical miles; read as text so bad input can be reported
+    distance: Option<String>,
+    sort: Option<String>,
+    order: Option<String>,
+}
+
+/// A pilot within the glance radius, with their VATSIM hours if known.
+#[derive(Serialize)]
+struct GlanceRow {
+    pilot: GlancePilot,
+    distance: f64,
+    piloting_hours: Option<f64>,
+    controlling_hours: Option<f64>,
+}
+
+/// Most VATSIM member stats requests made at once for a glance.
+const GLANCE_STATS_CONCURRENCY: usize = 4;
+
+/// Most uncached member stats looked up for a single glance.
+const GLANCE_STATS_MAX_LOOKUPS: usize = 40;
+
+/// Piloting and controlling hours for the CIDs, from the cache where possible.
+///
+/// Only the first `GLANCE_STATS_MAX_LOOKUPS` uncached CIDs are requested, a few
+/// at a time, so pass them in order of importance. Members whose stats weren't
+/// looked up or couldn't be retrieved are left out of the map.
+async fn glance_member_stats(state: &AppState, cids: &[u64]) -> HashMap<u64, MemberStats> {
+    let cache_key = "PILOT_STATS";
+    let cached = state.cache.get(&cache_key).filter(|cached| {
+        (Instant::now() - cached.inserted).as_secs() < state.config().cache.ttl(cache_key)
+    });
+    let inserted = cached
+        .as_ref()
+        .map(|cached| cached.inserted)
+        .unwrap_or_else(Instant::now);
+    let mut stats: HashMap<u64, MemberStats> = cached
+        .and_then(|cached| serde_json::from_str(&cached.data).ok())
+        .unwrap_or_default();
+
+    let mut pending = cids
+        .iter()
+        .copied()
+        .filter(|cid| !stats.contains_key(cid))
+        .unique()
+        .take(GLANCE_STATS_MAX_LOOKUPS)
+        .collect::<Vec<_>>()
+        .into_iter();
+    let mut requests = JoinSet::new();
+    for cid in pending.by_ref().take(GLANCE_STATS_CONCURRENCY) {
+        requests.spawn(async move { (cid, get_member_stats(cid).await) });
+    }
+    if requests.is_empty() {
+        return stats;
+    }
+    while let Some(joined) = requests.join_next().await {
+        if let Some(cid) = pending.next() {
+            requests.spawn(async move { (cid, get_member_stats(cid).await) });
+        }
+        match joined {
+            Ok((cid, Ok(member_stats))) => {
+                stats.insert(cid, member_stats);
+            }
+            Ok((cid, Err(e))) => warn!("Could not get VATSIM stats for {cid}: {e}"),
+            Err(e) => warn!("VATSIM stats request failed: {e}"),
+        }
+    }
+    // keep the original timestamp so newly-added entries don't extend the old ones
+    if let Ok(data) = serde_json::to_string(&stats) {
+        state.cache.insert(cache_key, CacheEntry { inserted, data });
+    }
+    stats
+}
+
+/// Pilots within a radius of one of the airspace's airports.
+///
+/// The radius comes from the "distance" query parameter, defaulting to the
+/// configured glance distance and clamped to a sane maximum. Pilots are
+/// ordered by the "sort" and "order" parameters, defaulting to the least
+/// piloting time first.
+async fn page_glance(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(code): Path<String>,
+    Query(query): Query<GlanceQuery>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let config = state.config();
+    // airports outside the config can still be looked up in the bundled data
+    let airport = config
+        .airports
+        .all
+        .iter()
+        .find(|airport| airport.code.eq_ignore_ascii_case(&code))
+        .cloned()
+        .unwrap_or_else(|| Airport {
+            code: code.to_uppercase(),
+            ..Default::default()
+        });
+    let center = match airport_coordinates(&config.airports.all, &code) {
+        Some(center) => center,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Unknown airport, or it has no location",
+            )
+            .await?;
+            return Ok(Redirect::to("/airspace/airports").into_response());
+        }
+    };
+    let requested = query
+        .distance
+        .as_deref()
+        .map(str::trim)
+        .filter(|distance| !distance.is_empty());
+    let parsed = requested.map(str::parse::<f64>);
+    let invalid_distance = parsed.as_ref().is_some_and(|parsed| parsed.is_err());
+    let distance = glance_distance(
+        state.config().airports.glance_distance,
+        parsed.and_then(Result::ok),
+    );
+    let sort = query
+        .sort
+        .as_deref()
+        .and_then(GlanceSort::parse)
+        .unwrap_or_default();
+    let descending = query.order.as_deref() == Some("desc");
+
+    // the pilot list is shared by every airport's glance
+    let cache_key = "ONLINE_PILOTS";
+    let cached = state.cache.get(&cache_key).filter(|cached| {
+        (Instant::now() - cached.inserted).as_secs() < state.config().cache.ttl(cache_key)
+    });
+    let pilots: Vec<GlancePilot> = match cached {
+        Some(cached) => serde_json::from_str(&cached.data)?,
+        None => {
+            let vatsim_data = get_vatsim_data().await?;
+            let pilots: Vec<_> = vatsim_data
+                .pilots
+                .iter()
+                .map(|flight| {
+                    let plan = flight.flight_plan.as_ref();
+                    GlancePilot {
+                        callsign: flight.callsign.clone(),
+                        pilot_name: flight.name.clone(),
                         pilot_cid: flight.cid,
-                        callsign: &flight.callsign,
-                        departure: &plan.departure,
-                        arrival: &plan.arrival,
+                        departure: plan.map(|p| p.departure.clone()).unwrap_or_default(),
+                        arrival: plan.map(|p| p.arrival.clone()).unwrap_or_default(),
+                        aircraft: plan.map(|p| p.aircraft_short.clone()).unwrap_or_default(),
                         altitude: flight.altitude.separate_with_commas(),
                         speed: flight.groundspeed.separate_with_commas(),
-                        simaware_id: match simaware_data.get(&flight.cid) {
-                            Some(id) => id,
-                            None => "",
-                        },
-                    })
-                } else {
-                    None
-                }
-            } else {
-                None
+                        latitude: flight.latitude,
+                        longitude: flight.longitude,
+                    }
+                })
+                .collect();
+            state
+                .cache
+                .insert(cache_key, CacheEntry::new(serde_json::to_string(&pilots)?));
+            pilots
+        }
+    };
+    let mut nearby: Vec<_> = pilots
+        .into_iter()
+        .map(|pilot| {
+            let nm = distance_nm(center, (pilot.latitude, pilot.longitude));
+            (pilot, nm)
+        })
+        .filter(|(_, nm)| *nm <= distance)
+        .collect();
+    // the closest pilots get their stats looked up first
+    nearby.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    let cids: Vec<_> = nearby.iter().map(|(pilot, _)| pilot.pilot_cid).collect();
+    let stats = glance_member_stats(&state, &cids).await;
+    let mut pilots: Vec<_> = nearby
+        .into_iter()
+        .map(|(pilot, nm)| {
+            let member_stats = stats.get(&pilot.pilot_cid);
+            GlanceRow {
+                pilot,
+                distance: nm,
+                piloting_hours: member_stats.map(|s| s.pilot),
+                controlling_hours: member_stats.map(|s| s.atc),
             }
         })
         .collect();
+    pilots.sort_by(|a, b| {
+        let ordering = match sort {
+            GlanceSort::Callsign => a.pilot.callsign.cmp(&b.pilot.callsign),
+            GlanceSort::Aircraft => a.pilot.aircraft.cmp(&b.pilot.aircraft),
+            GlanceSort::Distance => a.distance.total_cmp(&b.distance),
+            GlanceSort::PilotingTime => {
+                return compare_hours(a.piloting_hours, b.piloting_hours, descending)
+            }
+            GlanceSort::ControllingTime => {
+                return compare_hours(a.controlling_hours, b.controlling_hours, descending)
+            }
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
 
+    let template = state.templates.get_template("airspace/glance")?;
+    let rendered = template.render(context! {
+        user_info,
+        airport,
+        pilots,
+        distance,
+        invalid_distance,
+        max_distance => GLANCE_MAX_DISTANCE,
+        sort => sort.as_str(),
+        descending,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Page of the controllers currently online in the airspace.
+///
+/// The table itself is loaded from `snippet_online_controllers` so that it can refresh.
+async fn page_online_controllers(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
-    let template = state.templates.get_template("airspace/flights")?;
-    let rendered = template.render(context! { user_info, flights })?;
+    let template = state.templates.get_template("airspace/controllers")?;
+    let rendered = template.render(context! { user_info })?;
+    Ok(Html(rendered))
+}
+
+/// Table of the controllers currently online in the airspace.
+async fn snippet_online_controllers(
+    State(state): State<Arc<AppState>>,
+) -> Result<Html<String>, AppError> {
+    // cache this endpoint's returned data
+    let cache_key = "ONLINE_CONTROLLERS_FULL";
+    if let Some(cached) = state.cache.get(&cache_key) {
+        let elapsed = Instant::now() - cached.inserted;
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
+            return Ok(Html(cached.data));
+        }
+        state.cache.invalidate(&cache_key);
+    }
+
+    let online = get_online_facility_controllers(&state.config(), &state.db).await?;
+    let template = state.templates.get_template("airspace/controllers_table")?;
+    let rendered = template.render(context! { online })?;
     state
         .cache
         .insert(cache_key, CacheEntry::new(rendered.clone()));
@@ -113,35 +401,34 @@ async fn page_weather(
     State(state): State<Arc<AppState>>,
     session: Session,
 ) -> Result<Html<String>, AppError> {
-    // cache this endpoint's returned data for 5 minutes
+    // cache this endpoint's returned data
     let cache_key = "WEATHER_FULL";
     if let Some(cached) = state.cache.get(&cache_key) {
         let elapsed = Instant::now() - cached.inserted;
-        if elapsed.as_secs() < 300 {
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
             return Ok(Html(cached.data));
         }
         state.cache.invalidate(&cache_key);
     }
 
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!(
-            "https://metar.vatsim.net/{}",
-            state
-                .config
-                .airports
-                .all
-                .iter()
-                .map(|airport| &airport.code)
-                .join(",")
-        ))
-        .send()
-        .await?;
-    if !resp.status().is_success() {
-        return Err(anyhow!("Got status {} from METAR API", resp.status().as_u16()).into());
-    }
-    let text = resp.text().await?;
+    let airports = state
+        .config()
+        .airports
+        .all
+        .iter()
+        .map(|airport| &airport.code)
+        .join(",");
+    let text = match get_metars(&airports).await {
+        Ok(text) => Some(text),
+        Err(e) => {
+            error!("Could not get METARs for the weather page: {e}");
+            None
+        }
+    };
+    let weather_unavailable = text.is_none();
     let weather: Vec<_> = text
-        .split_terminator('\n')
+        .iter()
+        .flat_map(|text| text.split_terminator('\n'))
         .flat_map(|line| {
             parse_metar(line).map_err(|e| {
                 let airport = line.split(' ').next().unwrap_or("Unknown");
@@ -153,10 +440,12 @@ async fn page_weather(
 
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
     let template = state.templates.get_template("airspace/weather")?;
-    let rendered = template.render(context! { user_info, weather })?;
-    state
-        .cache
-        .insert(cache_key, CacheEntry::new(rendered.clone()));
+    let rendered = template.render(context! { user_info, weather, weather_unavailable })?;
+    if !weather_unavailable {
+        state
+            .cache
+            .insert(cache_key, CacheEntry::new(rendered.clone()));
+    }
     Ok(Html(rendered))
 }
 
@@ -192,9 +481,18 @@ async fn page_staffing_request_post(
     Form(staffing_request): Form<StaffingRequestForm>,
 ) -> Result<Redirect, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await.unwrap();
+    if !validate_banner_url(&staffing_request.banner) {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "The banner must be an http(s) link to an image",
+        )
+        .await?;
+        return Ok(Redirect::to("/airspace/staffing_request"));
+    }
     if let Some(user_info) = user_info {
         let resp = GENERAL_HTTP_CLIENT
-            .post(&state.config.discord.webhooks.staffing_request)
+            .post(&state.config().discord.webhooks.staffing_request)
             .json(&json!({
                 "content": "",
                 "embeds": [{
@@ -285,6 +583,18 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
             include_str!("../../templates/airspace/flights.jinja"),
         )
         .unwrap();
+    templates
+        .add_template(
+            "airspace/controllers",
+            include_str!("../../templates/airspace/controllers.jinja"),
+        )
+        .unwrap();
+    templates
+        .add_template(
+            "airspace/controllers_table",
+            include_str!("../../templates/airspace/controllers_table.jinja"),
+        )
+        .unwrap();
     templates
         .add_template(
             "airspace/staffing_request",
@@ -297,11 +607,23 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
             include_str!("../../templates/airspace/weather.jinja"),
         )
         .unwrap();
+    templates
+        .add_template(
+            "airspace/glance",
+            include_str!("../../templates/airspace/glance.jinja"),
+        )
+        .unwrap();
     templates.add_filter("format_number", |value: u16| value.separate_with_commas());
 
     Router::new()
         .route("/airspace/airports", get(page_airports))
         .route("/airspace/flights", get(page_flights))
+        .route("/airspace/glance/:airport", get(page_glance))
+        .route("/airspace/controllers", get(page_online_controllers))
+        .route(
+            "/airspace/controllers/online",
+            get(snippet_online_controllers),
+        )
         .route("/airspace/weather", get(page_weather))
         .route("/airspace/staffing_request", get(page_staffing_request))
         .route(
@@ -0,0 +1,322 @@
+//! JSON endpoints for other facility services, like the Discord bot.
+//!
+//! Every endpoint here is under `/api`, and browsers on the origins in the
+//! `api.cors_allowed_origins` config can call them cross-origin. Cookies
+//! aren't allowed cross-origin, so the session-based endpoints (OI
+//! suggestions) only work from this site. The origins are read when the
+//! router is built, so changing them needs a restart.
+//!
+//! Write endpoints (ATIS updates), the bot's role mappings, and the API call
+//! metrics take an `ApiKey`, so they need one of the keys from the `api.keys`
+//! config.
+
+use crate::{
+    endpoints::admin::{reject_if_not_staff, StaffRequirement},
+    middleware::ApiKey,
+    shared::{
+        sql::{self, Atis, Controller, Event},
+        AppError, AppState, CacheEntry, Config, UserInfo, SESSION_USER_INFO_KEY,
+    },
+    utils::{
+        api_metrics::{self, ApiCallStats},
+        current_atis, determine_staff_positions, discord_roles_for, display_name, ois_suggestions,
+        verify_signature,
+    },
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Instant,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_sessions::Session;
+
+/// Public view of an event, also used to read other facilities' events.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiEvent {
+    pub id: u32,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+}
+
+impl From<Event> for ApiEvent {
+    fn from(event: Event) -> Self {
+        Self {
+            id: event.id,
+            name: event.name,
+            start: event.start,
+            end: event.end,
+            description: event.description,
+            image_url: event.image_url,
+        }
+    }
+}
+
+/// Published upcoming events.
+async fn get_events(State(state): State<Arc<AppState>>) -> Result<Json<Vec<ApiEvent>>, AppError> {
+    let events: Vec<Event> = sqlx::query_as(sql::GET_UPCOMING_EVENTS)
+        .bind(Utc::now())
+        .fetch_all(&state.db)
+        .await?;
+    Ok(Json(events.into_iter().map(ApiEvent::from).collect()))
+}
+
+/// Header containing the hex HMAC-SHA256 of an ATIS update's body.
+const ATIS_SIGNATURE_HEADER: &str = "X-Signature";
+
+/// ATIS update as sent by vATIS.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VatisUpdate {
+    facility: String,
+    atis_letter: String,
+    atis_type: String,
+    #[serde(default)]
+    airport_conditions: String,
+    #[serde(default)]
+    notams: String,
+    timestamp: Option<DateTime<Utc>>,
+}
+
+/// Outcomes of this site's calls to the VATUSA and VATSIM APIs since it started.
+///
+/// Requires an API key.
+async fn get_metrics(_: ApiKey) -> Json<BTreeMap<&'static str, ApiCallStats>> {
+    Json(api_metrics::snapshot())
+}
+
+/// Store an ATIS update from vATIS.
+///
+/// Requires an API key. If a shared secret is configured, the body must
+/// also be signed with it.
+async fn post_atis(
+    State(state): State<Arc<AppState>>,
+    ApiKey(key_name): ApiKey,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let secret = &state.config().atis.secret;
+    if !secret.is_empty() {
+        let signature = headers
+            .get(ATIS_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !verify_signature(secret, &body, signature) {
+            warn!("Rejected ATIS update from \"{key_name}\" with a missing or invalid signature");
+            return Ok(StatusCode::UNAUTHORIZED);
+        }
+    }
+    let update: VatisUpdate = match serde_json::from_slice(&body) {
+        Ok(update) => update,
+        Err(e) => {
+            warn!("Could not parse ATIS update: {e}");
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+    sqlx::query(sql::INSERT_ATIS_ENTRY)
+        .bind(update.facility.to_uppercase())
+        .bind(update.atis_letter)
+        .bind(update.atis_type)
+        .bind(update.airport_conditions)
+        .bind(update.notams)
+        .bind(update.timestamp.unwrap_or_else(Utc::now))
+        .execute(&state.db)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+/// Current ATIS for a facility; empty if there isn't one.
+async fn get_atis(
+    State(state): State<Arc<AppState>>,
+    Path(facility): Path<String>,
+) -> Result<Json<Vec<Atis>>, AppError> {
+    let entries: Vec<Atis> = sqlx::query_as(sql::GET_ALL_ATIS_ENTRIES)
+        .fetch_all(&state.db)
+        .await?;
+    Ok(Json(current_atis(entries, &facility, Utc::now())))
+}
+
+#[derive(Serialize)]
+struct RoleMappings {
+    cid: u32,
+    roles: Vec<String>,
+}
+
+/// Discord roles that a controller should have, for the bot's role sync.
+///
+/// Requires an API key.
+async fn get_role_mappings(
+    State(state): State<Arc<AppState>>,
+    _: ApiKey,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let controller = match controller {
+        Some(c) => c,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    let roles = discord_roles_for(&controller, &state.config());
+    Ok(Json(RoleMappings { cid, roles }).into_response())
+}
+
+/// Public profile card for a roster controller.
+///
+/// Only holds information already shown on the public roster; no contact
+/// details, Discord IDs, or staff notes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiControllerProfile {
+    pub cid: u32,
+    /// Name, shortened for controllers with name privacy enabled
+    pub name: String,
+    pub rating: String,
+    pub operating_initials: Option<String>,
+    pub roles: Vec<String>,
+    pub is_home: bool,
+}
+
+impl ApiControllerProfile {
+    fn new(controller: &Controller, state: &AppState) -> Self {
+        Self {
+            cid: controller.cid,
+            name: display_name(
+                &controller.first_name,
+                &controller.last_name,
+                controller.name_privacy,
+                false,
+            ),
+            rating: Controller::rating_name(controller.rating).to_owned(),
+            operating_initials: controller
+                .operating_initials
+                .clone()
+                .filter(|ois| !ois.is_empty()),
+            roles: determine_staff_positions(controller, &state.config()),
+            is_home: controller.home_facility == state.config().facility.code,
+        }
+    }
+}
+
+/// Public profile card of a controller on the roster.
+///
+/// The cards for the whole roster are built together and cached.
+async fn get_controller_profile(
+    State(state): State<Arc<AppState>>,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let cache_key = "CONTROLLER_PROFILES";
+    let cached = state.cache.get(&cache_key).filter(|cached| {
+        (Instant::now() - cached.inserted).as_secs() < state.config().cache.ttl(cache_key)
+    });
+    let mut profiles: HashMap<u32, ApiControllerProfile> = match cached {
+        Some(cached) => serde_json::from_str(&cached.data)?,
+        None => {
+            let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+                .fetch_all(&state.db)
+                .await?;
+            let profiles: HashMap<_, _> = controllers
+                .iter()
+                .map(|controller| {
+                    (
+                        controller.cid,
+                        ApiControllerProfile::new(controller, &state),
+                    )
+                })
+                .collect();
+            state.cache.insert(
+                cache_key,
+                CacheEntry::new(serde_json::to_string(&profiles)?),
+            );
+            profiles
+        }
+    };
+    match profiles.remove(&cid) {
+        Some(profile) => Ok(Json(profile).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// Unused operating initials staff could assign to a controller, best first.
+async fn get_ois_suggestions(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if let Some(redirect) =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::SeniorStaff).await
+    {
+        return Ok(redirect);
+    }
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(cid)
+        .fetch_optional(&state.db)
+        .await?;
+    let controller = match controller {
+        Some(controller) => controller,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    let in_use: Vec<String> = sqlx::query_scalar(sql::GET_ALL_OIS)
+        .fetch_all(&state.db)
+        .await?;
+    let suggestions = ois_suggestions(&controller.first_name, &controller.last_name, &in_use, 5);
+    Ok(Json(suggestions).into_response())
+}
+
+/// CORS for the configured origins, or `None` if there aren't any.
+///
+/// Origins that aren't valid header values are logged and skipped. The list
+/// is fixed when the router is built; a config reload keeps the old one.
+fn cors_layer(config: &Config) -> Option<CorsLayer> {
+    let origins: Vec<HeaderValue> = config
+        .api
+        .cors_allowed_origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Ignoring invalid CORS origin: {origin}");
+                None
+            }
+        })
+        .collect();
+    if origins.is_empty() {
+        return None;
+    }
+    Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST]),
+    )
+}
+
+/// This file's routes.
+pub fn router(config: &Config) -> Router<Arc<AppState>> {
+    let router = Router::new()
+        .route("/api/events", get(get_events))
+        .route("/api/atis", post(post_atis))
+        .route("/api/atis/:facility", get(get_atis))
+        .route("/api/role_mappings/:cid", get(get_role_mappings))
+        .route("/api/controller/:cid", get(get_controller_profile))
+        .route("/api/ois_suggestions/:cid", get(get_ois_suggestions))
+        .route("/api/metrics", get(get_metrics));
+    match cors_layer(config) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
@@ -34,7 +34,7 @@ async fn page_auth_login(
         debug!("Already logged-in user hit login page");
         return Ok(Redirect::to("/"));
     }
-    let redirect_url = oauth_redirect_start(&state.config);
+    let redirect_url = oauth_redirect_start(&state.config());
     Ok(Redirect::to(&redirect_url))
 }
 
@@ -47,15 +47,15 @@ async fn page_auth_callback(
     State(state): State<Arc<AppState>>,
     session: Session,
 ) -> Result<Html<String>, AppError> {
-    let token_data = code_to_tokens(&query.code, &state.config).await?;
-    let session_user_info = get_user_info(&token_data.access_token, &state.config).await?;
+    let token_data = code_to_tokens(&query.code, &state.config()).await?;
+    let session_user_info = get_user_info(&token_data.access_token, &state.config()).await?;
     let db_user_info: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
         .bind(&session_user_info.data.cid)
         .fetch_optional(&state.db)
         .await?;
-    let is_staff = match db_user_info {
-        Some(controller) => !controller.roles.is_empty(),
-        None => false,
+    let (is_staff, timezone) = match db_user_info {
+        Some(controller) => (!controller.roles.is_empty(), controller.timezone),
+        None => (false, None),
     };
 
     let to_session = UserInfo {
@@ -63,6 +63,7 @@ async fn page_auth_callback(
         first_name: session_user_info.data.personal.name_first,
         last_name: session_user_info.data.personal.name_last,
         is_staff,
+        timezone,
     };
     session
         .insert(SESSION_USER_INFO_KEY, to_session.clone())
@@ -4,19 +4,25 @@
 
 use crate::{
     shared::{
-        sql::{self, Event},
+        sql::{self, Controller, Event, EventAssignment, EventPosition, EventRegistration},
         AppError, AppState, UserInfo, SESSION_USER_INFO_KEY,
     },
-    utils::flashed_messages,
+    utils::{
+        category_full, flashed_messages, preferred_position, registration_waitlisted,
+        waitlist_candidates,
+    },
 };
 use axum::{
     extract::{Path, State},
     response::{Html, IntoResponse, Redirect, Response},
-    routing::get,
-    Router,
+    routing::{get, post},
+    Form, Router,
 };
+use chrono::Utc;
+use log::info;
 use minijinja::{context, Environment};
-use std::sync::Arc;
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
 use tower_sessions::Session;
 
 /// Render a snippet that lists published upcoming events.
@@ -28,13 +34,19 @@ async fn snippet_get_upcoming_events(
     session: Session,
 ) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let events: Vec<Event> = sqlx::query_as(sql::GET_UPCOMING_EVENTS)
+        .bind(sqlx::types::chrono::Utc::now())
+        .fetch_all(&state.db)
+        .await?;
     let template = state.templates.get_template("events/upcoming_events")?;
-    let rendered = template.render(context! { user_info })?;
+    let rendered = template.render(context! { user_info, events })?;
     Ok(Html(rendered))
 }
 
 /// Render the full page for a single event, including controls for signup.
 ///
+/// Archived events are only shown to staff members.
+///
 /// TODO decide if controls for editing the event will be rendered on this
 /// page or a separate page. Separate page would generally follow the patterns
 /// being established in this app, but joined would be inline with what the EC
@@ -49,10 +61,62 @@ async fn page_get_event(
         .bind(id)
         .fetch_optional(&state.db)
         .await?;
+    let is_staff = user_info.as_ref().is_some_and(|info| info.is_staff);
+    let event = event.filter(|event| !event.archived || is_staff);
     match event {
         Some(event) => {
+            let positions: Vec<EventPosition> = sqlx::query_as(sql::GET_EVENT_POSITIONS)
+                .bind(event.id)
+                .fetch_all(&state.db)
+                .await?;
+            let preferred_position_id = match &user_info {
+                Some(info) => {
+                    let preferences: Vec<String> =
+                        sqlx::query_scalar(sql::GET_POSITION_PREFERENCES)
+                            .bind(info.cid)
+                            .fetch_all(&state.db)
+                            .await?;
+                    preferred_position(&positions, &preferences)
+                }
+                None => None,
+            };
+            let registrations: Vec<EventRegistration> =
+                sqlx::query_as(sql::GET_EVENT_REGISTRATIONS)
+                    .bind(event.id)
+                    .fetch_all(&state.db)
+                    .await?;
+            let my_registration = user_info.as_ref().and_then(|info| {
+                registrations
+                    .iter()
+                    .find(|registration| registration.cid == info.cid)
+            });
+            let my_waitlisted = my_registration.is_some_and(|registration| {
+                registration_waitlisted(&positions, registration.position_id, registration.cid)
+            });
+            let waitlist = if is_staff {
+                waitlist_candidates(&positions, &registrations)
+            } else {
+                HashMap::new()
+            };
+            let min_rating_name = event.min_rating.map(Controller::rating_name);
+            let ratings: Vec<_> = (1..=12)
+                .map(|rating| (rating, Controller::rating_name(rating)))
+                .collect();
+            let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
             let template = state.templates.get_template("events/event")?;
-            let rendered = template.render(context! { user_info, event })?;
+            let rendered = template.render(context! {
+                user_info,
+                event,
+                positions,
+                preferred_position_id,
+                my_registration,
+                my_waitlisted,
+                waitlist,
+                min_rating_name,
+                ratings,
+                flashed_messages,
+                notify_assignments => state.config().email.notify_event_assignments,
+            })?;
             Ok(Html(rendered).into_response())
         }
         None => {
@@ -67,6 +131,153 @@ async fn page_get_event(
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct RegistrationForm {
+    position_id: u32,
+}
+
+/// Handler for a controller registering for an event and requesting a position.
+///
+/// Replaces any registration the controller already has for the event.
+async fn post_register_for_event(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+    Form(registration_form): Form<RegistrationForm>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let redirect = Redirect::to(&format!("/events/{id}")).into_response();
+    let cid = match user_info {
+        Some(info) => info.cid,
+        None => return Ok(redirect),
+    };
+    let event: Option<Event> = sqlx::query_as(sql::GET_EVENT)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let event = match event.filter(|e| e.published && !e.archived && e.end > Utc::now()) {
+        Some(event) => event,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "Event not found",
+            )
+            .await?;
+            return Ok(Redirect::to("/").into_response());
+        }
+    };
+    if !event.registration_open {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Registration for this event is closed",
+        )
+        .await?;
+        return Ok(redirect);
+    }
+    if let Some(min_rating) = event.min_rating {
+        let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+            .bind(cid)
+            .fetch_optional(&state.db)
+            .await?;
+        if controller.is_none_or(|c| c.rating < min_rating) {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                &format!(
+                    "This event requires a rating of {} or higher",
+                    Controller::rating_name(min_rating)
+                ),
+            )
+            .await?;
+            return Ok(redirect);
+        }
+    }
+    let positions: Vec<EventPosition> = sqlx::query_as(sql::GET_EVENT_POSITIONS)
+        .bind(event.id)
+        .fetch_all(&state.db)
+        .await?;
+    if !positions
+        .iter()
+        .any(|position| position.id == registration_form.position_id)
+    {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Position not found",
+        )
+        .await?;
+        return Ok(redirect);
+    }
+
+    let waitlisted = category_full(&positions, registration_form.position_id, cid);
+    let mut tx = state.db.begin().await?;
+    sqlx::query(sql::DELETE_EVENT_REGISTRATION_FOR)
+        .bind(event.id)
+        .bind(cid)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(sql::INSERT_EVENT_REGISTRATION)
+        .bind(event.id)
+        .bind(registration_form.position_id)
+        .bind(cid)
+        .bind(waitlisted)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!(
+        "{cid} registered for event {}{}",
+        event.id,
+        if waitlisted { " on the waitlist" } else { "" }
+    );
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        if waitlisted {
+            "Registered for the event; those positions are full, so you're on the waitlist"
+        } else {
+            "Registered for the event"
+        },
+    )
+    .await?;
+    Ok(redirect)
+}
+
+/// Page listing the upcoming events a controller has registered for.
+///
+/// Viewable by the controller themselves and by staff.
+async fn page_controller_events(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(cid): Path<u32>,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let allowed = user_info
+        .as_ref()
+        .is_some_and(|info| info.cid == cid || info.is_staff);
+    if !allowed {
+        return Ok(Redirect::to("/").into_response());
+    }
+    let mut assignments: Vec<EventAssignment> =
+        sqlx::query_as(sql::GET_UPCOMING_EVENT_ASSIGNMENTS_FOR)
+            .bind(cid)
+            .bind(sqlx::types::chrono::Utc::now())
+            .fetch_all(&state.db)
+            .await?;
+    for assignment in &mut assignments {
+        let positions: Vec<EventPosition> = sqlx::query_as(sql::GET_EVENT_POSITIONS)
+            .bind(assignment.event_id)
+            .fetch_all(&state.db)
+            .await?;
+        assignment.waitlisted =
+            registration_waitlisted(&positions, assignment.requested_position_id, cid);
+    }
+    let template = state.templates.get_template("events/controller_events")?;
+    let rendered = template.render(context! { user_info, cid, assignments })?;
+    Ok(Html(rendered).into_response())
+}
+
 /// This file's routes and templates.
 pub fn router(template: &mut Environment) -> Router<Arc<AppState>> {
     template
@@ -82,7 +293,16 @@ pub fn router(template: &mut Environment) -> Router<Arc<AppState>> {
         )
         .unwrap();
 
+    template
+        .add_template(
+            "events/controller_events",
+            include_str!("../../templates/events/controller_events.jinja"),
+        )
+        .unwrap();
+
     Router::new()
         .route("/events/", get(snippet_get_upcoming_events))
         .route("/events/:id", get(page_get_event))
+        .route("/events/:id/register", post(post_register_for_event))
+        .route("/controller/:cid/events", get(page_controller_events))
 }
@@ -1,23 +1,34 @@
 //! Endpoints for getting information on the facility.
 
 use crate::{
+    endpoints::admin::{reject_if_not_staff, StaffRequirement},
     shared::{
-        sql::{self, Activity, Certification, Controller, Resource, VisitorApplication},
+        sql::{
+            self, Activity, Certification, Controller, Resource, ScheduleEntry, SopInitial,
+            VisitorApplication,
+        },
         AppError, AppState, Config, UserInfo, SESSION_USER_INFO_KEY,
     },
-    utils::{determine_staff_positions, flashed_messages, vatusa},
+    utils::{
+        activity_violation, asset_content_disposition, asset_content_type, build_vcard,
+        determine_staff_positions, display_last_name, display_name, flashed_messages,
+        is_new_controller, is_on_loa, normalize_position, quarter_months, record_log,
+        resource_category_order, schedule_block_problem, vatusa, LogCategory, GENERAL_HTTP_CLIENT,
+    },
 };
 use axum::{
-    extract::State,
-    response::{Html, Redirect},
-    routing::get,
+    extract::{Path, State},
+    http::header,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
     Form, Router,
 };
 use chrono::{DateTime, Months, Utc};
 use itertools::Itertools;
-use log::warn;
+use log::{info, warn};
 use minijinja::{context, Environment};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
@@ -29,11 +40,18 @@ struct StaffPosition {
     short: &'static str,
     name: &'static str,
     order: u8,
-    controllers: Vec<Controller>,
+    controllers: Vec<StaffMember>,
     email: Option<String>,
     description: &'static str,
 }
 
+/// Name of someone in a staff position, respecting their name privacy.
+#[derive(Debug, Serialize)]
+struct StaffMember {
+    first_name: String,
+    last_name: String,
+}
+
 fn generate_staff_outline(config: &Config) -> HashMap<&'static str, StaffPosition> {
     let email_domain = &config.staff.email_domain;
     HashMap::from([
@@ -58,7 +76,7 @@ fn generate_staff_outline(config: &Config) -> HashMap<&'static str, StaffPositio
             name: "Training Administrator",
             order: 3,
             controllers: Vec::new(),
-            email: Some(format!("ta@{email_domain}")),
+            email: Some(config.staff.training_contact()),
             description: "Responsible for overseeing and management of the facility's training program and staff.",
         }),
         ("FE", StaffPosition {
@@ -131,14 +149,16 @@ fn generate_staff_outline(config: &Config) -> HashMap<&'static str, StaffPositio
 #[derive(Debug, Serialize)]
 struct ControllerWithCerts<'a> {
     cid: u32,
-    first_name: &'a str,
-    last_name: &'a str,
+    name: String,
     operating_initials: &'a str,
     rating: &'static str,
     is_home: bool,
     roles: String,
     certs: Vec<Certification>,
+    expired_certs: Vec<String>,
     loa_until: Option<DateTime<Utc>>,
+    /// Only set for training staff viewers
+    promotion_eligible: bool,
 }
 
 /// View the full roster.
@@ -153,6 +173,12 @@ async fn page_roster(
     let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS)
         .fetch_all(&state.db)
         .await?;
+    let now = Utc::now();
+    let show_full_names = user_info.as_ref().is_some_and(|info| info.is_staff);
+    let is_training_staff =
+        reject_if_not_staff(&state, &user_info, StaffRequirement::TrainingStaff)
+            .await
+            .is_none();
 
     let controllers_with_certs: Vec<_> = controllers
         .iter()
@@ -161,68 +187,119 @@ async fn page_roster(
                 Some(s) => s,
                 None => "",
             };
-            let roles = determine_staff_positions(controller, &state.config).join(", ");
+            let roles = determine_staff_positions(controller, &state.config()).join(", ");
 
             let certs = certifications
                 .iter()
                 .filter(|cert| cert.cid == controller.cid)
                 .cloned()
                 .collect::<Vec<_>>();
+            let expired_certs = certs
+                .iter()
+                .filter(|cert| cert.valid_until.is_some_and(|until| until < now))
+                .map(|cert| cert.name.clone())
+                .collect();
 
             ControllerWithCerts {
                 cid: controller.cid,
-                first_name: &controller.first_name,
-                last_name: &controller.last_name,
+                name: display_name(
+                    &controller.first_name,
+                    &controller.last_name,
+                    controller.name_privacy,
+                    show_full_names,
+                ),
                 operating_initials,
                 rating: Controller::rating_name(controller.rating),
-                is_home: controller.home_facility == "ZDV",
+                is_home: controller.home_facility == state.config().facility.code,
                 roles,
                 certs,
+                expired_certs,
                 loa_until: controller.loa_until,
+                promotion_eligible: is_training_staff && controller.promotion_eligible,
             }
         })
         .sorted_by(|a, b| Ord::cmp(&a.cid, &b.cid))
         .collect();
 
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
     let template = state.templates.get_template("facility/roster")?;
     let rendered = template.render(context! {
        user_info,
+       flashed_messages,
        controllers => controllers_with_certs
     })?;
     Ok(Html(rendered))
 }
 
-/// View the facility's staff.
-async fn page_staff(
-    State(state): State<Arc<AppState>>,
-    session: Session,
-) -> Result<Html<String>, AppError> {
-    let mut staff_map = generate_staff_outline(&state.config);
+/// Staff positions, in order, with the controllers in each.
+async fn load_staff(
+    state: &AppState,
+    show_full_names: bool,
+) -> Result<Vec<StaffPosition>, AppError> {
+    let mut staff_map = generate_staff_outline(&state.config());
     let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS)
         .fetch_all(&state.db)
         .await?;
     for controller in &controllers {
-        let roles = determine_staff_positions(controller, &state.config);
+        let roles = determine_staff_positions(controller, &state.config());
         for role in roles {
             if let Some(staff_pos) = staff_map.get_mut(role.as_str()) {
-                staff_pos.controllers.push(controller.clone());
+                staff_pos.controllers.push(StaffMember {
+                    first_name: controller.first_name.clone(),
+                    last_name: display_last_name(
+                        &controller.last_name,
+                        controller.name_privacy,
+                        show_full_names,
+                    ),
+                });
             } else {
                 warn!("No staff role found for: {role}");
             }
         }
     }
 
-    let staff: Vec<_> = staff_map
-        .values()
+    Ok(staff_map
+        .into_values()
         .sorted_by(|a, b| Ord::cmp(&a.order, &b.order))
-        .collect();
+        .collect())
+}
 
+/// View the facility's staff.
+async fn page_staff(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let show_full_names = user_info.as_ref().is_some_and(|info| info.is_staff);
+    let staff = load_staff(&state, show_full_names).await?;
     let template = state.templates.get_template("facility/staff")?;
     let rendered = template.render(context! { user_info, staff })?;
     Ok(Html(rendered))
 }
 
+/// Contact cards for the facility's staff, for importing into address books.
+///
+/// Positions without anyone in them are skipped.
+async fn staff_vcards(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+    let staff = load_staff(&state, false).await?;
+    let site_name = state.config().facility.site_name();
+    let cards: String = staff
+        .iter()
+        .flat_map(|position| {
+            position.controllers.iter().map(|controller| {
+                build_vcard(
+                    &site_name,
+                    &controller.first_name,
+                    &controller.last_name,
+                    position.name,
+                    position.email.as_deref(),
+                )
+            })
+        })
+        .collect();
+    Ok(([(header::CONTENT_TYPE, "text/vcard; charset=utf-8")], cards).into_response())
+}
+
 /// View all controller's recent (summarized) controlling activity.
 async fn page_activity(
     State(state): State<Arc<AppState>>,
@@ -252,9 +329,13 @@ async fn page_activity(
         rating: i8,
         months: Vec<ActivityMonth>,
         violation: bool,
+        on_loa: bool,
+        new_controller: bool,
     }
 
     // this could be a join, but oh well
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let show_full_names = user_info.as_ref().is_some_and(|info| info.is_staff);
     let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
         .fetch_all(&state.db)
         .await?;
@@ -284,6 +365,8 @@ async fn page_activity(
             .to_string(),
     ];
 
+    let current_quarter = quarter_months(state.config().currency.quarter, now, 0);
+
     // collect activity into months by controller
     let mut activity_data: Vec<ControllerActivity> = controllers
         .iter()
@@ -302,10 +385,22 @@ async fn page_activity(
                         .into()
                 })
                 .collect();
-            let violation = months.iter().take(3).map(|month| month.value).sum::<u32>() < 180; // 3 hours in a quarter
+            let quarter = this_controller
+                .iter()
+                .filter(|a| current_quarter.contains(&a.month))
+                .map(|a| a.minutes)
+                .sum::<u32>();
+            let violation = activity_violation(&state.config().currency, controller, quarter, now);
+            let on_loa = is_on_loa(controller, now);
+            let new_controller = is_new_controller(&state.config().currency, controller, now);
 
             ControllerActivity {
-                name: format!("{} {}", controller.first_name, controller.last_name),
+                name: display_name(
+                    &controller.first_name,
+                    &controller.last_name,
+                    controller.name_privacy,
+                    show_full_names,
+                ),
                 ois: match &controller.operating_initials {
                     Some(ois) => ois.to_owned(),
                     None => String::new(),
@@ -315,6 +410,8 @@ async fn page_activity(
                 rating: controller.rating,
                 months,
                 violation,
+                on_loa,
+                new_controller,
             }
         })
         .sorted_by(|a, b| Ord::cmp(&a.cid, &b.cid))
@@ -335,9 +432,9 @@ async fn page_activity(
             });
     }
 
-    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
     let template = state.templates.get_template("facility/activity")?;
-    let rendered = template.render(context! { user_info, activity_data })?;
+    let rendered = template.render(context! { user_info, activity_data, flashed_messages })?;
     Ok(Html(rendered))
 }
 
@@ -361,20 +458,348 @@ async fn page_resources(
         .into_iter()
         .sorted()
         .collect();
-    let categories: Vec<_> = state
-        .config
-        .database
-        .resource_category_ordering
-        .iter()
-        .filter(|category| categories.contains(category))
+    let categories: Vec<_> = resource_category_order(&state.config(), &state.db)
+        .await?
+        .into_iter()
+        .filter(|category| categories.contains(&category))
         .collect();
 
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let initialed: Vec<u32> = match &user_info {
+        Some(user_info) => {
+            let initials: Vec<SopInitial> = sqlx::query_as(sql::GET_SOP_INITIALS_FOR)
+                .bind(user_info.cid)
+                .fetch_all(&state.db)
+                .await?;
+            initials.iter().map(|initial| initial.resource_id).collect()
+        }
+        None => Vec::new(),
+    };
+    let sop_categories = &state.config().resources.sop_categories;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
     let template = state.templates.get_template("facility/resources")?;
-    let rendered = template.render(context! { user_info, resources, categories })?;
+    let rendered = template.render(context! {
+        user_info,
+        resources,
+        categories,
+        initialed,
+        sop_categories,
+        flashed_messages
+    })?;
+    Ok(Html(rendered))
+}
+
+/// Initial an SOP as having been read.
+async fn post_initial_sop(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+) -> Result<Redirect, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let user_info = match user_info {
+        Some(user_info) => user_info,
+        None => return Ok(Redirect::to("/facility/resources")),
+    };
+    let resource: Option<Resource> = sqlx::query_as(sql::GET_RESOURCE_BY_ID)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let resource = match resource {
+        Some(resource)
+            if state
+                .config()
+                .resources
+                .sop_categories
+                .contains(&resource.category) =>
+        {
+            resource
+        }
+        _ => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "That resource isn't an SOP.",
+            )
+            .await?;
+            return Ok(Redirect::to("/facility/resources"));
+        }
+    };
+
+    sqlx::query(sql::INSERT_SOP_INITIAL)
+        .bind(user_info.cid)
+        .bind(resource.id)
+        .bind(Utc::now())
+        .execute(&state.db)
+        .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        &format!("Initialed {}", resource.name),
+    )
+    .await?;
+    Ok(Redirect::to("/facility/resources"))
+}
+
+#[derive(Debug, Serialize)]
+struct ScheduleRow {
+    entry: ScheduleEntry,
+    name: String,
+    /// Whether the viewer can remove the entry
+    removable: bool,
+}
+
+/// Page of when controllers plan to be online, and a form to add to it.
+async fn page_schedule(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Html<String>, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let is_moderator = reject_if_not_staff(&state, &user_info, StaffRequirement::EventStaff)
+        .await
+        .is_none();
+    let entries: Vec<ScheduleEntry> = sqlx::query_as(sql::GET_UPCOMING_SCHEDULE)
+        .bind(Utc::now())
+        .fetch_all(&state.db)
+        .await?;
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let show_full_names = user_info.as_ref().is_some_and(|info| info.is_staff);
+    let viewer_cid = user_info.as_ref().map(|info| info.cid);
+    let schedule: Vec<ScheduleRow> = entries
+        .into_iter()
+        .map(|entry| {
+            let name = controllers
+                .iter()
+                .find(|c| c.cid == entry.cid)
+                .map(|c| display_name(&c.first_name, &c.last_name, c.name_privacy, show_full_names))
+                .unwrap_or_else(|| entry.cid.to_string());
+            ScheduleRow {
+                removable: is_moderator || viewer_cid == Some(entry.cid),
+                name,
+                entry,
+            }
+        })
+        .collect();
+    let can_post = viewer_cid.is_some_and(|cid| controllers.iter().any(|c| c.cid == cid));
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("facility/schedule")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        schedule,
+        can_post,
+        announce_available => !state.config().discord.webhooks.schedule.is_empty(),
+    })?;
     Ok(Html(rendered))
 }
 
+#[derive(Debug, Deserialize)]
+struct ScheduleForm {
+    position: String,
+    /// From a "datetime-local" input, in UTC
+    start: String,
+    /// From a "datetime-local" input, in UTC
+    end: String,
+    announce: Option<String>,
+}
+
+/// Handler for a controller on the roster adding to the schedule.
+///
+/// If they ask and the webhook is configured, the block is also posted to Discord.
+async fn post_schedule(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(form): Form<ScheduleForm>,
+) -> Result<Redirect, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let user_info = match user_info {
+        Some(user_info) => user_info,
+        None => return Ok(Redirect::to("/facility/schedule")),
+    };
+    let controller: Option<Controller> = sqlx::query_as(sql::GET_CONTROLLER_BY_CID)
+        .bind(user_info.cid)
+        .fetch_optional(&state.db)
+        .await?;
+    if !controller.is_some_and(|c| c.is_on_roster) {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Only controllers on the roster can post to the schedule",
+        )
+        .await?;
+        return Ok(Redirect::to("/facility/schedule"));
+    }
+    let config = state.config();
+    let position = match normalize_position(&config, &form.position) {
+        Some(position) => position,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "That isn't one of the facility's positions",
+            )
+            .await?;
+            return Ok(Redirect::to("/facility/schedule"));
+        }
+    };
+    let parse = |s: &str| {
+        chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
+            .ok()
+            .map(|date| date.and_utc())
+    };
+    let (start, end) = match (parse(&form.start), parse(&form.end)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "A start and an end are required",
+            )
+            .await?;
+            return Ok(Redirect::to("/facility/schedule"));
+        }
+    };
+    let now = Utc::now();
+    if let Some(problem) = schedule_block_problem(start, end, now) {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            &problem,
+        )
+        .await?;
+        return Ok(Redirect::to("/facility/schedule"));
+    }
+    sqlx::query(sql::INSERT_SCHEDULE_ENTRY)
+        .bind(user_info.cid)
+        .bind(&position)
+        .bind(start)
+        .bind(end)
+        .bind(now)
+        .execute(&state.db)
+        .await?;
+    info!(
+        "{} added {position} from {start} to {end} to the schedule",
+        user_info.cid
+    );
+
+    let webhook = &config.discord.webhooks.schedule;
+    if form.announce.is_some() && !webhook.is_empty() {
+        let sent = GENERAL_HTTP_CLIENT
+            .post(webhook)
+            .json(&json!({
+                "content": "",
+                "embeds": [{
+                    "title": format!("{} plans to open {position}", user_info.first_name),
+                    "description": format!(
+                        "<t:{}:F> to <t:{}:t>",
+                        start.timestamp(),
+                        end.timestamp()
+                    ),
+                }]
+            }))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        if let Err(e) = sent {
+            warn!("Could not announce schedule block: {e}");
+        }
+    }
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Added to the schedule",
+    )
+    .await?;
+    Ok(Redirect::to("/facility/schedule"))
+}
+
+/// Handler for removing a block from the schedule.
+///
+/// Controllers can remove their own; events staff can remove anyone's.
+async fn post_schedule_delete(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(id): Path<u32>,
+) -> Result<Redirect, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let entry: Option<ScheduleEntry> = sqlx::query_as(sql::GET_SCHEDULE_ENTRY)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+    let (user_info, entry) = match (user_info, entry) {
+        (Some(user_info), Some(entry)) => (user_info, entry),
+        _ => return Ok(Redirect::to("/facility/schedule")),
+    };
+    if entry.cid != user_info.cid {
+        let viewer = Some(user_info.clone());
+        if reject_if_not_staff(&state, &viewer, StaffRequirement::EventStaff)
+            .await
+            .is_some()
+        {
+            return Ok(Redirect::to("/facility/schedule"));
+        }
+        record_log(
+            &state.db,
+            LogCategory::Audit,
+            Some(entry.cid),
+            &format!(
+                "{} removed {}'s schedule block on {}",
+                user_info.cid, entry.cid, entry.position
+            ),
+        )
+        .await?;
+    }
+    sqlx::query(sql::DELETE_SCHEDULE_ENTRY)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Info,
+        "Removed from the schedule",
+    )
+    .await?;
+    Ok(Redirect::to("/facility/schedule"))
+}
+
+/// Serve a resource's uploaded file.
+///
+/// Only files that a resource points to are served, with a content type
+/// and disposition so they open in the browser under their original name.
+async fn get_asset(
+    State(state): State<Arc<AppState>>,
+    Path(file_name): Path<String>,
+) -> Result<Response, AppError> {
+    let resource: Option<Resource> = sqlx::query_as(sql::GET_RESOURCE_BY_FILE_NAME)
+        .bind(&file_name)
+        .fetch_optional(&state.db)
+        .await?;
+    if resource.is_none() {
+        return Ok(Redirect::to("/404").into_response());
+    }
+    let data = match tokio::fs::read(std::path::Path::new("./assets").join(&file_name)).await {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Could not read asset {file_name}: {e}");
+            return Ok(Redirect::to("/404").into_response());
+        }
+    };
+    let (content_type, _) = asset_content_type(&file_name);
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                asset_content_disposition(&file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        ],
+        data,
+    )
+        .into_response())
+}
+
 /// Check visitor requirements and submit an application.
 async fn page_visitor_application(
     State(state): State<Arc<AppState>>,
@@ -415,7 +840,7 @@ async fn page_visitor_application_form(
     };
     // check VATUSA checklist
     let checklist = match vatusa::transfer_checklist(
-        &state.config.vatsim.vatusa_api_key,
+        &state.config().vatsim.vatusa_api_key,
         user_info.cid,
     )
     .await
@@ -430,48 +855,78 @@ async fn page_visitor_application_form(
     let template = state
         .templates
         .get_template("facility/visitor_application_form")?;
-    let rendered =
-        template.render(context! { user_info, pending_request, controller_info, checklist })?;
+    let rendered = template.render(context! {
+        user_info,
+        pending_request,
+        controller_info,
+        checklist,
+        min_rating => state.config().visitors.min_rating,
+    })?;
     Ok(Html(rendered))
 }
 
-#[derive(Debug, Deserialize)]
-struct VisitorApplicationForm {
-    rating: u8,
-    facility: String,
-}
-
 /// Submit the request to join as a visitor.
+///
+/// The controller's rating and home facility are taken from VATUSA rather than
+/// the form so they can't be forged, and the rating is checked against the
+/// configured minimum.
 async fn page_visitor_application_form_submit(
     State(state): State<Arc<AppState>>,
     session: Session,
-    Form(application_form): Form<VisitorApplicationForm>,
 ) -> Result<Redirect, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
-    if let Some(user_info) = user_info {
-        sqlx::query(sql::INSERT_INTO_VISITOR_REQ)
-            .bind(user_info.cid)
-            .bind(&user_info.first_name)
-            .bind(&user_info.last_name)
-            .bind(application_form.facility)
-            .bind(application_form.rating)
-            .bind(sqlx::types::chrono::Utc::now())
-            .execute(&state.db)
+    let user_info = match user_info {
+        Some(user_info) => user_info,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "You must be logged in to submit a visitor request.",
+            )
             .await?;
-        flashed_messages::push_flashed_message(
-            session,
-            flashed_messages::FlashedMessageLevel::Success,
-            "Request submitted, thank you!",
-        )
-        .await?;
-    } else {
-        flashed_messages::push_flashed_message(
-            session,
-            flashed_messages::FlashedMessageLevel::Error,
-            "You must be logged in to submit a visitor request.",
-        )
+            return Ok(Redirect::to("/facility/visitor_application"));
+        }
+    };
+    let min_rating = state.config().visitors.min_rating;
+    let controller_info = match vatusa::get_controller_info(user_info.cid).await {
+        Ok(info) if info.rating < min_rating => Err(format!(
+            "Visitors must be rated {} or higher.",
+            Controller::rating_name(min_rating as i8)
+        )),
+        Ok(info) => Ok(info),
+        Err(e) => {
+            warn!("Could not check visitor rating for {}: {e}", user_info.cid);
+            Err("Could not check your rating with VATUSA. Please try again later.".to_owned())
+        }
+    };
+    let controller_info = match controller_info {
+        Ok(info) => info,
+        Err(error) => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                &error,
+            )
+            .await?;
+            return Ok(Redirect::to("/facility/visitor_application"));
+        }
+    };
+
+    sqlx::query(sql::INSERT_INTO_VISITOR_REQ)
+        .bind(user_info.cid)
+        .bind(&user_info.first_name)
+        .bind(&user_info.last_name)
+        .bind(&controller_info.facility)
+        .bind(controller_info.rating)
+        .bind(sqlx::types::chrono::Utc::now())
+        .execute(&state.db)
         .await?;
-    }
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Request submitted, thank you!",
+    )
+    .await?;
     Ok(Redirect::to("/facility/visitor_application"))
 }
 
@@ -512,6 +967,12 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
             include_str!("../../templates/facility/visitor_application_form.jinja"),
         )
         .unwrap();
+    templates
+        .add_template(
+            "facility/schedule",
+            include_str!("../../templates/facility/schedule.jinja"),
+        )
+        .unwrap();
     templates.add_filter("minutes_to_hm", |total_minutes: u32| {
         let hours = total_minutes / 60;
         let minutes = total_minutes % 60;
@@ -531,8 +992,13 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
     Router::new()
         .route("/facility/roster", get(page_roster))
         .route("/facility/staff", get(page_staff))
+        .route("/facility/staff.vcf", get(staff_vcards))
         .route("/facility/activity", get(page_activity))
         .route("/facility/resources", get(page_resources))
+        .route("/facility/resources/:id/initial", post(post_initial_sop))
+        .route("/facility/schedule", get(page_schedule).post(post_schedule))
+        .route("/facility/schedule/:id/delete", post(post_schedule_delete))
+        .route("/assets/:file_name", get(get_asset))
         .route(
             "/facility/visitor_application",
             get(page_visitor_application),
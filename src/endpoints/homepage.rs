@@ -1,20 +1,28 @@
 //! HTTP endpoints for the homepage.
 
 use crate::{
-    shared::{AppError, AppState, CacheEntry, UserInfo, SESSION_USER_INFO_KEY},
+    shared::{
+        sql::{self, Atis},
+        AppError, AppState, CacheEntry, UserInfo, SESSION_USER_INFO_KEY,
+    },
     utils::{
-        flashed_messages, get_controller_cids_and_names, parse_metar, parse_vatsim_timestamp,
-        position_in_facility_airspace, GENERAL_HTTP_CLIENT,
+        current_atis, flashed_messages, get_metars, get_online_facility_controllers,
+        get_vatsim_data, parse_metar,
     },
 };
-use anyhow::{anyhow, Result};
-use axum::{extract::State, response::Html, routing::get, Router};
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    response::Html,
+    routing::get,
+    Router,
+};
+use chrono::Utc;
 use log::{error, warn};
 use minijinja::{context, Environment};
 use serde::Serialize;
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{sync::Arc, time::Instant};
 use tower_sessions::Session;
-use vatsim_utils::live_api::Vatsim;
 
 /// Homepage.
 async fn page_home(
@@ -32,54 +40,17 @@ async fn page_home(
 async fn snippet_online_controllers(
     State(state): State<Arc<AppState>>,
 ) -> Result<Html<String>, AppError> {
-    #[derive(Serialize)]
-    struct OnlineController {
-        cid: u64,
-        callsign: String,
-        name: String,
-        online_for: String,
-    }
-
-    // cache this endpoint's returned data for 60 seconds
+    // cache this endpoint's returned data
     let cache_key = "ONLINE_CONTROLLERS";
     if let Some(cached) = state.cache.get(&cache_key) {
         let elapsed = Instant::now() - cached.inserted;
-        if elapsed.as_secs() < 60 {
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
             return Ok(Html(cached.data));
         }
         state.cache.invalidate(&cache_key);
     }
 
-    let cid_name_map = match get_controller_cids_and_names(&state.db).await {
-        Ok(map) => map,
-        Err(e) => {
-            error!("Error generating controller CID -> name map: {e}");
-            HashMap::new()
-        }
-    };
-
-    let now = chrono::Utc::now();
-    let data = Vatsim::new().await?.get_v3_data().await?;
-    let online: Vec<_> = data
-        .controllers
-        .iter()
-        .filter(|controller| position_in_facility_airspace(&state.config, &controller.callsign))
-        .map(|controller| {
-            let logon = parse_vatsim_timestamp(&controller.logon_time)
-                .expect("Could not parse VATSIM timestamp");
-            let seconds = (now - logon).num_seconds() as u32;
-            OnlineController {
-                cid: controller.cid,
-                callsign: controller.callsign.clone(),
-                name: cid_name_map
-                    .get(&controller.cid)
-                    .map(|s| format!("{} {}", s.0, s.1))
-                    .unwrap_or(String::from("?")),
-                online_for: format!("{}h{}m", seconds / 3600, (seconds / 60) % 60),
-            }
-        })
-        .collect();
-
+    let online = get_online_facility_controllers(&state.config(), &state.db).await?;
     let template = state
         .templates
         .get_template("homepage/online_controllers")?;
@@ -90,30 +61,42 @@ async fn snippet_online_controllers(
     Ok(Html(rendered))
 }
 
+/// Render the current ATIS for an airport.
+async fn snippet_atis(
+    State(state): State<Arc<AppState>>,
+    Path(facility): Path<String>,
+) -> Result<Html<String>, AppError> {
+    let entries: Vec<Atis> = sqlx::query_as(sql::GET_ALL_ATIS_ENTRIES)
+        .fetch_all(&state.db)
+        .await?;
+    let atis = current_atis(entries, &facility, Utc::now());
+    let template = state.templates.get_template("homepage/atis")?;
+    let rendered = template.render(context! { facility => facility.to_uppercase(), atis })?;
+    Ok(Html(rendered))
+}
+
 async fn snippet_weather(State(state): State<Arc<AppState>>) -> Result<Html<String>, AppError> {
-    // cache this endpoint's returned data for 5 minutes
+    // cache this endpoint's returned data
     let cache_key = "WEATHER_BRIEF";
     if let Some(cached) = state.cache.get(&cache_key) {
         let elapsed = Instant::now() - cached.inserted;
-        if elapsed.as_secs() < 300 {
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
             return Ok(Html(cached.data));
         }
         state.cache.invalidate(&cache_key);
     }
 
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!(
-            "https://metar.vatsim.net/{}",
-            state.config.airports.weather_for.join(",")
-        ))
-        .send()
-        .await?;
-    if !resp.status().is_success() {
-        return Err(anyhow!("Got status {} from METAR API", resp.status().as_u16()).into());
-    }
-    let text = resp.text().await?;
+    let text = match get_metars(&state.config().airports.weather_for.join(",")).await {
+        Ok(text) => Some(text),
+        Err(e) => {
+            error!("Could not get METARs for the homepage: {e}");
+            None
+        }
+    };
+    let weather_unavailable = text.is_none();
     let weather: Vec<_> = text
-        .split_terminator('\n')
+        .iter()
+        .flat_map(|text| text.split_terminator('\n'))
         .flat_map(|line| {
             parse_metar(line).map_err(|e| {
                 let airport = line.split(' ').next().unwrap_or("Unknown");
@@ -124,10 +107,12 @@ async fn snippet_weather(State(state): State<Arc<AppState>>) -> Result<Html<Stri
         .collect();
 
     let template = state.templates.get_template("homepage/weather")?;
-    let rendered = template.render(context! { weather })?;
-    state
-        .cache
-        .insert(cache_key, CacheEntry::new(rendered.clone()));
+    let rendered = template.render(context! { weather, weather_unavailable })?;
+    if !weather_unavailable {
+        state
+            .cache
+            .insert(cache_key, CacheEntry::new(rendered.clone()));
+    }
     Ok(Html(rendered))
 }
 
@@ -139,24 +124,24 @@ async fn snippet_flights(State(state): State<Arc<AppState>>) -> Result<Html<Stri
         to: u16,
     }
 
-    // cache this endpoint's returned data for 60 seconds
+    // cache this endpoint's returned data
     let cache_key = "ONLINE_FLIGHTS_HOMEPAGE";
     if let Some(cached) = state.cache.get(&cache_key) {
         let elapsed = Instant::now() - cached.inserted;
-        if elapsed.as_secs() < 60 {
+        if elapsed.as_secs() < state.config().cache.ttl(cache_key) {
             return Ok(Html(cached.data));
         }
         state.cache.invalidate(&cache_key);
     }
 
-    let artcc_fields: Vec<_> = state
-        .config
+    let config = state.config();
+    let artcc_fields: Vec<_> = config
         .airports
         .all
         .iter()
         .map(|airport| &airport.code)
         .collect();
-    let data = Vatsim::new().await?.get_v3_data().await?;
+    let data = get_vatsim_data().await?;
     let flights: OnlineFlights =
         data.pilots
             .iter()
@@ -202,6 +187,12 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
             include_str!("../../templates/homepage/weather.jinja"),
         )
         .unwrap();
+    templates
+        .add_template(
+            "homepage/atis",
+            include_str!("../../templates/homepage/atis.jinja"),
+        )
+        .unwrap();
     templates
         .add_template(
             "homepage/flights",
@@ -214,4 +205,5 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
         .route("/home/online/controllers", get(snippet_online_controllers))
         .route("/home/online/flights", get(snippet_flights))
         .route("/home/weather", get(snippet_weather))
+        .route("/home/atis/:facility", get(snippet_atis))
 }
@@ -1,40 +1,121 @@
 //! HTTP endpoints.
 
 use crate::{
-    shared::{sql, AppError, AppState, UserInfo, SESSION_USER_INFO_KEY},
-    utils::flashed_messages,
+    shared::{
+        sql::{self, Controller, Feedback},
+        AppError, AppState, UserInfo, SESSION_USER_INFO_KEY,
+    },
+    utils::{
+        controller_by_display_name, display_name, feedback_contact_blocked, flashed_messages,
+        local_date, matches_route, normalize_position, post_feedback_to_discord, record_log,
+        suggest_route, LogCategory, UNCREDITED_CONTROLLER,
+    },
 };
 use anyhow::Result;
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::Uri,
     response::{Html, Redirect},
     routing::{get, post},
     Form, Router,
 };
+use itertools::Itertools;
+use log::warn;
 use minijinja::{context, Environment};
 use serde::Deserialize;
 use std::sync::Arc;
-use tower_http::services::ServeDir;
 use tower_sessions::Session;
 
 pub mod admin;
 pub mod airspace;
+pub mod api;
 pub mod auth;
 pub mod events;
 pub mod facility;
 pub mod homepage;
+pub mod news;
 pub mod user;
 
+/// Pages that users might navigate to directly, for suggesting on 404s.
+///
+/// Each must be a path registered in one of the routers; a test checks this.
+pub(crate) const PAGE_ROUTES: [&str; 44] = [
+    "/",
+    "/admin/certifications/bulk",
+    "/admin/roles/bulk",
+    "/admin/config",
+    "/admin/emails",
+    "/admin/events/archived",
+    "/admin/events/import",
+    "/admin/logs",
+    "/admin/maintenance",
+    "/admin/log_files",
+    "/admin/cache",
+    "/admin/removal_report",
+    "/admin/resources",
+    "/admin/resources/categories",
+    "/admin/feedback",
+    "/admin/feedback/stats",
+    "/admin/news",
+    "/admin/roster/:cid",
+    "/admin/solo_certs",
+    "/admin/solo_certs/reconcile",
+    "/airspace/airports",
+    "/airspace/flights",
+    "/airspace/controllers",
+    "/airspace/staffing_request",
+    "/airspace/weather",
+    "/controller/:cid/events",
+    "/controller/:cid/export",
+    "/controller/my_training_given",
+    "/events/:id",
+    "/facility/activity",
+    "/facility/resources",
+    "/facility/roster",
+    "/facility/schedule",
+    "/facility/staff",
+    "/facility/visitor_application",
+    "/feedback",
+    "/news",
+    "/news.xml",
+    "/user/certifications",
+    "/user/discord",
+    "/user/positions",
+    "/user/timezone",
+    "/user/training_notes",
+    "/auth/log_in",
+];
+
+/// Fallback for requests that don't match any route.
+///
+/// Redirects to the 404 page, along with the closest known page, if any.
+pub async fn fallback(uri: Uri) -> Redirect {
+    match suggest_route(uri.path(), &PAGE_ROUTES) {
+        Some(suggestion) => Redirect::to(&format!("/404?suggestion={suggestion}")),
+        None => Redirect::to("/404"),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NotFoundQuery {
+    suggestion: Option<String>,
+}
+
 /// 404 not found page.
 ///
 /// Redirected to whenever the router cannot find a valid handler for the requested path.
 async fn page_404(
     State(state): State<Arc<AppState>>,
     session: Session,
+    Query(query): Query<NotFoundQuery>,
 ) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    // only show suggestions that could have come from `fallback`
+    let suggestion = query
+        .suggestion
+        .filter(|suggestion| matches_route(suggestion, &PAGE_ROUTES));
     let template = state.templates.get_template("404")?;
-    let rendered = template.render(context! { user_info })?;
+    let rendered = template.render(context! { user_info, suggestion })?;
     Ok(Html(rendered))
 }
 
@@ -46,20 +127,88 @@ async fn page_feedback_form(
     session: Session,
 ) -> Result<Html<String>, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let show_full_names = user_info.as_ref().is_some_and(|info| info.is_staff);
+    let controllers: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let controllers: Vec<_> = controllers
+        .iter()
+        .filter(|controller| !controller.no_feedback_contact)
+        .map(|controller| {
+            display_name(
+                &controller.first_name,
+                &controller.last_name,
+                controller.name_privacy,
+                show_full_names,
+            )
+        })
+        .sorted()
+        .collect();
     let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
     let template = state.templates.get_template("feedback")?;
-    let rendered = template.render(context! { user_info, flashed_messages })?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        controllers,
+        max_comment_length => state.config().feedback.max_comment_length,
+    })?;
     Ok(Html(rendered))
 }
 
 #[derive(Debug, Deserialize)]
 struct FeedbackForm {
+    #[serde(default)]
     controller: String,
+    /// Set if the pilot doesn't know who was working the position
+    unknown_controller: Option<String>,
     position: String,
     rating: String,
     comments: String,
 }
 
+/// Post feedback straight to Discord if its rating is configured to skip review.
+///
+/// If posting fails, the feedback is left in the review queue.
+async fn auto_approve_feedback(state: &AppState, id: i64) -> Result<()> {
+    let feedback: Feedback = sqlx::query_as(sql::GET_FEEDBACK_BY_ID)
+        .bind(id)
+        .fetch_one(&state.db)
+        .await?;
+    let blocked: Vec<Controller> = sqlx::query_as(sql::GET_NO_FEEDBACK_CONTACT_CONTROLLERS)
+        .fetch_all(&state.db)
+        .await?;
+    // uncredited feedback needs staff to attribute it first
+    if feedback.controller == UNCREDITED_CONTROLLER
+        || feedback_contact_blocked(&blocked, &feedback.controller)
+        || !state
+            .config()
+            .feedback
+            .auto_approve_ratings
+            .contains(&feedback.rating)
+    {
+        return Ok(());
+    }
+    if let Err(e) = post_feedback_to_discord(&state.config(), &feedback).await {
+        warn!("Could not auto-approve feedback {id}, leaving it for review: {e}");
+        return Ok(());
+    }
+    sqlx::query(sql::UPDATE_FEEDBACK_AUTO_APPROVED)
+        .bind(id)
+        .execute(&state.db)
+        .await?;
+    record_log(
+        &state.db,
+        LogCategory::Info,
+        None,
+        &format!(
+            "Feedback {id} for {} on {} auto-approved with rating {}",
+            feedback.controller, feedback.position, feedback.rating
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Submit the feedback form.
 async fn page_feedback_form_post(
     State(state): State<Arc<AppState>>,
@@ -67,16 +216,62 @@ async fn page_feedback_form_post(
     Form(feedback): Form<FeedbackForm>,
 ) -> Result<Redirect, AppError> {
     let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let position = match normalize_position(&state.config(), &feedback.position) {
+        Some(p) => p,
+        None => {
+            flashed_messages::push_flashed_message(
+                session,
+                flashed_messages::FlashedMessageLevel::Error,
+                "That doesn't look like one of our positions, e.g. DEN_TWR",
+            )
+            .await?;
+            return Ok(Redirect::to("/feedback"));
+        }
+    };
+    let controller = feedback.controller.trim();
+    let controller = if feedback.unknown_controller.is_some() {
+        UNCREDITED_CONTROLLER
+    } else if controller.is_empty() {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            "Enter the controller, or check that you don't know who it was",
+        )
+        .await?;
+        return Ok(Redirect::to("/feedback"));
+    } else {
+        controller
+    };
+    // the form lists controllers with name privacy by first name and last initial
+    let roster: Vec<Controller> = sqlx::query_as(sql::GET_ALL_CONTROLLERS_ON_ROSTER)
+        .fetch_all(&state.db)
+        .await?;
+    let controller = match controller_by_display_name(&roster, controller) {
+        Some(c) => format!("{} {}", c.first_name, c.last_name),
+        None => controller.to_owned(),
+    };
+    let max_length = state.config().feedback.max_comment_length;
+    if feedback.comments.chars().count() > max_length {
+        flashed_messages::push_flashed_message(
+            session,
+            flashed_messages::FlashedMessageLevel::Error,
+            &format!("Comments can be at most {max_length} characters"),
+        )
+        .await?;
+        return Ok(Redirect::to("/feedback"));
+    }
     if let Some(user_info) = user_info {
-        sqlx::query(sql::INSERT_FEEDBACK)
-            .bind(feedback.controller)
-            .bind(feedback.position)
+        let id = sqlx::query(sql::INSERT_FEEDBACK)
+            .bind(controller)
+            .bind(position)
             .bind(feedback.rating)
             .bind(feedback.comments)
             .bind(sqlx::types::chrono::Utc::now())
             .bind(user_info.cid)
             .execute(&state.db)
-            .await?;
+            .await?
+            .last_insert_rowid();
+        auto_approve_feedback(&state, id).await?;
         flashed_messages::push_flashed_message(
             session,
             flashed_messages::FlashedMessageLevel::Success,
@@ -96,16 +291,34 @@ async fn page_feedback_form_post(
 
 /// This file's routes and templates.
 pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
+    // `{{ date|local_date }}` uses the logged-in user's timezone; one can also be passed explicitly
+    templates.add_filter(
+        "local_date",
+        |state: &minijinja::State, date: String, timezone: Option<String>| {
+            let timezone = timezone.or_else(|| {
+                state
+                    .lookup("user_info")
+                    .and_then(|user_info| user_info.get_attr("timezone").ok())
+                    .and_then(|tz| tz.as_str().map(str::to_owned))
+            });
+            local_date(&date, timezone.as_deref())
+        },
+    );
     templates
         .add_template("404", include_str!("../../templates/404.jinja"))
         .unwrap();
     templates
         .add_template("feedback", include_str!("../../templates/feedback.jinja"))
         .unwrap();
+    templates
+        .add_template(
+            "maintenance",
+            include_str!("../../templates/maintenance.jinja"),
+        )
+        .unwrap();
 
     Router::new()
         .route("/404", get(page_404))
         .route("/feedback", get(page_feedback_form))
         .route("/feedback", post(page_feedback_form_post))
-        .nest_service("/assets", ServeDir::new("assets"))
 }
@@ -0,0 +1,59 @@
+//! Endpoints for reading facility news.
+//!
+//! The CRUD of news entries themselves is under /admin routes.
+
+use crate::{
+    shared::{
+        sql::{self, NewsEntry},
+        AppError, AppState, UserInfo, SESSION_USER_INFO_KEY,
+    },
+    utils::build_news_feed,
+};
+use axum::{
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use minijinja::{context, Environment};
+use std::sync::Arc;
+use tower_sessions::Session;
+
+/// View all news entries, newest first.
+async fn page_news(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Html<String>, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let news: Vec<NewsEntry> = sqlx::query_as(sql::GET_ALL_NEWS)
+        .fetch_all(&state.db)
+        .await?;
+    let template = state.templates.get_template("news/news")?;
+    let rendered = template.render(context! { user_info, news })?;
+    Ok(Html(rendered))
+}
+
+/// RSS feed of all news entries.
+///
+/// Links point at the configured facility domain rather than the request's
+/// `Host` header, which the client controls.
+async fn feed_news(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+    let news: Vec<NewsEntry> = sqlx::query_as(sql::GET_ALL_NEWS)
+        .fetch_all(&state.db)
+        .await?;
+    let config = state.config();
+    let feed = build_news_feed(&config.facility, &config.facility.site_url(), &news);
+    Ok(([(header::CONTENT_TYPE, "application/rss+xml")], feed).into_response())
+}
+
+/// This file's routes and templates.
+pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
+    templates
+        .add_template("news/news", include_str!("../../templates/news/news.jinja"))
+        .unwrap();
+
+    Router::new()
+        .route("/news", get(page_news))
+        .route("/news.xml", get(feed_news))
+}
@@ -1,16 +1,23 @@
 //! HTTP endpoints for user-specific pages.
 
 use crate::{
-    shared::{AppError, AppState, UserInfo, SESSION_USER_INFO_KEY},
-    utils::vatusa,
+    shared::{
+        sql::{self, Certification},
+        AppError, AppState, UserInfo, SESSION_USER_INFO_KEY,
+    },
+    utils::{flashed_messages, get_controller_cids_and_names, vatusa, POSITION_CATEGORIES},
 };
 use axum::{
     extract::State,
     response::{Html, IntoResponse, Redirect, Response},
     routing::get,
-    Router,
+    Form, Router,
 };
+use chrono::{DateTime, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use itertools::Itertools;
 use minijinja::{context, Environment};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tower_sessions::Session;
 
@@ -26,7 +33,7 @@ async fn page_training_notes(
         return Ok(Redirect::to("/").into_response());
     }
     let mut training_records = vatusa::get_training_records(
-        &state.config.vatsim.vatusa_api_key,
+        &state.config().vatsim.vatusa_api_key,
         user_info.as_ref().unwrap().cid,
     )
     .await?;
@@ -38,6 +45,59 @@ async fn page_training_notes(
     Ok(Html(rendered).into_response())
 }
 
+/// Show the user where they stand on each of the facility's certifications.
+async fn page_certifications(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    #[derive(Debug, Serialize)]
+    struct CertificationStatus<'a> {
+        name: &'a str,
+        value: Option<String>,
+        changed_on: Option<DateTime<Utc>>,
+        set_by: Option<String>,
+    }
+
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let cid = match &user_info {
+        Some(info) => info.cid,
+        None => return Ok(Redirect::to("/").into_response()),
+    };
+    let certifications: Vec<Certification> = sqlx::query_as(sql::GET_ALL_CERTIFICATIONS_FOR)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let cid_name_map = get_controller_cids_and_names(&state.db).await?;
+    let config = state.config();
+    let statuses: Vec<_> = config
+        .training
+        .certifications
+        .iter()
+        .map(
+            |name| match certifications.iter().find(|cert| &cert.name == name) {
+                Some(cert) => CertificationStatus {
+                    name,
+                    value: Some(cert.value.clone()),
+                    changed_on: Some(cert.changed_on),
+                    set_by: Some(match cid_name_map.get(&(cert.set_by as u64)) {
+                        Some((first, last)) => format!("{first} {last}"),
+                        None => cert.set_by.to_string(),
+                    }),
+                },
+                None => CertificationStatus {
+                    name,
+                    value: None,
+                    changed_on: None,
+                    set_by: None,
+                },
+            },
+        )
+        .collect();
+    let template = state.templates.get_template("user/certifications")?;
+    let rendered = template.render(context! { user_info, statuses })?;
+    Ok(Html(rendered).into_response())
+}
+
 /// Show the user a link to the Discord server, as well as provide
 /// the start of the Discord OAuth flow for account linking.
 async fn page_discord(
@@ -51,11 +111,149 @@ async fn page_discord(
     let template = state.templates.get_template("user/discord")?;
     let rendered = template.render(context! {
        user_info,
-       join_link => &state.config.discord.join_link
+       join_link => &state.config().discord.join_link
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Page for the user to pick the timezone times are shown in.
+async fn page_timezone(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    if user_info.is_none() {
+        return Ok(Redirect::to("/").into_response());
+    }
+    let timezones: Vec<_> = TZ_VARIANTS.iter().map(|tz| tz.name()).collect();
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("user/timezone")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        timezones,
+        now => Utc::now(),
     })?;
     Ok(Html(rendered).into_response())
 }
 
+#[derive(Debug, Deserialize)]
+struct TimezoneForm {
+    /// IANA timezone name, or blank for UTC
+    timezone: String,
+}
+
+/// Save the user's timezone to their controller record and session.
+async fn post_timezone(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(timezone_form): Form<TimezoneForm>,
+) -> Result<Redirect, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let mut user_info = match user_info {
+        Some(user_info) => user_info,
+        None => return Ok(Redirect::to("/")),
+    };
+    let timezone = match timezone_form.timezone.trim() {
+        "" => None,
+        name => match name.parse::<Tz>() {
+            Ok(tz) => Some(tz.name().to_owned()),
+            Err(_) => {
+                flashed_messages::push_flashed_message(
+                    session,
+                    flashed_messages::FlashedMessageLevel::Error,
+                    "Unknown timezone",
+                )
+                .await?;
+                return Ok(Redirect::to("/user/timezone"));
+            }
+        },
+    };
+    sqlx::query(sql::UPDATE_CONTROLLER_TIMEZONE)
+        .bind(&timezone)
+        .bind(user_info.cid)
+        .execute(&state.db)
+        .await?;
+    user_info.timezone = timezone;
+    session.insert(SESSION_USER_INFO_KEY, user_info).await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Timezone updated",
+    )
+    .await?;
+    Ok(Redirect::to("/user/timezone"))
+}
+
+/// Page for the user to rank the position categories they'd like at events.
+async fn page_position_preferences(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+) -> Result<Response, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let cid = match &user_info {
+        Some(info) => info.cid,
+        None => return Ok(Redirect::to("/").into_response()),
+    };
+    let preferences: Vec<String> = sqlx::query_scalar(sql::GET_POSITION_PREFERENCES)
+        .bind(cid)
+        .fetch_all(&state.db)
+        .await?;
+    let flashed_messages = flashed_messages::drain_flashed_messages(session).await?;
+    let template = state.templates.get_template("user/position_preferences")?;
+    let rendered = template.render(context! {
+        user_info,
+        flashed_messages,
+        preferences,
+        categories => POSITION_CATEGORIES,
+    })?;
+    Ok(Html(rendered).into_response())
+}
+
+/// Save the user's ranked position categories.
+///
+/// The form is read as a list of pairs as it contains a "category" entry
+/// for each rank, in order.
+async fn post_position_preferences(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Form(form): Form<Vec<(String, String)>>,
+) -> Result<Redirect, AppError> {
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await?;
+    let cid = match user_info {
+        Some(info) => info.cid,
+        None => return Ok(Redirect::to("/")),
+    };
+    let categories: Vec<_> = form
+        .iter()
+        .filter(|(key, _)| key == "category")
+        .map(|(_, value)| value.as_str())
+        .filter(|value| POSITION_CATEGORIES.contains(value))
+        .unique()
+        .collect();
+    let mut tx = state.db.begin().await?;
+    sqlx::query(sql::DELETE_POSITION_PREFERENCES)
+        .bind(cid)
+        .execute(&mut *tx)
+        .await?;
+    for (index, category) in categories.iter().enumerate() {
+        sqlx::query(sql::INSERT_POSITION_PREFERENCE)
+            .bind(cid)
+            .bind(category)
+            .bind(index as u32)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    flashed_messages::push_flashed_message(
+        session,
+        flashed_messages::FlashedMessageLevel::Success,
+        "Position preferences saved",
+    )
+    .await?;
+    Ok(Redirect::to("/user/positions"))
+}
+
 pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
     templates
         .add_template(
@@ -63,6 +261,12 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
             include_str!("../../templates/user/training_notes.jinja"),
         )
         .unwrap();
+    templates
+        .add_template(
+            "user/certifications",
+            include_str!("../../templates/user/certifications.jinja"),
+        )
+        .unwrap();
     templates
         .add_template(
             "user/discord",
@@ -70,7 +274,27 @@ pub fn router(templates: &mut Environment) -> Router<Arc<AppState>> {
         )
         .unwrap();
 
+    templates
+        .add_template(
+            "user/timezone",
+            include_str!("../../templates/user/timezone.jinja"),
+        )
+        .unwrap();
+
+    templates
+        .add_template(
+            "user/position_preferences",
+            include_str!("../../templates/user/position_preferences.jinja"),
+        )
+        .unwrap();
+
     Router::new()
         .route("/user/training_notes", get(page_training_notes))
+        .route("/user/certifications", get(page_certifications))
         .route("/user/discord", get(page_discord))
+        .route("/user/timezone", get(page_timezone).post(post_timezone))
+        .route(
+            "/user/positions",
+            get(page_position_preferences).post(post_position_preferences),
+        )
 }
@@ -2,13 +2,14 @@
 
 #![deny(clippy::all)]
 
-use anyhow::Result;
-use shared::Config;
+use anyhow::{Context, Result};
+use log::info;
+use shared::{sql, Config};
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
     Executor, SqlitePool,
 };
-use std::path::Path;
+use std::{env, io::Write, path::Path};
 
 pub mod endpoints;
 pub mod middleware;
@@ -23,6 +24,31 @@ pub fn load_config(path: &Path) -> Result<Config> {
     Ok(config)
 }
 
+/// Set up logging, filtered by the `RUST_LOG` env var.
+///
+/// With `json`, each record is written as a line of JSON for log aggregators.
+pub fn init_logging(json: bool) {
+    if !json {
+        pretty_env_logger::init();
+        return;
+    }
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    builder
+        .format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{line}")
+        })
+        .init();
+}
+
 /// Connect to the SQLite file at the destination, if it exists. If it does
 /// not, a new file is created and statements to create tables are executed.
 pub async fn load_db(config: &Config) -> Result<SqlitePool> {
@@ -34,10 +60,210 @@ pub async fn load_db(config: &Config) -> Result<SqlitePool> {
     let pool = if !Path::new(&config.database.file).exists() {
         let options = options.create_if_missing(true);
         let pool = SqlitePool::connect_with(options).await?;
-        pool.execute(shared::sql::CREATE_TABLES).await?;
+        pool.execute(sql::CREATE_TABLES).await?;
+        sqlx::query(sql::SET_SCHEMA_VERSION)
+            .bind(sql::MIGRATIONS.len() as u32)
+            .execute(&pool)
+            .await?;
         pool
     } else {
-        SqlitePool::connect_with(options).await?
+        let pool = SqlitePool::connect_with(options).await?;
+        run_migrations(&pool).await?;
+        pool
     };
     Ok(pool)
 }
+
+/// Apply the migrations the DB hasn't had yet, each in its own transaction.
+///
+/// DBs from before versioning are treated as version 0.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
+    pool.execute(sql::CREATE_SCHEMA_VERSION).await?;
+    let version: Option<u32> = sqlx::query_scalar(sql::GET_SCHEMA_VERSION)
+        .fetch_optional(pool)
+        .await?;
+    let version = version.unwrap_or_default() as usize;
+    for (index, migration) in sql::MIGRATIONS.iter().enumerate().skip(version) {
+        let mut tx = pool.begin().await?;
+        tx.execute(*migration)
+            .await
+            .with_context(|| format!("Applying DB migration {}", index + 1))?;
+        sqlx::query(sql::SET_SCHEMA_VERSION)
+            .bind(index as u32 + 1)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        info!("Applied DB migration {}", index + 1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_migrations;
+    use crate::{shared::sql, utils::tests::test_db};
+    use pretty_assertions::assert_eq;
+    use sqlx::{sqlite::SqlitePoolOptions, Executor, SqlitePool};
+
+    /// The schema from before DB versioning.
+    const BASELINE_SCHEMA: &str = r#"
+CREATE TABLE controller (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL UNIQUE,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    email TEXT,
+    operating_initials TEXT,
+    rating INTEGER,
+    status TEXT,
+    discord_id TEXT UNIQUE,
+    home_facility TEXT,
+    is_on_roster INTEGER,
+    roles TEXT,
+    loa_until TEXT
+) STRICT;
+
+CREATE TABLE certification (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    changed_on TEXT NOT NULL,
+    set_by INTEGER NOT NULL
+) STRICT;
+
+CREATE TABLE feedback (
+    id INTEGER PRIMARY KEY NOT NULL,
+    controller TEXT NOT NULL,
+    position TEXT NOT NULL,
+    rating TEXT NOT NULL,
+    comments TEXT,
+    created_date TEXT NOT NULL,
+    submitter_cid INTEGER NOT NULL,
+    reviewed_by_cid INTEGER,
+    reviewer_action TEXT NOT NULL DEFAULT 'pending',
+    posted_to_discord INTEGER NOT NULL DEFAULT FALSE
+) STRICT;
+
+CREATE TABLE activity (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    month TEXT NOT NULL,
+    minutes INTEGER NOT NULL,
+
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE resource (
+    id INTEGER PRIMARY KEY NOT NULL,
+    category TEXT NOT NULL,
+    name TEXT NOT NULL,
+    file_name TEXT,
+    link TEXT,
+    updated TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE visitor_request (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    home_facility TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    date TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE event (
+    id INTEGER PRIMARY KEY NOT NULL,
+    created_by INTEGER NOT NULL,
+    published INTEGER NOT NULL DEFAULT FALSE,
+    complete INTEGER NOT NULL DEFAULT FALSE,
+    name TEXT NOT NULL,
+    start TEXT NOT NULL,
+    end TEXT NOT NULL,
+    description TEXT,
+    image_url TEXT,
+
+    FOREIGN KEY (created_by) REFERENCES controller(id)
+) STRICT;
+
+CREATE TABLE event_position (
+    id INTEGER PRIMARY KEY NOT NULL,
+    event_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    cid INTEGER,
+
+    FOREIGN KEY (event_id) REFERENCES event(id),
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE event_registration (
+    id INTEGER PRIMARY KEY NOT NULL,
+    event_id INTEGER NOT NULL,
+    position_id INTEGER NOT NULL,
+    cid INTEGER NOT NULL,
+
+    FOREIGN KEY (event_id) REFERENCES event(id),
+    FOREIGN KEY (position_id) REFERENCES event_position(id),
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+"#;
+
+    /// Every table's columns, with their types, nullability, and defaults.
+    async fn schema_columns(
+        db: &SqlitePool,
+    ) -> Vec<(String, String, String, bool, Option<String>)> {
+        sqlx::query_as(
+            "SELECT m.name, p.name, p.type, p.\"notnull\", p.dflt_value
+            FROM sqlite_master m JOIN pragma_table_info(m.name) p
+            WHERE m.type='table'
+            ORDER BY m.name, p.name",
+        )
+        .fetch_all(db)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrations() {
+        let new_columns = schema_columns(&test_db().await).await;
+
+        // an existing DB from before versioning gets every migration, once,
+        // and ends up with the same tables and columns as a new DB
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        db.execute(BASELINE_SCHEMA).await.unwrap();
+        sqlx::query(
+            "INSERT INTO controller (cid, first_name, last_name, operating_initials) VALUES (1, 'A', 'A', 'AB'), (2, 'B', 'B', 'AB')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO activity (cid, month, minutes) VALUES (1, '2024-01', 5), (1, '2024-01', 7)")
+            .execute(&db)
+            .await
+            .unwrap();
+        run_migrations(&db).await.unwrap();
+        run_migrations(&db).await.unwrap();
+        let version: u32 = sqlx::query_scalar(sql::GET_SCHEMA_VERSION)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(version as usize, sql::MIGRATIONS.len());
+        assert_eq!(schema_columns(&db).await, new_columns);
+        let minutes: Vec<u32> = sqlx::query_scalar("SELECT minutes FROM activity")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(minutes, vec![7]);
+        let initials: Vec<Option<String>> =
+            sqlx::query_scalar("SELECT operating_initials FROM controller ORDER BY cid")
+                .fetch_all(&db)
+                .await
+                .unwrap();
+        assert_eq!(initials, vec![Some("AB".to_owned()), None]);
+    }
+}
@@ -1,31 +1,152 @@
 //! App middleware functions.
 
-use axum::{extract::Request, middleware::Next, response::Response};
-use log::{debug, warn};
+use crate::{
+    endpoints::admin::{reject_if_not_staff, StaffRequirement},
+    shared::{sql, AppState, UserInfo, KVS_MAINTENANCE_MODE, REQUEST_ID, SESSION_USER_INFO_KEY},
+    utils::{api_key_name, maintenance_exempt},
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use log::{debug, error, info, warn};
+use minijinja::context;
 use once_cell::sync::Lazy;
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
+use tower_sessions::Session;
+use uuid::Uuid;
 
 static IGNORE_PATHS: Lazy<HashSet<&str>> = Lazy::new(|| HashSet::from(["/favicon.ico"]));
 
+/// Response header with the request's ID.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// Simple logging middleware.
 ///
+/// Each request gets an ID, which is available to handlers through
+/// `REQUEST_ID`, included in the log line, and sent back in the
+/// `x-request-id` header so errors can be matched to their logs.
+///
 /// Logs the method, path, and response code to debug
 /// if processing returned a successful code, and to
 /// warn otherwise.
 pub async fn logging(request: Request, next: Next) -> Response {
+    let request_id = Uuid::new_v4().to_string();
     let uri = request.uri().clone();
     let path = uri.path();
+    let method = request.method().clone();
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .await;
     if !IGNORE_PATHS.contains(path) {
-        let method = request.method().clone();
-        let response = next.run(request).await;
-        let s = format!("{} {} {}", method, path, response.status().as_u16());
+        let s = format!(
+            "[{request_id}] {} {} {}",
+            method,
+            path,
+            response.status().as_u16()
+        );
         if response.status().is_success() || response.status().is_redirection() {
             debug!("{s}");
         } else {
             warn!("{s}");
         }
-        response
-    } else {
-        next.run(request).await
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Name of the configured API key a request was made with.
+///
+/// Taking this as a handler argument makes the route require a valid key in
+/// an `Authorization: Bearer` header; requests without one get a 401. Each
+/// use is logged with the key's name.
+pub struct ApiKey(pub String);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ApiKey {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let authorization = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let config = state.config();
+        match api_key_name(&config.api.keys, authorization) {
+            Some(name) => {
+                info!(
+                    "API key \"{name}\" used for {} {}",
+                    parts.method,
+                    parts.uri.path()
+                );
+                Ok(Self(name.to_owned()))
+            }
+            None => {
+                warn!(
+                    "Rejected {} {} without a valid API key",
+                    parts.method,
+                    parts.uri.path()
+                );
+                Err(StatusCode::UNAUTHORIZED)
+            }
+        }
+    }
+}
+
+/// Whether the site is in maintenance mode.
+pub async fn in_maintenance_mode(state: &AppState) -> bool {
+    let value: Option<String> = match sqlx::query_scalar(sql::GET_KVS_ENTRY)
+        .bind(KVS_MAINTENANCE_MODE)
+        .fetch_optional(&state.db)
+        .await
+    {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Could not check maintenance mode: {e}");
+            return false;
+        }
+    };
+    value.is_some_and(|value| value == "true")
+}
+
+/// Maintenance mode middleware.
+///
+/// While maintenance mode is on, everyone but admins gets the maintenance
+/// page, except for the paths in `maintenance_exempt`.
+pub async fn maintenance(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    request: Request,
+    next: Next,
+) -> Response {
+    if maintenance_exempt(request.uri().path()) || !in_maintenance_mode(&state).await {
+        return next.run(request).await;
+    }
+    let user_info: Option<UserInfo> = session.get(SESSION_USER_INFO_KEY).await.ok().flatten();
+    if reject_if_not_staff(&state, &user_info, StaffRequirement::Admins)
+        .await
+        .is_none()
+    {
+        return next.run(request).await;
+    }
+    let rendered = state
+        .templates
+        .get_template("maintenance")
+        .and_then(|template| template.render(context! { user_info }));
+    match rendered {
+        Ok(rendered) => (StatusCode::SERVICE_UNAVAILABLE, Html(rendered)).into_response(),
+        Err(e) => {
+            error!("Could not render maintenance page: {e}");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
     }
 }
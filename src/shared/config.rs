@@ -1,38 +1,118 @@
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 /// Default place to look for the config file.
 pub const DEFAULT_CONFIG_FILE_NAME: &str = "site_config.toml";
 
 /// App configuration.
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct Config {
+    #[serde(default)]
+    pub facility: ConfigFacility,
     pub database: ConfigDatabase,
     pub staff: ConfigStaff,
     pub vatsim: ConfigVatsim,
+    pub training: ConfigTraining,
     pub airports: ConfigAirports,
     pub stats: ConfigStats,
     pub discord: ConfigDiscord,
+    #[serde(default)]
+    pub email: ConfigEmail,
+    #[serde(default)]
+    pub cache: ConfigCache,
+    #[serde(default)]
+    pub roster: ConfigRoster,
+    #[serde(default)]
+    pub feedback: ConfigFeedback,
+    #[serde(default)]
+    pub logs: ConfigLogs,
+    #[serde(default)]
+    pub atis: ConfigAtis,
+    #[serde(default)]
+    pub currency: ConfigCurrency,
+    #[serde(default)]
+    pub visitors: ConfigVisitors,
+    #[serde(default)]
+    pub resources: ConfigResources,
+    #[serde(default)]
+    pub api: ConfigApi,
+}
+
+impl Config {
+    /// Copy of the config with secrets replaced, safe to show to users.
+    ///
+    /// Unset secrets are left empty so it's still visible whether they're configured.
+    pub fn redacted(&self) -> Self {
+        fn redact(value: &mut String) {
+            if !value.is_empty() {
+                *value = REDACTED.to_owned();
+            }
+        }
+
+        let mut config = self.clone();
+        redact(&mut config.vatsim.oauth_client_secret);
+        redact(&mut config.vatsim.vatusa_api_key);
+        redact(&mut config.discord.webhooks.staffing_request);
+        redact(&mut config.discord.webhooks.feedback);
+        redact(&mut config.discord.webhooks.roster_alert);
+        redact(&mut config.discord.webhooks.staff_notes);
+        redact(&mut config.discord.webhooks.roster_changes);
+        redact(&mut config.discord.webhooks.schedule);
+        redact(&mut config.email.password);
+        redact(&mut config.atis.secret);
+        for key in &mut config.api.keys {
+            redact(&mut key.key);
+        }
+        config
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+/// Replacement text for secret config values.
+pub const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigDatabase {
     pub file: String,
+    /// Initial resource category order, used until admins set one on the site
     pub resource_category_ordering: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigStaffOverride {
     pub role: String,
     pub cid: u32,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigStaff {
     pub overrides: Vec<ConfigStaffOverride>,
     pub email_domain: String,
+    #[serde(default = "default_min_note_length")]
+    pub min_note_length: usize,
+    /// Training Administrator email; "ta@" the email domain if empty
+    #[serde(default)]
+    pub training_email: String,
+}
+
+impl ConfigStaff {
+    /// Email address that training matters should go to.
+    pub fn training_contact(&self) -> String {
+        if self.training_email.is_empty() {
+            format!("ta@{}", self.email_domain)
+        } else {
+            self.training_email.clone()
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+fn default_min_note_length() -> usize {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigVatsim {
     pub oauth_url_base: String,
     pub oauth_client_id: String,
@@ -41,10 +121,48 @@ pub struct ConfigVatsim {
     pub vatusa_api_key: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ConfigTraining {
+    pub certifications: Vec<String>,
+    #[serde(default)]
+    pub certification_expiry: Vec<ConfigCertificationExpiry>,
+    #[serde(default)]
+    pub expiry_reminder_days: u32,
+    #[serde(default)]
+    pub certification_usage: Vec<ConfigCertificationUsage>,
+}
+
+/// Recurrency requirement for a single certification.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ConfigCertificationExpiry {
+    pub name: String,
+    pub valid_for_days: u32,
+}
+
+/// Certification that lapses if the controller doesn't work its positions.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ConfigCertificationUsage {
+    pub name: String,
+    /// Callsign suffixes that count as using the certification, like "_APP"
+    pub position_suffixes: Vec<String>,
+    /// Days without a session on one of the positions before it lapses
+    pub unused_days: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigAirports {
     pub all: Vec<Airport>,
     pub weather_for: Vec<String>,
+    /// Points (latitude, longitude) of the airspace's outline, in order
+    #[serde(default)]
+    pub boundary: Vec<(f64, f64)>,
+    /// Default radius, in nautical miles, of the pilot glance around an airport
+    #[serde(default = "default_glance_distance")]
+    pub glance_distance: f64,
+}
+
+fn default_glance_distance() -> f64 {
+    20.0
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -54,22 +172,432 @@ pub struct Airport {
     pub location: String,
     pub towered: bool,
     pub class: String,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    #[serde(default)]
+    pub runways: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigStats {
     pub position_prefixes: Vec<String>,
     pub position_suffixes: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigDiscord {
     pub join_link: String,
     pub webhooks: ConfigDiscordWebhooks,
+    #[serde(default)]
+    pub feedback_colors: ConfigFeedbackColors,
+    #[serde(default)]
+    pub role_mappings: Vec<ConfigRoleMapping>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+/// Discord role that roster controllers should have if they hold
+/// any of the staff positions or ratings.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ConfigRoleMapping {
+    pub discord_role: String,
+    /// Staff positions, including assistants, e.g. "ATM", "AEC", "MTR"
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Short rating names, e.g. "S1", "C1"
+    #[serde(default)]
+    pub ratings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigDiscordWebhooks {
     pub staffing_request: String,
     pub feedback: String,
+    #[serde(default)]
+    pub roster_alert: String,
+    #[serde(default)]
+    pub staff_notes: String,
+    #[serde(default)]
+    pub roster_changes: String,
+    #[serde(default)]
+    pub schedule: String,
+}
+
+impl ConfigDiscordWebhooks {
+    /// Each webhook's config key, URL, and whether the site needs it set.
+    ///
+    /// Webhooks that aren't required just turn off their notifications when left empty.
+    pub fn all(&self) -> [(&'static str, &str, bool); 6] {
+        [
+            ("staffing_request", &self.staffing_request, true),
+            ("feedback", &self.feedback, true),
+            ("roster_alert", &self.roster_alert, false),
+            ("staff_notes", &self.staff_notes, false),
+            ("roster_changes", &self.roster_changes, false),
+            ("schedule", &self.schedule, false),
+        ]
+    }
+}
+
+/// Discord embed colors for each feedback rating, as RGB integers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigFeedbackColors {
+    pub excellent: u32,
+    pub good: u32,
+    pub fair: u32,
+    pub poor: u32,
+}
+
+impl Default for ConfigFeedbackColors {
+    fn default() -> Self {
+        Self {
+            excellent: 0x2ecc71,
+            good: 0x3498db,
+            fair: 0xf1c40f,
+            poor: 0xe74c3c,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigEmail {
+    pub host: String,
+    pub user: String,
+    pub password: String,
+    pub from: String,
+    pub digest_recipients: Vec<String>,
+    /// Days between staff digests; 0 is treated as 1
+    pub digest_interval_days: u64,
+    /// Email controllers when they're assigned to an event position
+    pub notify_event_assignments: bool,
+}
+
+impl ConfigEmail {
+    /// Days between staff digests, never less than 1.
+    pub fn digest_days(&self) -> u64 {
+        self.digest_interval_days.max(1)
+    }
+}
+
+impl Default for ConfigEmail {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            user: String::new(),
+            password: String::new(),
+            from: String::new(),
+            digest_recipients: Vec::new(),
+            digest_interval_days: 7,
+            notify_event_assignments: false,
+        }
+    }
+}
+
+/// Server-side cache size and lifetimes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigCache {
+    /// Maximum number of cached entries
+    #[serde(default = "default_cache_capacity")]
+    pub capacity: u64,
+    /// Lifetimes in seconds, by cache key
+    #[serde(flatten)]
+    pub ttls: HashMap<String, u64>,
+}
+
+fn default_cache_capacity() -> u64 {
+    100
+}
+
+impl Default for ConfigCache {
+    fn default() -> Self {
+        Self {
+            capacity: default_cache_capacity(),
+            ttls: HashMap::new(),
+        }
+    }
+}
+
+impl ConfigCache {
+    /// Lifetime of the cache key's entries, falling back to the built-in default.
+    pub fn ttl(&self, key: &str) -> u64 {
+        self.ttls.get(key).copied().unwrap_or(match key {
+            "WEATHER_BRIEF" | "WEATHER_FULL" => 300,
+            "RATING_HISTORY" | "LAST_TRAINING" | "PILOT_STATS" | "FEEDBACK_STATS" => 3_600,
+            "ONLINE_CONTROLLERS_FULL" | "ONLINE_PILOTS" => 30,
+            _ => 60,
+        })
+    }
+}
+
+/// Safety limits for the roster sync, and what happens to removed controllers' records.
+///
+/// If a single sync would remove more controllers than either limit allows,
+/// the removals are skipped and staff are alerted instead. A limit of 0 disables it.
+/// Controllers missing from the VATUSA roster are only removed once they've been
+/// missing for `removal_after_missed_syncs` syncs in a row.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigRoster {
+    pub max_removals: usize,
+    pub max_removal_percent: f64,
+    /// Delete a removed controller's certifications instead of keeping them in case they return
+    pub delete_removed_certifications: bool,
+    /// Consecutive syncs a controller must be missing from before they're removed
+    pub removal_after_missed_syncs: u32,
+}
+
+impl Default for ConfigRoster {
+    fn default() -> Self {
+        Self {
+            max_removals: 10,
+            max_removal_percent: 10.0,
+            delete_removed_certifications: false,
+            removal_after_missed_syncs: 3,
+        }
+    }
+}
+
+/// Limits on and handling of submitted feedback.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigFeedback {
+    /// Maximum number of characters in the comments
+    pub max_comment_length: usize,
+    /// Ratings that are posted to Discord immediately instead of waiting for staff review
+    pub auto_approve_ratings: Vec<String>,
+}
+
+impl Default for ConfigFeedback {
+    fn default() -> Self {
+        Self {
+            max_comment_length: 2_000,
+            auto_approve_ratings: Vec::new(),
+        }
+    }
+}
+
+/// Receiving ATIS updates from vATIS.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigAtis {
+    /// Shared secret for signing update requests; signatures aren't checked if empty
+    pub secret: String,
+    /// Hours to keep ATIS entries before they're deleted
+    pub retention_hours: u64,
+}
+
+impl Default for ConfigAtis {
+    fn default() -> Self {
+        Self {
+            secret: String::new(),
+            retention_hours: 24,
+        }
+    }
+}
+
+/// Emailing controllers who are close to missing the activity requirement.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigCurrency {
+    /// Days before the end of the month to start reminding; 0, the default, disables reminders
+    pub reminder_days: u32,
+    /// Only remind controllers who are at least this many minutes short
+    pub min_minutes_short: u32,
+    /// Days to wait before reminding the same controller again
+    pub reminder_interval_days: u32,
+    /// Days after joining the facility that a controller is exempt from the activity requirement
+    pub new_controller_grace_days: u32,
+    /// How the months of a quarter are chosen for the activity requirement
+    pub quarter: QuarterMode,
+}
+
+/// How the months of a quarter are chosen for the activity requirement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuarterMode {
+    /// The current month and the 2 before it
+    #[default]
+    Rolling,
+    /// January through March, April through June, and so on
+    Calendar,
+}
+
+impl Default for ConfigCurrency {
+    fn default() -> Self {
+        Self {
+            reminder_days: 0,
+            min_minutes_short: 1,
+            reminder_interval_days: 7,
+            new_controller_grace_days: 90,
+            quarter: QuarterMode::Rolling,
+        }
+    }
+}
+
+/// Files uploaded for the facility's resources.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigResources {
+    /// Largest file that can be uploaded, in megabytes
+    pub max_upload_mb: usize,
+    /// File extensions that can be uploaded, lowercase and without the dot
+    pub allowed_extensions: Vec<String>,
+    /// Resource categories that controllers need to initial as having read
+    pub sop_categories: Vec<String>,
+}
+
+impl Default for ConfigResources {
+    fn default() -> Self {
+        Self {
+            max_upload_mb: 50,
+            allowed_extensions: [
+                "pdf", "txt", "png", "jpg", "jpeg", "gif", "zip", "doc", "docx", "xls", "xlsx",
+                "ppt", "pptx",
+            ]
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect(),
+            sop_categories: vec!["SOP".to_owned()],
+        }
+    }
+}
+
+/// The facility the site is for.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigFacility {
+    /// VATUSA facility code, like "ZDV"
+    pub code: String,
+    /// Full name, like "Denver ARTCC"
+    pub name: String,
+    /// Domain the facility's sites are under
+    pub domain: String,
+    /// ICAO code of the facility's main airport
+    pub primary_airport: String,
+}
+
+impl ConfigFacility {
+    /// Short name of the virtual facility, like "vZDV".
+    pub fn site_name(&self) -> String {
+        format!("v{}", self.code)
+    }
+
+    /// Scheme and host of the site, like "https://zdvartcc.org".
+    pub fn site_url(&self) -> String {
+        format!("https://{}", self.domain)
+    }
+}
+
+impl Default for ConfigFacility {
+    fn default() -> Self {
+        Self {
+            code: "ZDV".to_owned(),
+            name: "Denver ARTCC".to_owned(),
+            domain: "zdvartcc.org".to_owned(),
+            primary_airport: "KDEN".to_owned(),
+        }
+    }
+}
+
+/// Access to the JSON API from other sites.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct ConfigApi {
+    /// Origins allowed to call the API from the browser, like "https://example.com"
+    ///
+    /// Only read at startup; changes need a restart.
+    pub cors_allowed_origins: Vec<String>,
+    /// Keys for the write endpoints
+    pub keys: Vec<ConfigApiKey>,
+    /// Hosts of other facilities' sites that events can be imported from, like "example.com"
+    pub event_import_hosts: Vec<String>,
+}
+
+/// A key for calling the write API endpoints.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ConfigApiKey {
+    /// Who the key is for, used in the logs
+    pub name: String,
+    pub key: String,
+}
+
+/// Requirements for controllers applying to visit.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigVisitors {
+    /// Lowest numeric rating that can apply
+    pub min_rating: u8,
+    /// Certifications visitors work towards; all training certifications if empty
+    pub required_certifications: Vec<String>,
+}
+
+impl Default for ConfigVisitors {
+    fn default() -> Self {
+        Self {
+            min_rating: 4,
+            required_certifications: Vec::new(),
+        }
+    }
+}
+
+/// Log files viewable by admins on the site.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigLogs {
+    /// Directory the log files are in
+    pub directory: PathBuf,
+    /// File names within the directory
+    pub files: Vec<String>,
+    /// Most lines that can be shown from a file at once
+    pub max_lines: usize,
+}
+
+impl Default for ConfigLogs {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("."),
+            files: vec![
+                "vzdv_site.log".to_owned(),
+                "vzdv_tasks.log".to_owned(),
+                "vzdv_bot.log".to_owned(),
+            ],
+            max_lines: 5_000,
+        }
+    }
+}
+
+impl ConfigLogs {
+    /// Full path to a log file, if it's one of the configured files.
+    ///
+    /// Only bare file names are accepted so that a name can't point outside the directory.
+    pub fn path_for(&self, name: &str) -> Option<PathBuf> {
+        let is_bare_name = Path::new(name).file_name().is_some_and(|n| n == name);
+        if !is_bare_name || !self.files.iter().any(|file| file == name) {
+            return None;
+        }
+        Some(self.directory.join(name))
+    }
+}
+
+impl ConfigRoster {
+    /// Whether removing this many controllers from a roster of this size is suspicious.
+    pub fn removals_exceed_limit(&self, removals: usize, roster_size: usize) -> bool {
+        if self.max_removals > 0 && removals > self.max_removals {
+            return true;
+        }
+        if self.max_removal_percent > 0.0 && roster_size > 0 {
+            let percent = removals as f64 / roster_size as f64 * 100.0;
+            return percent > self.max_removal_percent;
+        }
+        false
+    }
+
+    /// Whether a controller missing from this many syncs in a row should be removed.
+    ///
+    /// A setting of 0 is treated as 1, removing them on the first sync they're missing from.
+    pub fn removal_due(&self, missed_syncs: u32) -> bool {
+        missed_syncs >= self.removal_after_missed_syncs.max(1)
+    }
 }
@@ -2,7 +2,10 @@
 
 #![allow(unused)]
 
-use std::time::Instant;
+use std::{
+    sync::{Arc, OnceLock, RwLock},
+    time::Instant,
+};
 
 use axum::{
     http::StatusCode,
@@ -16,28 +19,56 @@ use serde_json::json;
 use sqlx::SqlitePool;
 
 pub mod config;
-pub use config::{Config, DEFAULT_CONFIG_FILE_NAME};
+pub use config::{Config, ConfigFacility, DEFAULT_CONFIG_FILE_NAME};
 pub mod sql;
 
 /// Wrapper around `anyhow`'s `Error` type, which is itself a wrapper
 /// around the stdlib's `Error` type.
 pub struct AppError(anyhow::Error);
 
+/// Facility the site was started for, for the error page's templates.
+static FACILITY: OnceLock<ConfigFacility> = OnceLock::new();
+
+/// Add the facility's template globals, used by the layout.
+///
+/// The first facility set here is also used for the error page.
+pub fn add_facility_globals(env: &mut Environment, facility: &ConfigFacility) {
+    let _ = FACILITY.set(facility.clone());
+    env.add_global("facility", minijinja::Value::from_serialize(facility));
+    env.add_global("site_name", facility.site_name());
+}
+
+tokio::task_local! {
+    /// ID of the request being handled, set by the logging middleware.
+    pub static REQUEST_ID: String;
+}
+
+/// ID of the request being handled, if called while handling one.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
 /// Try to construct the error page.
-fn try_build_error_page() -> anyhow::Result<String> {
+fn try_build_error_page(request_id: Option<&str>) -> anyhow::Result<String> {
     let mut env = Environment::new();
+    let facility = FACILITY.get().cloned().unwrap_or_default();
+    add_facility_globals(&mut env, &facility);
     env.add_template("_layout", include_str!("../../templates/_layout.jinja"))?;
     env.add_template("_error", include_str!("../../templates/_error.jinja"))?;
     let template = env.get_template("_error")?;
-    let rendered = template.render(context! {})?;
+    let rendered = template.render(context! { request_id })?;
     Ok(rendered)
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        error!("Unhandled error: {}", self.0);
+        let request_id = current_request_id();
+        match &request_id {
+            Some(id) => error!("Unhandled error in request {id}: {}", self.0),
+            None => error!("Unhandled error: {}", self.0),
+        }
         // attempt to construct the error page, falling back to plain text if anything failed
-        if let Ok(body) = try_build_error_page() {
+        if let Ok(body) = try_build_error_page(request_id.as_deref()) {
             (StatusCode::INTERNAL_SERVER_ERROR, Html(body)).into_response()
         } else {
             (
@@ -77,21 +108,59 @@ impl CacheEntry {
 
 /// App's state, available in all handlers via an extractor.
 pub struct AppState {
-    /// App config
-    pub config: config::Config,
+    /// App config, replaced when it's reloaded
+    config: RwLock<Arc<Config>>,
     /// Access to the DB
     pub db: SqlitePool,
     /// Loaded templates
     pub templates: Environment<'static>,
-    /// Server-side cache
+    /// Server-side cache of rendered pages and snippets that make external
+    /// requests: online controllers and flights (homepage and full page),
+    /// and weather (homepage and full page).
+    ///
+    /// Capacity and per-key lifetimes are set in the config.
     pub cache: Cache<&'static str, CacheEntry>,
 }
 
+impl AppState {
+    pub fn new(
+        config: Config,
+        db: SqlitePool,
+        templates: Environment<'static>,
+        cache: Cache<&'static str, CacheEntry>,
+    ) -> Self {
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            db,
+            templates,
+            cache,
+        }
+    }
+
+    /// The current app config.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Replace the app config, like after reloading it from disk.
+    pub fn set_config(&self, config: Config) {
+        *self.config.write().unwrap() = Arc::new(config);
+    }
+}
+
 /// Key for user info CRUD in session.
 pub const SESSION_USER_INFO_KEY: &str = "USER_INFO";
 /// Key for flashed messages CRUD in session.
 pub const SESSION_FLASHED_MESSAGES_KEY: &str = "FLASHED_MESSAGES";
 
+/// Key in the kvs table for whether the site is in maintenance mode.
+pub const KVS_MAINTENANCE_MODE: &str = "MAINTENANCE_MODE";
+
+/// IPC action for the tasks binary to sync all controllers' activity.
+pub const IPC_ACTIVITY_SYNC: &str = "ACTIVITY_SYNC";
+/// IPC action for the tasks binary to sync a single controller (CID as the data) from VATUSA.
+pub const IPC_VATUSA_SYNC: &str = "VATUSA_SYNC";
+
 /// Data stored in the user's session.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UserInfo {
@@ -99,6 +168,9 @@ pub struct UserInfo {
     pub first_name: String,
     pub last_name: String,
     pub is_staff: bool,
+    /// IANA timezone name to show times in, or UTC if unset
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -22,6 +22,20 @@ pub struct Controller {
     pub is_on_roster: bool,
     pub roles: String,
     pub loa_until: Option<DateTime<Utc>>,
+    /// Whether the controller has name privacy enabled at VATUSA
+    pub name_privacy: bool,
+    /// IANA timezone name the controller wants times shown in
+    pub timezone: Option<String>,
+    /// Whether VATUSA reports the controller as eligible for their next rating
+    pub promotion_eligible: bool,
+    /// When the controller joined the facility, per VATUSA
+    pub join_date: Option<DateTime<Utc>>,
+    /// Set by staff to keep the controller out of feedback and its notifications
+    pub no_feedback_contact: bool,
+    /// When the controller first went missing from the VATUSA roster, if they still are
+    pub roster_missing_since: Option<DateTime<Utc>>,
+    /// Consecutive roster syncs the controller has been missing from
+    pub roster_missed_syncs: u32,
 }
 
 impl Controller {
@@ -56,6 +70,11 @@ pub struct Certification {
     pub value: String,
     pub changed_on: DateTime<Utc>,
     pub set_by: u32,
+    /// Only set for certifications with a configured recurrency requirement
+    pub valid_until: Option<DateTime<Utc>>,
+    /// Last session on one of the certification's positions, for those configured to
+    /// lapse when unused
+    pub last_used: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, FromRow, Serialize)]
@@ -66,6 +85,17 @@ pub struct Activity {
     pub minutes: u32,
 }
 
+#[derive(Debug, FromRow, Serialize)]
+pub struct Atis {
+    pub id: u32,
+    pub facility: String,
+    pub letter: String,
+    pub atis_type: String,
+    pub airport_conditions: String,
+    pub notams: String,
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, FromRow, Serialize)]
 pub struct Feedback {
     pub id: u32,
@@ -80,7 +110,17 @@ pub struct Feedback {
     pub posted_to_discord: bool,
 }
 
+/// Number of feedback entries for a month, rating, and position.
 #[derive(Debug, FromRow, Serialize)]
+pub struct FeedbackCount {
+    /// "YYYY-MM"
+    pub month: String,
+    pub rating: String,
+    pub position: String,
+    pub count: u32,
+}
+
+#[derive(Debug, FromRow, Serialize, Clone)]
 pub struct Resource {
     pub id: u32,
     pub category: String,
@@ -106,11 +146,24 @@ pub struct Event {
     pub id: u32,
     pub published: bool,
     pub complete: bool,
+    pub archived: bool,
     pub name: String,
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     pub description: Option<String>,
     pub image_url: Option<String>,
+    /// Lowest controller rating that can register, if restricted
+    pub min_rating: Option<i8>,
+    pub registration_open: bool,
+}
+
+/// A controller's acknowledgment that they've read an SOP resource.
+#[derive(Debug, FromRow, Serialize)]
+pub struct SopInitial {
+    pub id: u32,
+    pub cid: u32,
+    pub resource_id: u32,
+    pub created_date: DateTime<Utc>,
 }
 
 #[derive(Debug, FromRow, Serialize)]
@@ -127,10 +180,100 @@ pub struct EventRegistration {
     pub event_id: u32,
     pub position_id: u32,
     pub cid: u32,
+    /// Whether every position in the requested category was taken when registering
+    ///
+    /// Only a record; use `registration_waitlisted` for the current status.
+    pub waitlisted: bool,
+}
+
+/// A controller's registration for an event, with any position they've been assigned.
+#[derive(Debug, FromRow, Serialize)]
+pub struct EventAssignment {
+    pub event_id: u32,
+    pub event_name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub requested_position: Option<String>,
+    pub assigned_position: Option<String>,
+    pub requested_position_id: u32,
+    /// Filled in from the event's current positions
+    #[sqlx(default)]
+    pub waitlisted: bool,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct NewsEntry {
+    pub id: u32,
+    pub title: String,
+    pub body: String,
+    pub created_date: DateTime<Utc>,
+    pub posted_by: u32,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct LogEntry {
+    pub id: u32,
+    pub message: String,
+    /// One of "info", "audit", or "security"
+    pub category: String,
+    /// Controller the entry is about, if any
+    pub cid: Option<u32>,
+    pub created_date: DateTime<Utc>,
+}
+
+/// Message from the site for the tasks binary.
+#[derive(Debug, FromRow, Serialize)]
+pub struct IpcMessage {
+    pub id: u32,
+    pub action: String,
+    pub data: String,
+    pub created_date: DateTime<Utc>,
+}
+
+/// Last rating history fetched from VATUSA, stored as JSON.
+#[derive(Debug, FromRow, Serialize)]
+pub struct RatingHistorySnapshot {
+    pub cid: u32,
+    pub data: String,
+    pub refreshed: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct SoloCert {
+    pub id: u32,
+    pub cid: u32,
+    pub issued_by: u32,
+    pub position: String,
+    /// Whether the cert was successfully sent to VATUSA
+    pub reported: bool,
+    pub created_date: DateTime<Utc>,
+    pub expiration_date: DateTime<Utc>,
+}
+
+/// A block of time a controller plans to be online.
+#[derive(Debug, FromRow, Serialize)]
+pub struct ScheduleEntry {
+    pub id: u32,
+    pub cid: u32,
+    pub position: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub created_date: DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct StaffNote {
+    pub id: u32,
+    pub cid: u32,
+    pub by: u32,
+    pub comment: String,
+    pub date: DateTime<Utc>,
 }
 
 /// Statements to create tables. Only ran when the DB file does not exist,
 /// so no migration or "IF NOT EXISTS" conditions need to be added.
+///
+/// Changes that existing DBs need also go in `MIGRATIONS`.
 pub const CREATE_TABLES: &str = r#"
 CREATE TABLE controller (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -145,16 +288,29 @@ CREATE TABLE controller (
     home_facility TEXT,
     is_on_roster INTEGER,
     roles TEXT,
-    loa_until TEXT
+    loa_until TEXT,
+    name_privacy INTEGER NOT NULL DEFAULT FALSE,
+    timezone TEXT,
+    promotion_eligible INTEGER NOT NULL DEFAULT FALSE,
+    join_date TEXT,
+    no_feedback_contact INTEGER NOT NULL DEFAULT FALSE,
+    roster_missing_since TEXT,
+    roster_missed_syncs INTEGER NOT NULL DEFAULT 0
 ) STRICT;
 
+CREATE UNIQUE INDEX controller_operating_initials
+    ON controller(operating_initials)
+    WHERE operating_initials IS NOT NULL AND operating_initials <> '';
+
 CREATE TABLE certification (
     id INTEGER PRIMARY KEY NOT NULL,
     cid INTEGER NOT NULL,
     name TEXT NOT NULL,
     value TEXT NOT NULL,
     changed_on TEXT NOT NULL,
-    set_by INTEGER NOT NULL
+    set_by INTEGER NOT NULL,
+    valid_until TEXT,
+    last_used TEXT
 ) STRICT;
 
 CREATE TABLE feedback (
@@ -176,7 +332,8 @@ CREATE TABLE activity (
     month TEXT NOT NULL,
     minutes INTEGER NOT NULL,
 
-    FOREIGN KEY (cid) REFERENCES controller(cid)
+    FOREIGN KEY (cid) REFERENCES controller(cid),
+    UNIQUE(cid, month)
 ) STRICT;
 
 CREATE TABLE resource (
@@ -188,6 +345,45 @@ CREATE TABLE resource (
     updated TEXT NOT NULL
 ) STRICT;
 
+CREATE TABLE atis (
+    id INTEGER PRIMARY KEY NOT NULL,
+    facility TEXT NOT NULL,
+    letter TEXT NOT NULL,
+    atis_type TEXT NOT NULL,
+    airport_conditions TEXT NOT NULL,
+    notams TEXT NOT NULL,
+    timestamp TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE kvs (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE resource_category (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    ordering INTEGER NOT NULL
+) STRICT;
+
+CREATE TABLE sop_initial (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    resource_id INTEGER NOT NULL,
+    created_date TEXT NOT NULL,
+
+    UNIQUE(cid, resource_id)
+) STRICT;
+
+CREATE TABLE position_preference (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    ordering INTEGER NOT NULL,
+
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
 CREATE TABLE visitor_request (
     id INTEGER PRIMARY KEY NOT NULL,
     cid INTEGER NOT NULL,
@@ -203,11 +399,14 @@ CREATE TABLE event (
     created_by INTEGER NOT NULL,
     published INTEGER NOT NULL DEFAULT FALSE,
     complete INTEGER NOT NULL DEFAULT FALSE,
+    archived INTEGER NOT NULL DEFAULT FALSE,
     name TEXT NOT NULL,
     start TEXT NOT NULL,
     end TEXT NOT NULL,
     description TEXT,
     image_url TEXT,
+    min_rating INTEGER,
+    registration_open INTEGER NOT NULL DEFAULT TRUE,
 
     FOREIGN KEY (created_by) REFERENCES controller(id)
 ) STRICT;
@@ -227,13 +426,282 @@ CREATE TABLE event_registration (
     event_id INTEGER NOT NULL,
     position_id INTEGER NOT NULL,
     cid INTEGER NOT NULL,
+    waitlisted INTEGER NOT NULL DEFAULT FALSE,
 
     FOREIGN KEY (event_id) REFERENCES event(id),
     FOREIGN KEY (position_id) REFERENCES event_position(id),
     FOREIGN KEY (cid) REFERENCES controller(cid)
 ) STRICT;
+
+CREATE TABLE news (
+    id INTEGER PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+    posted_by INTEGER NOT NULL,
+
+    FOREIGN KEY (posted_by) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE solo_cert (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    issued_by INTEGER NOT NULL,
+    position TEXT NOT NULL,
+    reported INTEGER NOT NULL DEFAULT FALSE,
+    created_date TEXT NOT NULL,
+    expiration_date TEXT NOT NULL,
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE staff_note (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    by INTEGER NOT NULL,
+    comment TEXT NOT NULL,
+    date TEXT NOT NULL,
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE log (
+    id INTEGER PRIMARY KEY NOT NULL,
+    message TEXT NOT NULL,
+    category TEXT NOT NULL DEFAULT 'info',
+    cid INTEGER,
+    created_date TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE ipc (
+    id INTEGER PRIMARY KEY NOT NULL,
+    action TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+
+    UNIQUE(action, data)
+) STRICT;
+
+CREATE TABLE rating_history (
+    cid INTEGER PRIMARY KEY NOT NULL,
+    data TEXT NOT NULL,
+    refreshed TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE controller_schedule (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    position TEXT NOT NULL,
+    start TEXT NOT NULL,
+    end TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+
+CREATE TABLE schema_version (
+    id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+    version INTEGER NOT NULL
+) STRICT;
 "#;
 
+/// Schema changes for existing DBs, applied in order on startup.
+///
+/// A DB's `schema_version` is the number of these it has had applied. New
+/// DBs get the full schema from `CREATE_TABLES` and start at the latest
+/// version, so append here and update `CREATE_TABLES` to match; never edit
+/// or reorder entries that have shipped. DBs from before versioning have the
+/// original schema and start at version 0, so together these take that schema
+/// to what `CREATE_TABLES` makes.
+pub const MIGRATIONS: &[&str] = &[
+    // 1: certification expiry
+    "ALTER TABLE certification ADD COLUMN valid_until TEXT",
+    // 2: news
+    "
+CREATE TABLE IF NOT EXISTS news (
+    id INTEGER PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+    posted_by INTEGER NOT NULL,
+
+    FOREIGN KEY (posted_by) REFERENCES controller(cid)
+) STRICT;
+",
+    // 3: archived events
+    "ALTER TABLE event ADD COLUMN archived INTEGER NOT NULL DEFAULT FALSE",
+    // 4: unique operating initials, keeping them for whoever had them first
+    "
+UPDATE controller SET operating_initials=NULL
+WHERE operating_initials IS NOT NULL AND operating_initials <> ''
+AND id NOT IN (
+    SELECT MIN(id) FROM controller
+    WHERE operating_initials IS NOT NULL AND operating_initials <> ''
+    GROUP BY operating_initials
+);
+CREATE UNIQUE INDEX IF NOT EXISTS controller_operating_initials
+    ON controller(operating_initials)
+    WHERE operating_initials IS NOT NULL AND operating_initials <> '';
+",
+    // 5: audit log
+    "
+CREATE TABLE IF NOT EXISTS log (
+    id INTEGER PRIMARY KEY NOT NULL,
+    message TEXT NOT NULL,
+    cid INTEGER,
+    created_date TEXT NOT NULL
+) STRICT;
+",
+    // 6: staff notes
+    "
+CREATE TABLE IF NOT EXISTS staff_note (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    by INTEGER NOT NULL,
+    comment TEXT NOT NULL,
+    date TEXT NOT NULL,
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+",
+    // 7: solo certs
+    "
+CREATE TABLE IF NOT EXISTS solo_cert (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    issued_by INTEGER NOT NULL,
+    position TEXT NOT NULL,
+    reported INTEGER NOT NULL DEFAULT FALSE,
+    created_date TEXT NOT NULL,
+    expiration_date TEXT NOT NULL,
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+",
+    // 8: audit log categories
+    "ALTER TABLE log ADD COLUMN category TEXT NOT NULL DEFAULT 'info'",
+    // 9: rating history
+    "
+CREATE TABLE IF NOT EXISTS rating_history (
+    cid INTEGER PRIMARY KEY NOT NULL,
+    data TEXT NOT NULL,
+    refreshed TEXT NOT NULL
+) STRICT;
+",
+    // 10: site to tasks IPC
+    "
+CREATE TABLE IF NOT EXISTS ipc (
+    id INTEGER PRIMARY KEY NOT NULL,
+    action TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+
+    UNIQUE(action, data)
+) STRICT;
+",
+    // 11: name privacy
+    "ALTER TABLE controller ADD COLUMN name_privacy INTEGER NOT NULL DEFAULT FALSE",
+    // 12: resource category ordering
+    "
+CREATE TABLE IF NOT EXISTS resource_category (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    ordering INTEGER NOT NULL
+) STRICT;
+",
+    // 13: ATIS
+    "
+CREATE TABLE IF NOT EXISTS atis (
+    id INTEGER PRIMARY KEY NOT NULL,
+    facility TEXT NOT NULL,
+    letter TEXT NOT NULL,
+    atis_type TEXT NOT NULL,
+    airport_conditions TEXT NOT NULL,
+    notams TEXT NOT NULL,
+    timestamp TEXT NOT NULL
+) STRICT;
+",
+    // 14: event minimum rating
+    "ALTER TABLE event ADD COLUMN min_rating INTEGER",
+    // 15: event registration closing
+    "ALTER TABLE event ADD COLUMN registration_open INTEGER NOT NULL DEFAULT TRUE",
+    // 16: key-value store
+    "
+CREATE TABLE IF NOT EXISTS kvs (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+) STRICT;
+",
+    // 17: controller timezones
+    "ALTER TABLE controller ADD COLUMN timezone TEXT",
+    // 18: position preferences
+    "
+CREATE TABLE IF NOT EXISTS position_preference (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    ordering INTEGER NOT NULL,
+
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+",
+    // 19: promotion eligibility
+    "ALTER TABLE controller ADD COLUMN promotion_eligible INTEGER NOT NULL DEFAULT FALSE",
+    // 20: facility join date
+    "ALTER TABLE controller ADD COLUMN join_date TEXT",
+    // 21: SOP initials
+    "
+CREATE TABLE IF NOT EXISTS sop_initial (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    resource_id INTEGER NOT NULL,
+    created_date TEXT NOT NULL,
+
+    UNIQUE(cid, resource_id)
+) STRICT;
+",
+    // 22: feedback contact opt-out
+    "ALTER TABLE controller ADD COLUMN no_feedback_contact INTEGER NOT NULL DEFAULT FALSE",
+    // 23: event waitlist
+    "ALTER TABLE event_registration ADD COLUMN waitlisted INTEGER NOT NULL DEFAULT FALSE",
+    // 24: unique activity per controller and month
+    "
+DELETE FROM activity WHERE id NOT IN (SELECT MAX(id) FROM activity GROUP BY cid, month);
+CREATE UNIQUE INDEX IF NOT EXISTS activity_cid_month ON activity(cid, month);
+",
+    // 25: controller schedule
+    "
+CREATE TABLE IF NOT EXISTS controller_schedule (
+    id INTEGER PRIMARY KEY NOT NULL,
+    cid INTEGER NOT NULL,
+    position TEXT NOT NULL,
+    start TEXT NOT NULL,
+    end TEXT NOT NULL,
+    created_date TEXT NOT NULL,
+
+    FOREIGN KEY (cid) REFERENCES controller(cid)
+) STRICT;
+",
+    // 26: certification usage
+    "ALTER TABLE certification ADD COLUMN last_used TEXT",
+    // 27: roster removal grace
+    "
+ALTER TABLE controller ADD COLUMN roster_missing_since TEXT;
+ALTER TABLE controller ADD COLUMN roster_missed_syncs INTEGER NOT NULL DEFAULT 0;
+",
+];
+pub const CREATE_SCHEMA_VERSION: &str = "
+CREATE TABLE IF NOT EXISTS schema_version (
+    id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+    version INTEGER NOT NULL
+) STRICT
+";
+pub const GET_SCHEMA_VERSION: &str = "SELECT version FROM schema_version WHERE id=1";
+pub const SET_SCHEMA_VERSION: &str = "
+INSERT INTO schema_version
+    (id, version)
+VALUES
+    (1, $1)
+ON CONFLICT(id) DO UPDATE SET
+    version=excluded.version
+";
+
 pub const UPSERT_USER_LOGIN: &str = "
 INSERT INTO controller
     (id, cid, first_name, last_name, email, is_on_roster)
@@ -249,9 +717,9 @@ WHERE
 
 pub const UPSERT_USER_TASK: &str = "
 INSERT INTO controller
-    (id, cid, first_name, last_name, email, rating, home_facility, is_on_roster, roles)
+    (id, cid, first_name, last_name, email, rating, home_facility, is_on_roster, roles, name_privacy, promotion_eligible, join_date)
 VALUES
-    (NULL, $1, $2, $3, $4, $5, $6, $7, $8)
+    (NULL, $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
 ON CONFLICT(cid) DO UPDATE SET
     first_name=excluded.first_name,
     last_name=excluded.last_name,
@@ -259,7 +727,10 @@ ON CONFLICT(cid) DO UPDATE SET
     rating=excluded.rating,
     home_facility=excluded.home_facility,
     is_on_roster=excluded.is_on_roster,
-    roles=excluded.roles
+    roles=excluded.roles,
+    name_privacy=excluded.name_privacy,
+    promotion_eligible=excluded.promotion_eligible,
+    join_date=excluded.join_date
 WHERE
     cid=excluded.cid
 ";
@@ -269,19 +740,84 @@ pub const GET_ALL_CONTROLLERS_ON_ROSTER: &str = "SELECT * FROM controller WHERE
 pub const GET_ALL_CONTROLLER_CIDS: &str = "SELECT cid FROM controller";
 pub const GET_ALL_ROSTER_CONTROLLER_CIDS: &str =
     "SELECT cid FROM controller WHERE is_on_roster=TRUE";
-pub const UPDATE_REMOVED_FROM_ROSTER: &str = "UPDATE controller SET is_on_roster=0 WHERE cid=$1";
+pub const UPDATE_REMOVED_FROM_ROSTER: &str = "UPDATE controller SET is_on_roster=0, roster_missing_since=NULL, roster_missed_syncs=0 WHERE cid=$1";
+pub const UPDATE_MISSING_FROM_ROSTER: &str = "
+UPDATE controller SET
+    roster_missing_since=COALESCE(roster_missing_since, $2),
+    roster_missed_syncs=roster_missed_syncs+1
+WHERE cid=$1
+RETURNING roster_missed_syncs
+";
+pub const CLEAR_MISSING_FROM_ROSTER: &str = "
+UPDATE controller SET
+    roster_missing_since=NULL,
+    roster_missed_syncs=0
+WHERE roster_missing_since IS NOT NULL AND cid IN (SELECT value FROM json_each($1))
+";
 pub const GET_CONTROLLER_BY_CID: &str = "SELECT * FROM controller WHERE cid=$1";
+pub const UPDATE_CONTROLLER_FROM_VATUSA: &str = "
+UPDATE controller SET
+    first_name=$1,
+    last_name=$2,
+    rating=$3,
+    home_facility=$4,
+    is_on_roster=$5
+WHERE
+    cid=$6
+";
+pub const UPDATE_CONTROLLER_ROLES: &str = "UPDATE controller SET roles=$1 WHERE cid=$2";
+pub const UPDATE_CONTROLLER_TIMEZONE: &str = "UPDATE controller SET timezone=$1 WHERE cid=$2";
+pub const GET_ALL_OIS: &str = "SELECT operating_initials FROM controller WHERE operating_initials IS NOT NULL AND operating_initials <> ''";
+pub const UPDATE_CONTROLLER_NO_FEEDBACK_CONTACT: &str =
+    "UPDATE controller SET no_feedback_contact=$1 WHERE cid=$2";
+pub const GET_NO_FEEDBACK_CONTACT_CONTROLLERS: &str =
+    "SELECT * FROM controller WHERE no_feedback_contact=TRUE";
+pub const GET_CONTROLLER_EMAIL: &str = "SELECT email FROM controller WHERE cid=$1";
 pub const GET_CONTROLLER_CIDS_AND_NAMES: &str = "SELECT cid, first_name, last_name from controller";
+pub const UPDATE_CONTROLLER_OIS: &str = "
+UPDATE controller
+SET operating_initials=$1
+WHERE
+    cid=$2
+    AND NOT EXISTS (
+        SELECT 1 FROM controller WHERE operating_initials=$1 AND cid<>$2
+    )
+";
+pub const CLEAR_CONTROLLER_OIS: &str = "UPDATE controller SET operating_initials=NULL WHERE cid=$1";
 
 pub const GET_ALL_CERTIFICATIONS: &str = "SELECT * FROM certification";
+pub const GET_ALL_CERTIFICATIONS_FOR: &str = "SELECT * FROM certification WHERE cid=$1";
+pub const GET_CERTIFICATION_FOR: &str = "SELECT * FROM certification WHERE cid=$1 AND name=$2";
+pub const DELETE_CERTIFICATIONS_FOR: &str = "DELETE FROM certification WHERE cid=$1";
+pub const UPDATE_CERTIFICATION: &str =
+    "UPDATE certification SET value=$1, changed_on=$2, set_by=$3, valid_until=$4 WHERE id=$5";
+pub const INSERT_CERTIFICATION: &str = "
+INSERT INTO certification
+    (id, cid, name, value, changed_on, set_by, valid_until)
+VALUES
+    (NULL, $1, $2, $3, $4, $5, $6)
+";
+pub const UPDATE_CERTIFICATION_VALID_UNTIL: &str =
+    "UPDATE certification SET valid_until=$1 WHERE id=$2";
+pub const UPDATE_CERTIFICATION_LAST_USED: &str = "
+UPDATE certification SET last_used=$1
+WHERE cid=$2 AND name=$3 AND (last_used IS NULL OR last_used<$1)
+";
 
 pub const GET_ALL_ACTIVITY: &str = "SELECT * FROM activity";
-pub const DELETE_ACTIVITY_FOR_CID: &str = "DELETE FROM activity WHERE cid=$1";
-pub const INSERT_INTO_ACTIVITY: &str = "
+pub const GET_ACTIVITY_FOR: &str = "SELECT * FROM activity WHERE cid=$1";
+pub const UPSERT_ACTIVITY: &str = "
 INSERT INTO activity
     (id, cid, month, minutes)
 VALUES
     (NULL, $1, $2, $3)
+ON CONFLICT(cid, month) DO UPDATE SET
+    minutes=excluded.minutes
+";
+/// Remove the controller's months that aren't in the JSON array of months.
+pub const DELETE_ACTIVITY_NOT_IN: &str = "
+DELETE FROM activity
+WHERE cid=$1 AND month NOT IN (SELECT value FROM json_each($2))
 ";
 
 pub const INSERT_FEEDBACK: &str = "
@@ -290,17 +826,251 @@ INSERT INTO feedback
 VALUES
     (NULL, $1, $2, $3, $4, $5, $6)
 ";
-pub const GET_ALL_PENDING_FEEDBACK: &str =
-    "SELECT * FROM feedback WHERE reviewed_by_cid IS NULL OR reviewer_action='archive'";
+pub const GET_FEEDBACK_FOR_REVIEW_PAGE: &str = "
+SELECT * FROM feedback
+WHERE reviewer_action=$1
+AND ($2 = '' OR controller LIKE '%' || $2 || '%' OR position LIKE '%' || $2 || '%')
+AND (controller = '0') = $6
+ORDER BY
+    CASE WHEN $3 THEN created_date END ASC,
+    created_date DESC
+LIMIT $4 OFFSET $5
+";
+pub const COUNT_FEEDBACK_FOR_REVIEW: &str = "
+SELECT COUNT(*) FROM feedback
+WHERE reviewer_action=$1
+AND ($2 = '' OR controller LIKE '%' || $2 || '%' OR position LIKE '%' || $2 || '%')
+AND (controller = '0') = $3
+";
+pub const GET_FEEDBACK_FOR: &str =
+    "SELECT * FROM feedback WHERE controller=$1 OR controller=$2 COLLATE NOCASE ORDER BY created_date DESC";
+pub const GET_POSTED_FEEDBACK_FOR: &str = "SELECT * FROM feedback WHERE (controller=$1 OR controller=$2 COLLATE NOCASE) AND posted_to_discord=TRUE ORDER BY created_date DESC";
+pub const GET_FEEDBACK_SUBMITTED_BY: &str =
+    "SELECT * FROM feedback WHERE submitter_cid=$1 ORDER BY created_date DESC";
 pub const GET_FEEDBACK_BY_ID: &str = "SELECT * FROM feedback WHERE id=$1";
 pub const UPDATE_FEEDBACK_TAKE_ACTION: &str =
     "UPDATE feedback SET reviewed_by_cid=$1, reviewer_action=$2, posted_to_discord=$3 WHERE id=$4";
+pub const UPDATE_FEEDBACK_AUTO_APPROVED: &str =
+    "UPDATE feedback SET reviewer_action='auto', posted_to_discord=TRUE WHERE id=$1";
+pub const UPDATE_FEEDBACK_CONTROLLER: &str = "UPDATE feedback SET controller=$1 WHERE id=$2";
 pub const DELETE_FROM_FEEDBACK: &str = "DELETE FROM feedback WHERE id=$1";
+pub const GET_FEEDBACK_COUNT_SINCE: &str = "SELECT COUNT(*) FROM feedback WHERE created_date>$1";
+pub const GET_UPCOMING_SCHEDULE: &str =
+    "SELECT * FROM controller_schedule WHERE end>$1 ORDER BY start";
+pub const GET_SCHEDULE_ENTRY: &str = "SELECT * FROM controller_schedule WHERE id=$1";
+pub const INSERT_SCHEDULE_ENTRY: &str = "
+INSERT INTO controller_schedule
+    (id, cid, position, start, end, created_date)
+VALUES
+    (NULL, $1, $2, $3, $4, $5)
+";
+pub const DELETE_SCHEDULE_ENTRY: &str = "DELETE FROM controller_schedule WHERE id=$1";
+
+pub const GET_FEEDBACK_COUNTS: &str = "
+SELECT substr(created_date, 1, 7) AS month, rating, position, COUNT(*) AS count
+FROM feedback
+GROUP BY month, rating, position
+";
 
 pub const GET_ALL_RESOURCES: &str = "SELECT * FROM resource";
+pub const GET_RESOURCE_BY_ID: &str = "SELECT * FROM resource WHERE id=$1";
+pub const GET_RESOURCE_BY_FILE_NAME: &str = "SELECT * FROM resource WHERE file_name=$1";
+pub const CREATE_NEW_RESOURCE: &str = "
+INSERT INTO resource
+    (id, category, name, file_name, link, updated)
+VALUES
+    (NULL, $1, $2, $3, $4, $5)
+";
+pub const UPDATE_RESOURCE: &str = "
+UPDATE resource SET
+    category=$1,
+    name=$2,
+    file_name=$3,
+    link=$4,
+    updated=$5
+WHERE
+    id=$6
+";
+pub const DELETE_RESOURCE: &str = "DELETE FROM resource WHERE id=$1";
+pub const GET_ALL_SOP_INITIALS: &str = "SELECT * FROM sop_initial";
+pub const GET_SOP_INITIALS_FOR: &str = "SELECT * FROM sop_initial WHERE cid=$1";
+pub const INSERT_SOP_INITIAL: &str = "
+INSERT INTO sop_initial
+    (id, cid, resource_id, created_date)
+VALUES
+    (NULL, $1, $2, $3)
+ON CONFLICT(cid, resource_id) DO NOTHING
+";
+pub const INSERT_ATIS_ENTRY: &str = "
+INSERT INTO atis
+    (id, facility, letter, atis_type, airport_conditions, notams, timestamp)
+VALUES
+    (NULL, $1, $2, $3, $4, $5, $6)
+";
+pub const GET_ALL_ATIS_ENTRIES: &str = "SELECT * FROM atis ORDER BY timestamp DESC";
+pub const DELETE_ATIS_OLDER_THAN: &str = "DELETE FROM atis WHERE timestamp < $1";
+
+pub const GET_KVS_ENTRY: &str = "SELECT value FROM kvs WHERE key=$1";
+pub const UPSERT_KVS_ENTRY: &str = "
+INSERT INTO kvs
+    (key, value)
+VALUES
+    ($1, $2)
+ON CONFLICT(key) DO UPDATE SET
+    value=excluded.value
+WHERE
+    key=excluded.key
+";
+
+pub const GET_POSITION_PREFERENCES: &str =
+    "SELECT category FROM position_preference WHERE cid=$1 ORDER BY ordering";
+pub const DELETE_POSITION_PREFERENCES: &str = "DELETE FROM position_preference WHERE cid=$1";
+pub const INSERT_POSITION_PREFERENCE: &str = "
+INSERT INTO position_preference
+    (id, cid, category, ordering)
+VALUES
+    (NULL, $1, $2, $3)
+";
+
+pub const GET_RESOURCE_CATEGORY_ORDER: &str =
+    "SELECT name FROM resource_category ORDER BY ordering";
+pub const GET_RESOURCE_CATEGORIES_IN_USE: &str =
+    "SELECT DISTINCT category FROM resource ORDER BY category";
+pub const DELETE_RESOURCE_CATEGORIES: &str = "DELETE FROM resource_category";
+pub const INSERT_RESOURCE_CATEGORY: &str = "
+INSERT INTO resource_category
+    (id, name, ordering)
+VALUES
+    (NULL, $1, $2)
+";
+pub const GET_ALL_RESOURCE_FILE_NAMES: &str =
+    "SELECT file_name FROM resource WHERE file_name IS NOT NULL";
 
 pub const GET_PENDING_VISITOR_REQ_FOR: &str = "SELECT * FROM visitor_request WHERE cid=$1";
 pub const INSERT_INTO_VISITOR_REQ: &str =
     "INSERT INTO visitor_request VALUES (NULL, $1, $2, $3, $4, $5, $6);";
+pub const GET_VISITOR_REQ_COUNT_SINCE: &str = "SELECT COUNT(*) FROM visitor_request WHERE date>$1";
 
 pub const GET_EVENT: &str = "SELECT * FROM event WHERE id=$1";
+pub const GET_PUBLISHED_EVENTS_BETWEEN: &str =
+    "SELECT * FROM event WHERE published=TRUE AND archived=FALSE AND start>=$1 AND start<$2 ORDER BY start";
+pub const GET_UPCOMING_EVENTS: &str =
+    "SELECT * FROM event WHERE published=TRUE AND archived=FALSE AND end>$1 ORDER BY start";
+pub const INSERT_EVENT: &str = "
+INSERT INTO event
+    (id, created_by, published, complete, archived, name, start, end, description, image_url, min_rating)
+VALUES
+    (NULL, $1, FALSE, FALSE, FALSE, $2, $3, $4, $5, $6, $7)
+";
+pub const UPDATE_EVENT_MIN_RATING: &str = "UPDATE event SET min_rating=$1 WHERE id=$2";
+pub const UPDATE_EVENT_REGISTRATION_OPEN: &str =
+    "UPDATE event SET registration_open=$1 WHERE id=$2";
+pub const GET_ARCHIVED_EVENTS: &str = "SELECT * FROM event WHERE archived=TRUE ORDER BY start DESC";
+pub const UPDATE_EVENT_ARCHIVED: &str = "UPDATE event SET archived=$1 WHERE id=$2";
+pub const GET_UPCOMING_EVENT_ASSIGNMENTS_FOR: &str = "
+SELECT
+    event.id AS event_id,
+    event.name AS event_name,
+    event.start,
+    event.end,
+    requested.name AS requested_position,
+    assigned.name AS assigned_position,
+    event_registration.position_id AS requested_position_id
+FROM event_registration
+JOIN event ON event_registration.event_id = event.id
+LEFT JOIN event_position requested ON event_registration.position_id = requested.id
+LEFT JOIN event_position assigned
+    ON assigned.event_id = event.id AND assigned.cid = event_registration.cid
+WHERE event_registration.cid=$1 AND event.end > $2 AND event.archived=FALSE
+ORDER BY event.start ASC
+";
+pub const GET_EVENT_REGISTRATIONS_FOR: &str = "SELECT * FROM event_registration WHERE cid=$1";
+pub const GET_EVENT_REGISTRATIONS: &str =
+    "SELECT * FROM event_registration WHERE event_id=$1 ORDER BY id ASC";
+pub const DELETE_EVENT_REGISTRATIONS: &str = "DELETE FROM event_registration WHERE event_id=$1";
+pub const DELETE_FUTURE_EVENT_REGISTRATIONS_FOR: &str =
+    "DELETE FROM event_registration WHERE cid=$1 AND event_id IN (SELECT id FROM event WHERE end>$2)";
+pub const CLEAR_FUTURE_EVENT_POSITIONS_FOR: &str =
+    "UPDATE event_position SET cid=NULL WHERE cid=$1 AND event_id IN (SELECT id FROM event WHERE end>$2)";
+pub const DELETE_EVENT_REGISTRATION_FOR: &str =
+    "DELETE FROM event_registration WHERE event_id=$1 AND cid=$2";
+pub const INSERT_EVENT_REGISTRATION: &str = "
+INSERT INTO event_registration
+    (id, event_id, position_id, cid, waitlisted)
+VALUES
+    (NULL, $1, $2, $3, $4)
+";
+pub const GET_EVENT_POSITIONS: &str = "SELECT * FROM event_position WHERE event_id=$1";
+pub const INSERT_EVENT_POSITION: &str = "
+INSERT INTO event_position
+    (id, event_id, name, cid)
+VALUES
+    (NULL, $1, $2, NULL)
+";
+pub const GET_EVENT_POSITION: &str = "SELECT * FROM event_position WHERE id=$1";
+pub const UPDATE_EVENT_POSITION_CONTROLLER: &str = "UPDATE event_position SET cid=$1 WHERE id=$2";
+pub const DELETE_EVENT_POSITIONS: &str = "DELETE FROM event_position WHERE event_id=$1";
+pub const DELETE_EVENT: &str = "DELETE FROM event WHERE id=$1";
+
+pub const GET_ALL_NEWS: &str = "SELECT * FROM news ORDER BY created_date DESC";
+pub const INSERT_INTO_NEWS: &str = "
+INSERT INTO news
+    (id, title, body, created_date, posted_by)
+VALUES
+    (NULL, $1, $2, $3, $4)
+";
+pub const DELETE_FROM_NEWS: &str = "DELETE FROM news WHERE id=$1";
+
+pub const GET_ALL_SOLO_CERTS: &str = "SELECT * FROM solo_cert ORDER BY expiration_date ASC";
+pub const GET_SOLO_CERT_BY_ID: &str = "SELECT * FROM solo_cert WHERE id=$1";
+pub const GET_SOLO_CERTS_FOR: &str =
+    "SELECT * FROM solo_cert WHERE cid=$1 ORDER BY expiration_date ASC";
+pub const INSERT_INTO_SOLO_CERT: &str = "
+INSERT INTO solo_cert
+    (id, cid, issued_by, position, reported, created_date, expiration_date)
+VALUES
+    (NULL, $1, $2, $3, $4, $5, $6)
+";
+pub const DELETE_SOLO_CERT: &str = "DELETE FROM solo_cert WHERE id=$1";
+
+pub const GET_STAFF_NOTES_FOR: &str = "SELECT * FROM staff_note WHERE cid=$1 ORDER BY date DESC";
+pub const INSERT_INTO_STAFF_NOTE: &str = "
+INSERT INTO staff_note
+    (id, cid, by, comment, date)
+VALUES
+    (NULL, $1, $2, $3, $4)
+";
+
+pub const INSERT_INTO_LOG: &str = "
+INSERT INTO log
+    (id, message, category, cid, created_date)
+VALUES
+    (NULL, $1, $2, $3, $4)
+";
+pub const GET_RATING_HISTORY_FOR: &str = "SELECT * FROM rating_history WHERE cid=$1";
+pub const UPSERT_RATING_HISTORY: &str = "
+INSERT INTO rating_history
+    (cid, data, refreshed)
+VALUES
+    ($1, $2, $3)
+ON CONFLICT(cid) DO UPDATE SET
+    data=excluded.data,
+    refreshed=excluded.refreshed
+";
+/// Identical messages that are still queued are ignored.
+pub const INSERT_INTO_IPC: &str = "
+INSERT OR IGNORE INTO ipc
+    (id, action, data, created_date)
+VALUES
+    (NULL, $1, $2, $3)
+";
+pub const GET_IPC_MESSAGES: &str = "SELECT * FROM ipc ORDER BY id";
+pub const DELETE_IPC_MESSAGE: &str = "DELETE FROM ipc WHERE id=$1";
+pub const GET_RECENT_LOGS: &str = "
+SELECT * FROM log
+WHERE $1 = '' OR category=$1
+ORDER BY created_date DESC
+LIMIT $2 OFFSET $3
+";
+pub const COUNT_LOGS: &str = "SELECT COUNT(*) FROM log WHERE $1 = '' OR category=$1";
+pub const GET_LOGS_FOR: &str = "SELECT * FROM log WHERE cid=$1 ORDER BY created_date DESC";
@@ -0,0 +1,157 @@
+//! Counts of how calls to the VATUSA and VATSIM APIs turn out.
+//!
+//! Each process keeps its own counts from when it started. The site serves
+//! them at `/api/metrics` and the tasks process logs them every hour.
+
+use once_cell::sync::Lazy;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+static API_CALLS: Lazy<Mutex<BTreeMap<&'static str, ApiCallStats>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// How a single API call turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOutcome {
+    Success,
+    Timeout,
+    ClientError,
+    ServerError,
+    /// Connection problems, unreadable responses, and anything else
+    Failed,
+}
+
+impl CallOutcome {
+    /// Outcome of a call that got a response with this status.
+    pub fn from_status(status: StatusCode) -> Self {
+        if status.is_success() {
+            Self::Success
+        } else if status.is_client_error() {
+            Self::ClientError
+        } else if status.is_server_error() {
+            Self::ServerError
+        } else {
+            Self::Failed
+        }
+    }
+
+    /// Outcome of a call that errored, using the HTTP error it wraps if any.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Self {
+        let mut source = Some(error);
+        while let Some(error) = source {
+            if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+                if error.is_timeout() {
+                    return Self::Timeout;
+                }
+                if let Some(status) = error.status() {
+                    return Self::from_status(status);
+                }
+                return Self::Failed;
+            }
+            source = error.source();
+        }
+        Self::Failed
+    }
+}
+
+/// Outcome counts and latency for one API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ApiCallStats {
+    pub successes: u64,
+    pub timeouts: u64,
+    pub client_errors: u64,
+    pub server_errors: u64,
+    pub other_failures: u64,
+    pub total_millis: u64,
+    pub max_millis: u64,
+}
+
+impl ApiCallStats {
+    /// Count a call.
+    pub fn record(&mut self, outcome: CallOutcome, elapsed: Duration) {
+        match outcome {
+            CallOutcome::Success => self.successes += 1,
+            CallOutcome::Timeout => self.timeouts += 1,
+            CallOutcome::ClientError => self.client_errors += 1,
+            CallOutcome::ServerError => self.server_errors += 1,
+            CallOutcome::Failed => self.other_failures += 1,
+        }
+        let millis = elapsed.as_millis() as u64;
+        self.total_millis += millis;
+        self.max_millis = self.max_millis.max(millis);
+    }
+
+    pub fn calls(&self) -> u64 {
+        self.successes + self.failures()
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.timeouts + self.client_errors + self.server_errors + self.other_failures
+    }
+
+    /// Percent of calls that failed, or 0 if there haven't been any.
+    pub fn failure_percent(&self) -> f64 {
+        match self.calls() {
+            0 => 0.0,
+            calls => self.failures() as f64 / calls as f64 * 100.0,
+        }
+    }
+
+    pub fn average_millis(&self) -> u64 {
+        self.total_millis
+            .checked_div(self.calls())
+            .unwrap_or_default()
+    }
+}
+
+/// Count a call to the API.
+pub fn record_call(api: &'static str, outcome: CallOutcome, elapsed: Duration) {
+    API_CALLS
+        .lock()
+        .expect("API metrics lock poisoned")
+        .entry(api)
+        .or_default()
+        .record(outcome, elapsed);
+}
+
+/// Current counts for every API that's been called.
+pub fn snapshot() -> BTreeMap<&'static str, ApiCallStats> {
+    API_CALLS.lock().expect("API metrics lock poisoned").clone()
+}
+
+/// Send the request, counting how it turned out.
+pub async fn send(api: &'static str, request: RequestBuilder) -> reqwest::Result<Response> {
+    let start = Instant::now();
+    let result = request.send().await;
+    let outcome = match &result {
+        Ok(resp) => CallOutcome::from_status(resp.status()),
+        Err(e) => CallOutcome::from_error(e),
+    };
+    record_call(api, outcome, start.elapsed());
+    result
+}
+
+/// Run an API call made by another library, counting how it turned out.
+pub async fn track<T, E>(
+    api: &'static str,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    E: Error + 'static,
+{
+    let start = Instant::now();
+    let result = call.await;
+    let outcome = match &result {
+        Ok(_) => CallOutcome::Success,
+        Err(e) => CallOutcome::from_error(e),
+    };
+    record_call(api, outcome, start.elapsed());
+    result
+}
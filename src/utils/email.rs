@@ -0,0 +1,34 @@
+//! Sending emails through the configured SMTP server.
+
+use crate::shared::Config;
+use anyhow::Result;
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+/// Send a plain text email to a single recipient.
+///
+/// No templating is done here; callers are responsible for the
+/// full subject and body.
+pub async fn send_mail_raw(
+    config: &Config,
+    recipient: &str,
+    subject: &str,
+    body: &str,
+) -> Result<()> {
+    let email = Message::builder()
+        .from(config.email.from.parse()?)
+        .to(recipient.parse()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body.to_owned())?;
+    let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.email.host)?
+        .credentials(Credentials::new(
+            config.email.user.clone(),
+            config.email.password.clone(),
+        ))
+        .build();
+    mailer.send(email).await?;
+    Ok(())
+}
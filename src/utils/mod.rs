@@ -1,19 +1,42 @@
 //! Various utility structs and functions.
 
 use crate::shared::{
-    sql::{self, Controller},
+    config::{
+        Airport, ConfigApiKey, ConfigCertificationUsage, ConfigCurrency, ConfigDiscordWebhooks,
+        ConfigFacility, QuarterMode,
+    },
+    sql::{
+        self, Activity, Atis, Certification, Controller, EventPosition, EventRegistration,
+        Feedback, FeedbackCount, LogEntry, NewsEntry, Resource, SoloCert, SopInitial,
+        VisitorApplication,
+    },
     Config,
 };
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use log::{error, warn};
 use once_cell::sync::Lazy;
 use reqwest::ClientBuilder;
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqliteRow, Pool, Row, Sqlite};
-use std::collections::HashMap;
+use sha2::Sha256;
+use sqlx::{sqlite::SqliteRow, Executor, Pool, Row, Sqlite};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::SeekFrom,
+    path::Path,
+};
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use vatsim_utils::{distance::AIRPORTS_MAP, live_api::Vatsim, models::V3ResponseData};
 
+pub mod api_metrics;
 pub mod auth;
+pub mod email;
 pub mod flashed_messages;
+pub mod pagination;
 pub mod vatusa;
 
 // I don't know what this is, but there's a SUP in ZDV that has this rating.
@@ -61,8 +84,49 @@ pub struct AirportWeather<'a> {
     pub raw: &'a str,
 }
 
+/// Attempts made at fetching METARs before giving up.
+const METAR_FETCH_ATTEMPTS: u32 = 3;
+
+/// Time to wait after a failed METAR fetch attempt before the next one.
+pub fn metar_retry_delay(attempt: u32) -> std::time::Duration {
+    std::time::Duration::from_millis(250 * 2_u64.pow(attempt.saturating_sub(1)))
+}
+
+/// Get the METARs for the comma-separated airports, one per line.
+///
+/// Transient failures are retried a couple of times with a short backoff.
+pub async fn get_metars(airports: &str) -> Result<String> {
+    let mut attempt = 1;
+    loop {
+        match try_get_metars(airports).await {
+            Ok(text) => return Ok(text),
+            Err(e) if attempt < METAR_FETCH_ATTEMPTS => {
+                warn!("METAR fetch attempt {attempt} failed: {e}");
+                tokio::time::sleep(metar_retry_delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn try_get_metars(airports: &str) -> Result<String> {
+    let resp = api_metrics::send(
+        "vatsim_metar",
+        GENERAL_HTTP_CLIENT.get(format!("https://metar.vatsim.net/{airports}")),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!(
+            "Got status {} from METAR API",
+            resp.status().as_u16()
+        ));
+    }
+    Ok(resp.text().await?)
+}
+
 /// Parse a METAR into a struct of data.
-pub fn parse_metar(line: &str) -> Result<AirportWeather> {
+pub fn parse_metar(line: &str) -> Result<AirportWeather<'_>> {
     let parts: Vec<_> = line.split(' ').collect();
     let airport = parts.first().ok_or_else(|| anyhow!("Blank metar?"))?;
     let mut ceiling = 3_456;
@@ -138,6 +202,180 @@ pub async fn get_simaware_data() -> Result<HashMap<u64, String>> {
     Ok(mapping)
 }
 
+/// How a flight relates to the facility's airspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FlightRelevance {
+    /// Departing one of the airspace's airports for somewhere else
+    Departing,
+    /// Arriving at one of the airspace's airports from somewhere else
+    Arriving,
+    /// Departing and arriving at the airspace's airports
+    Local,
+    /// Not going to or from the airspace's airports, but currently in the airspace
+    Overflight,
+}
+
+/// Whether the point is within the polygon, using ray casting.
+///
+/// Points are (latitude, longitude). Fewer than 3 points never contain anything.
+pub fn point_in_polygon(point: (f64, f64), polygon: &[(f64, f64)]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let (y, x) = point;
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (yi, xi) = polygon[i];
+        let (yj, xj) = polygon[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Largest radius, in nautical miles, that the pilot glance can be asked for.
+pub const GLANCE_MAX_DISTANCE: f64 = 250.0;
+
+/// Radius for the pilot glance from the requested distance, if any.
+///
+/// Missing or nonsensical values use the configured default, and the result
+/// is clamped so the glance can't cover the whole network.
+pub fn glance_distance(default: f64, requested: Option<f64>) -> f64 {
+    requested
+        .filter(|distance| distance.is_finite() && *distance > 0.0)
+        .unwrap_or(default)
+        .clamp(1.0, GLANCE_MAX_DISTANCE)
+}
+
+/// Column to order the pilot glance by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum GlanceSort {
+    Callsign,
+    Aircraft,
+    Distance,
+    #[default]
+    PilotingTime,
+    ControllingTime,
+}
+
+impl GlanceSort {
+    /// Parse the "sort" query parameter, returning `None` for unknown values.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "callsign" => Some(Self::Callsign),
+            "aircraft" => Some(Self::Aircraft),
+            "distance" => Some(Self::Distance),
+            "piloting-time" => Some(Self::PilotingTime),
+            "controlling-time" => Some(Self::ControllingTime),
+            _ => None,
+        }
+    }
+
+    /// Value for the "sort" query parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Callsign => "callsign",
+            Self::Aircraft => "aircraft",
+            Self::Distance => "distance",
+            Self::PilotingTime => "piloting-time",
+            Self::ControllingTime => "controlling-time",
+        }
+    }
+}
+
+/// Compare optional hour totals, keeping unknown values last in either direction.
+pub fn compare_hours(a: Option<f64>, b: Option<f64>, descending: bool) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.total_cmp(&a),
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+/// Hours a VATSIM member has spent piloting and controlling.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MemberStats {
+    pub pilot: f64,
+    pub atc: f64,
+}
+
+/// Get the VATSIM live data feed.
+pub async fn get_vatsim_data() -> Result<V3ResponseData> {
+    let data = api_metrics::track("vatsim_data", async {
+        Vatsim::new().await?.get_v3_data().await
+    })
+    .await?;
+    Ok(data)
+}
+
+/// Get a VATSIM member's piloting and controlling hours.
+pub async fn get_member_stats(cid: u64) -> Result<MemberStats> {
+    let stats = api_metrics::send(
+        "vatsim_member_stats",
+        GENERAL_HTTP_CLIENT.get(format!("https://api.vatsim.net/v2/members/{cid}/stats")),
+    )
+    .await?
+    .error_for_status()?
+    .json()
+    .await?;
+    Ok(stats)
+}
+
+/// Great-circle distance between two (latitude, longitude) points in nautical miles.
+pub fn distance_nm(a: (f64, f64), b: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_NM: f64 = 3_440.065;
+    let (lat_a, lat_b) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_NM * h.sqrt().asin()
+}
+
+/// Location (latitude, longitude) of an airport.
+///
+/// Coordinates in the config's "airports.all" take precedence, falling back to
+/// the airport data bundled with `vatsim_utils`.
+pub fn airport_coordinates(airports: &[Airport], code: &str) -> Option<(f64, f64)> {
+    airports
+        .iter()
+        .find(|airport| airport.code.eq_ignore_ascii_case(code))
+        .and_then(|airport| Some((airport.latitude?, airport.longitude?)))
+        .or_else(|| {
+            AIRPORTS_MAP
+                .get(code.to_uppercase().as_str())
+                .map(|airport| (airport.latitude, airport.longitude))
+        })
+}
+
+/// Determine how, if at all, the flight is relevant to the facility's airspace.
+///
+/// Uses the config's "airports.all" for the flight plan's airports and
+/// "airports.boundary" for the flight's current position.
+pub fn flight_relevance(
+    config: &Config,
+    departure: Option<&str>,
+    arrival: Option<&str>,
+    position: (f64, f64),
+) -> Option<FlightRelevance> {
+    let is_ours = |code: Option<&str>| {
+        code.is_some_and(|code| config.airports.all.iter().any(|a| a.code == code))
+    };
+    match (is_ours(departure), is_ours(arrival)) {
+        (true, true) => Some(FlightRelevance::Local),
+        (true, false) => Some(FlightRelevance::Departing),
+        (false, true) => Some(FlightRelevance::Arriving),
+        (false, false) if point_in_polygon(position, &config.airports.boundary) => {
+            Some(FlightRelevance::Overflight)
+        }
+        _ => None,
+    }
+}
+
 /// Check whether the VATSIM session position is in this facility's airspace.
 ///
 /// Relies on the config's "stats.position_prefixes" and suffixes.
@@ -157,6 +395,115 @@ pub fn position_in_facility_airspace(config: &Config, position: &str) -> bool {
         .any(|suffix| position.ends_with(suffix))
 }
 
+/// Normalize a position entered by a user, like on the feedback form.
+///
+/// Uppercases the position and checks it's a facility position by the
+/// configured prefixes and suffixes, if any. Returns `None` for positions
+/// that don't look valid.
+pub fn normalize_position(config: &Config, position: &str) -> Option<String> {
+    let position = position.trim().to_uppercase();
+    if position.len() < 5
+        || !position
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    if config.stats.position_prefixes.is_empty() && config.stats.position_suffixes.is_empty() {
+        return Some(position);
+    }
+    if position_in_facility_airspace(config, &position) {
+        Some(position)
+    } else {
+        None
+    }
+}
+
+/// Number of single-character edits to turn one string into another.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Suggest the route closest to a path that wasn't found.
+///
+/// Route parameters (":cid") are filled in from the path when the segment counts
+/// line up and the values are simple, so "/controllers/123/events" suggests
+/// "/controller/123/events". Returns `None` if nothing is reasonably close.
+pub fn suggest_route(path: &str, routes: &[&str]) -> Option<String> {
+    let path = path.trim_end_matches('/');
+    let path_segments: Vec<_> = path.split('/').collect();
+    routes
+        .iter()
+        .map(|route| {
+            let route_segments: Vec<_> = route.split('/').collect();
+            let filled: Vec<_> = route_segments
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| match path_segments.get(i) {
+                    Some(value)
+                        if segment.starts_with(':')
+                            && route_segments.len() == path_segments.len()
+                            && is_route_param(value) =>
+                    {
+                        *value
+                    }
+                    _ => segment,
+                })
+                .collect();
+            let candidate = filled.join("/");
+            (edit_distance(path, &candidate), candidate)
+        })
+        .filter(|(_, candidate)| !candidate.contains(':'))
+        .min_by_key(|(distance, _)| *distance)
+        .filter(|(distance, _)| *distance > 0 && *distance <= (path.len() / 4).max(2))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Whether the path is one of the routes, with any parameters filled in.
+pub fn matches_route(path: &str, routes: &[&str]) -> bool {
+    let path_segments: Vec<_> = path.split('/').collect();
+    routes.iter().any(|route| {
+        let route_segments: Vec<_> = route.split('/').collect();
+        route_segments.len() == path_segments.len()
+            && route_segments
+                .iter()
+                .zip(&path_segments)
+                .all(|(segment, value)| {
+                    segment == value || (segment.starts_with(':') && is_route_param(value))
+                })
+    })
+}
+
+/// Whether a path segment is simple enough to fill in a route parameter.
+fn is_route_param(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Filter file names from the assets directory to those no resource points to.
+pub async fn find_orphaned_assets(db: &Pool<Sqlite>, file_names: &[String]) -> Result<Vec<String>> {
+    let referenced: Vec<String> = sqlx::query_scalar(sql::GET_ALL_RESOURCE_FILE_NAMES)
+        .fetch_all(db)
+        .await?;
+    Ok(file_names
+        .iter()
+        .filter(|name| !referenced.contains(name))
+        .cloned()
+        .collect())
+}
+
 /// Retrieve a mapping of controller CID to first and last names.
 pub async fn get_controller_cids_and_names(
     db: &Pool<Sqlite>,
@@ -174,156 +521,3599 @@ pub async fn get_controller_cids_and_names(
     Ok(cid_name_map)
 }
 
-/// Determine the staff position of the controller.
-///
-/// VATUSA does not differentiate between the official staff position (say, FE)
-/// and their assistants (e.g. AFE). At the VATUSA level, they're the same. Here,
-/// we do want to determine that difference.
+/// Controller connected to VATSIM on one of the facility's positions.
+#[derive(Debug, Serialize)]
+pub struct OnlineController {
+    pub cid: u64,
+    pub callsign: String,
+    pub name: String,
+    pub frequency: String,
+    pub online_for: String,
+}
+
+/// Get the controllers currently online on the facility's positions.
 ///
-/// This function will return all positions in the event the controller holds more
-/// than one, like being an Instructor and also the FE, or a Mentor and an AEC.
-pub fn determine_staff_positions(controller: &Controller, config: &Config) -> Vec<String> {
-    let mut ret_roles = Vec::new();
-    let db_roles: Vec<_> = controller.roles.split_terminator(',').collect();
-    for role in db_roles {
-        if IGNORE_MISSING_STAFF_POSITIONS_FOR.contains(&role) {
-            continue;
+/// Names honor VATUSA name privacy since the results are shown publicly.
+pub async fn get_online_facility_controllers(
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> Result<Vec<OnlineController>> {
+    let controllers: Result<Vec<Controller>, _> =
+        sqlx::query_as(sql::GET_ALL_CONTROLLERS).fetch_all(db).await;
+    let cid_name_map: HashMap<u64, String> = match controllers {
+        Ok(controllers) => controllers
+            .iter()
+            .map(|c| {
+                let name = display_name(&c.first_name, &c.last_name, c.name_privacy, false);
+                (c.cid as u64, name)
+            })
+            .collect(),
+        Err(e) => {
+            error!("Error generating controller CID -> name map: {e}");
+            HashMap::new()
         }
-        let ovr = config.staff.overrides.iter().find(|o| o.role == role);
-        if let Some(ovr) = ovr {
-            if ovr.cid == controller.cid {
-                ret_roles.push(role.to_owned());
-            } else {
-                ret_roles.push(format!("A{role}"));
+    };
+
+    let now = Utc::now();
+    let data = get_vatsim_data().await?;
+    let online = data
+        .controllers
+        .iter()
+        .filter(|controller| position_in_facility_airspace(config, &controller.callsign))
+        .map(|controller| {
+            let logon = parse_vatsim_timestamp(&controller.logon_time)
+                .expect("Could not parse VATSIM timestamp");
+            let seconds = (now - logon).num_seconds() as u32;
+            OnlineController {
+                cid: controller.cid,
+                callsign: controller.callsign.clone(),
+                name: cid_name_map
+                    .get(&controller.cid)
+                    .cloned()
+                    .unwrap_or(String::from("?")),
+                frequency: controller.frequency.clone(),
+                online_for: format!("{}h{}m", seconds / 3600, (seconds / 60) % 60),
             }
-        } else {
-            ret_roles.push(role.to_owned());
-        }
+        })
+        .collect();
+    Ok(online)
+}
+
+/// Name to show for a controller.
+///
+/// Controllers with VATUSA name privacy enabled are shown by first name and last
+/// initial, unless `show_full` is set for staff views.
+pub fn display_name(
+    first_name: &str,
+    last_name: &str,
+    name_privacy: bool,
+    show_full: bool,
+) -> String {
+    if !name_privacy || show_full {
+        return format!("{first_name} {last_name}");
     }
-    if controller.home_facility == "ZDV" && [8, 9, 10].contains(&controller.rating) {
-        ret_roles.push("INS".to_owned());
+    match display_last_name(last_name, name_privacy, show_full).as_str() {
+        "" => first_name.to_owned(),
+        last_name => format!("{first_name} {last_name}"),
     }
-    ret_roles
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::{
-        determine_staff_positions, parse_metar, parse_vatsim_timestamp,
-        position_in_facility_airspace, WeatherConditions,
-    };
-    use crate::shared::{config::ConfigStaffOverride, sql::Controller, Config};
-    use pretty_assertions::assert_eq;
-
-    #[test]
-    fn test_parse_vatsim_timestamp() {
-        parse_vatsim_timestamp("2024-03-02T16:20:37.0439318Z").unwrap();
+/// Last name to show for a controller; just the initial with name privacy enabled.
+pub fn display_last_name(last_name: &str, name_privacy: bool, show_full: bool) -> String {
+    if !name_privacy || show_full {
+        return last_name.to_owned();
     }
+    last_name
+        .chars()
+        .next()
+        .map(|initial| format!("{initial}."))
+        .unwrap_or_default()
+}
 
-    #[test]
-    fn test_parse_metar() {
-        let ret = parse_metar("KDEN 030253Z 22013KT 10SM SCT100 BKN160 13/M12 A2943 RMK AO2 PK WND 21036/0211 SLP924 T01331117 58005").unwrap();
-        assert_eq!(ret.name, "KDEN");
-        assert_eq!(ret.conditions, WeatherConditions::VFR);
+/// The one controller with name privacy enabled who's shown under `name`, if any.
+///
+/// Pilots pick from shortened names in the feedback form, so this maps the
+/// choice back to the controller.
+pub fn controller_by_display_name<'a>(
+    controllers: &'a [Controller],
+    name: &str,
+) -> Option<&'a Controller> {
+    controllers
+        .iter()
+        .filter(|c| {
+            c.name_privacy
+                && display_name(&c.first_name, &c.last_name, true, false).eq_ignore_ascii_case(name)
+        })
+        .exactly_one()
+        .ok()
+}
 
-        let ret = parse_metar("KDEN 2SM BNK005").unwrap();
-        assert_eq!(ret.conditions, WeatherConditions::IFR);
+/// Set a controller's operating initials, if they're not in use by anyone else.
+///
+/// The in-use check and the update are a single statement, and the table has a
+/// unique index on non-empty OIs, so two concurrent requests can't assign the
+/// same OIs. Returns whether the OIs were set. Blank OIs clear the controller's.
+pub async fn set_operating_initials(db: &Pool<Sqlite>, cid: u32, ois: &str) -> Result<bool> {
+    if ois.is_empty() {
+        sqlx::query(sql::CLEAR_CONTROLLER_OIS)
+            .bind(cid)
+            .execute(db)
+            .await?;
+        return Ok(true);
+    }
+    let result = sqlx::query(sql::UPDATE_CONTROLLER_OIS)
+        .bind(ois)
+        .bind(cid)
+        .execute(db)
+        .await;
+    match result {
+        Ok(result) => Ok(result.rows_affected() > 0),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
 
-        let ret = parse_metar("KDEN 4SM OVC020").unwrap();
-        assert_eq!(ret.conditions, WeatherConditions::MVFR);
+/// Maximum length of a Discord embed field's value.
+pub const DISCORD_EMBED_FIELD_LIMIT: usize = 1_024;
 
-        let ret = parse_metar("KDEN 1/2SM OVC001").unwrap();
-        assert_eq!(ret.conditions, WeatherConditions::LIFR);
+/// Shorten the text to at most `max` characters, appending "..." if anything was cut.
+///
+/// Counts characters rather than bytes so multibyte text is never split.
+pub fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_owned(),
     }
+}
 
-    #[test]
-    fn test_position_in_facility_airspace() {
-        let mut config = Config::default();
-        config.stats.position_prefixes.push("DEN".to_string());
-        config.stats.position_suffixes.push("_TWR".to_string());
+/// Make a JSON log line readable, leaving any other line as-is.
+pub fn format_log_line(line: &str) -> String {
+    #[derive(Deserialize)]
+    struct JsonLogLine {
+        timestamp: String,
+        level: String,
+        target: String,
+        message: String,
+    }
 
-        assert!(position_in_facility_airspace(&config, "DEN_2_TWR"));
-        assert!(!position_in_facility_airspace(&config, "SAN_GND"));
+    match serde_json::from_str::<JsonLogLine>(line) {
+        Ok(parsed) => format!(
+            "{} {:<5} {} > {}",
+            parsed.timestamp, parsed.level, parsed.target, parsed.message
+        ),
+        Err(_) => line.to_owned(),
     }
+}
 
-    #[test]
-    fn test_determine_staff_positions_empty() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        let config = Config::default();
+/// Read the last `count` lines of a file, most recent first.
+///
+/// The file is read backwards from the end in chunks, so only the requested
+/// lines are read no matter how large the file is. Lines that aren't valid
+/// UTF-8 are converted lossily rather than failing.
+pub async fn read_last_lines(path: &Path, count: usize) -> std::io::Result<Vec<String>> {
+    const CHUNK_SIZE: u64 = 8 * 1024;
 
-        assert!(determine_staff_positions(&controller, &config).is_empty());
+    let mut file = tokio::fs::File::open(path).await?;
+    let file_len = file.metadata().await?.len();
+    let mut lines = Vec::new();
+    let mut pos = file_len;
+    // bytes of the line that continues past the start of the last chunk read
+    let mut partial = Vec::new();
+    let mut at_file_end = true;
+    while pos > 0 && lines.len() < count {
+        let start = pos.saturating_sub(CHUNK_SIZE);
+        let mut chunk = vec![0; (pos - start) as usize];
+        file.seek(SeekFrom::Start(start)).await?;
+        file.read_exact(&mut chunk).await?;
+        chunk.extend_from_slice(&partial);
+        pos = start;
+
+        let mut line_end = chunk.len();
+        while lines.len() < count {
+            let Some(newline) = chunk[..line_end].iter().rposition(|&b| b == b'\n') else {
+                break;
+            };
+            // a trailing newline ends the last line rather than starting an empty one
+            if !(at_file_end && newline + 1 == line_end) {
+                lines.push(decode_line(&chunk[newline + 1..line_end]));
+            }
+            at_file_end = false;
+            line_end = newline;
+        }
+        chunk.truncate(line_end);
+        partial = chunk;
+    }
+    if pos == 0 && file_len > 0 && lines.len() < count {
+        lines.push(decode_line(&partial));
     }
+    Ok(lines)
+}
 
-    #[test]
-    fn test_determine_staff_positions_shared() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.roles = "MTR".to_owned();
-        let config = Config::default();
+fn decode_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\r')
+        .to_owned()
+}
 
-        assert_eq!(determine_staff_positions(&controller, &config), vec!["MTR"]);
-    }
+/// Category of an audit log entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogCategory {
+    /// Routine changes, like roster sync updates
+    Info,
+    /// Changes made by staff
+    Audit,
+    /// Changes to access, like roles and roster removals
+    Security,
+    /// Changes to a controller's certifications, kept as their history
+    Certification,
+}
 
-    #[test]
-    fn test_determine_staff_positions_single() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.roles = "FE".to_owned();
-        let config = Config::default();
+impl LogCategory {
+    pub const ALL: [LogCategory; 4] =
+        [Self::Info, Self::Audit, Self::Security, Self::Certification];
 
-        assert_eq!(determine_staff_positions(&controller, &config), vec!["FE"]);
+    /// Value stored in the DB.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Audit => "audit",
+            Self::Security => "security",
+            Self::Certification => "certification",
+        }
     }
 
-    #[test]
-    fn test_determine_staff_positions_single_assistant() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.roles = "FE".to_owned();
-        let mut config = Config::default();
-        config.staff.overrides.push(ConfigStaffOverride {
-            role: "FE".to_owned(),
-            cid: 456,
-        });
-
-        assert_eq!(determine_staff_positions(&controller, &config), vec!["AFE"]);
+    /// Parse a value stored in the DB.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == s)
     }
+}
 
-    #[test]
-    fn test_determine_staff_positions_multiple() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.roles = "FE,MTR".to_owned();
-        let mut config = Config::default();
-        config.staff.overrides.push(ConfigStaffOverride {
-            role: "FE".to_owned(),
-            cid: 456,
-        });
+/// Record an entry in the audit log, optionally about a single controller.
+///
+/// Takes any executor so that entries can be written as part of a transaction.
+pub async fn record_log<'e, E>(
+    executor: E,
+    category: LogCategory,
+    cid: Option<u32>,
+    message: &str,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(sql::INSERT_INTO_LOG)
+        .bind(message)
+        .bind(category.as_str())
+        .bind(cid)
+        .bind(Utc::now())
+        .execute(executor)
+        .await?;
+    Ok(())
+}
 
-        assert_eq!(
-            determine_staff_positions(&controller, &config),
-            vec!["AFE", "MTR"]
-        );
-    }
+/// Single entry in a controller's history.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TimelineEntry {
+    pub date: DateTime<Utc>,
+    pub category: &'static str,
+    pub description: String,
+}
 
-    #[test]
-    fn test_determine_staff_positions_instructor() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.rating = 10;
-        controller.home_facility = "ZDV".to_owned();
-        let config = Config::default();
+/// Merge a controller's log entries and related records into a single
+/// history, newest first.
+pub fn build_controller_timeline(
+    logs: &[LogEntry],
+    certifications: &[Certification],
+    feedback: &[Feedback],
+    visitor_requests: &[VisitorApplication],
+) -> Vec<TimelineEntry> {
+    let mut timeline: Vec<TimelineEntry> = Vec::new();
+    timeline.extend(logs.iter().map(|log| TimelineEntry {
+        date: log.created_date,
+        category: "Log",
+        description: log.message.clone(),
+    }));
+    timeline.extend(certifications.iter().map(|cert| TimelineEntry {
+        date: cert.changed_on,
+        category: "Certification",
+        description: format!("{} set to {} by {}", cert.name, cert.value, cert.set_by),
+    }));
+    timeline.extend(feedback.iter().map(|feedback| TimelineEntry {
+        date: feedback.created_date,
+        category: "Feedback",
+        description: format!(
+            "{} feedback for {} from {}",
+            feedback.rating, feedback.position, feedback.submitter_cid
+        ),
+    }));
+    timeline.extend(visitor_requests.iter().map(|request| TimelineEntry {
+        date: request.date,
+        category: "Visitor request",
+        description: format!("Requested to visit from {}", request.home_facility),
+    }));
+    timeline.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+    timeline
+}
 
-        assert_eq!(determine_staff_positions(&controller, &config), vec!["INS"]);
+/// Image file extensions accepted for banners.
+const BANNER_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// Check that a banner URL is a well-formed http(s) link to an image.
+///
+/// Banners are optional, so a blank value is valid.
+pub fn validate_banner_url(banner: &str) -> bool {
+    let banner = banner.trim();
+    if banner.is_empty() {
+        return true;
     }
+    let url = match reqwest::Url::parse(banner) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    if !["http", "https"].contains(&url.scheme()) || url.host_str().is_none() {
+        return false;
+    }
+    url.path()
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| BANNER_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
 
-    #[test]
-    fn test_determine_staff_positions_ingore() {
-        let mut controller = Controller::default();
-        controller.cid = 123;
-        controller.roles = "FACCBT".to_owned();
-        let config = Config::default();
+/// Order to show the resource categories in.
+///
+/// Set by admins on the site; until then, the config's ordering is used.
+pub async fn resource_category_order(config: &Config, db: &Pool<Sqlite>) -> Result<Vec<String>> {
+    let ordering: Vec<String> = sqlx::query_scalar(sql::GET_RESOURCE_CATEGORY_ORDER)
+        .fetch_all(db)
+        .await?;
+    if ordering.is_empty() {
+        return Ok(config.database.resource_category_ordering.clone());
+    }
+    Ok(ordering)
+}
 
-        assert!(determine_staff_positions(&controller, &config).is_empty());
+/// Name to store an uploaded file under in the assets directory.
+///
+/// The original name is kept for recognizability, minus anything that
+/// isn't safe in a path, and prefixed with a UUID to avoid collisions.
+pub fn asset_file_name(original: &str) -> String {
+    let original = original.rsplit(['/', '\\']).next().unwrap_or_default();
+    let safe: String = original
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let safe = safe.trim_start_matches('.');
+    format!("{}_{safe}", uuid::Uuid::new_v4())
+}
+
+/// Content type for serving a stored asset, and whether browsers can show it inline.
+pub fn asset_content_type(file_name: &str) -> (&'static str, bool) {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => ("application/pdf", true),
+        "txt" => ("text/plain; charset=utf-8", true),
+        "png" => ("image/png", true),
+        "jpg" | "jpeg" => ("image/jpeg", true),
+        "gif" => ("image/gif", true),
+        "zip" => ("application/zip", false),
+        "doc" => ("application/msword", false),
+        "docx" => (
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            false,
+        ),
+        "xls" => ("application/vnd.ms-excel", false),
+        "xlsx" => (
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            false,
+        ),
+        "ppt" => ("application/vnd.ms-powerpoint", false),
+        "pptx" => (
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            false,
+        ),
+        _ => ("application/octet-stream", false),
+    }
+}
+
+/// Content-Disposition header for serving a stored asset under the name it was uploaded with.
+///
+/// The UUID prefix from [`asset_file_name`] is dropped; names stored
+/// without one are used as they are.
+pub fn asset_content_disposition(stored_name: &str) -> String {
+    let original = match stored_name.split_once('_') {
+        Some((prefix, rest)) if uuid::Uuid::parse_str(prefix).is_ok() && !rest.is_empty() => rest,
+        _ => stored_name,
+    };
+    let original: String = original
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let (_, inline) = asset_content_type(stored_name);
+    let disposition = if inline { "inline" } else { "attachment" };
+    format!("{disposition}; filename=\"{original}\"")
+}
+
+/// MIME types that browsers would render or run if served from the assets path.
+const DISALLOWED_UPLOAD_TYPES: [&str; 7] = [
+    "text/html",
+    "text/javascript",
+    "application/javascript",
+    "application/xhtml+xml",
+    "image/svg+xml",
+    "application/x-msdownload",
+    "application/x-sh",
+];
+
+/// Whether an uploaded file can be stored as a resource.
+///
+/// The file's extension must be in the allowlist, and the type the browser
+/// sent for it can't be one that would be rendered or executed.
+pub fn upload_allowed(
+    file_name: &str,
+    content_type: Option<&str>,
+    allowed_extensions: &[String],
+) -> bool {
+    let extension = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => extension.to_lowercase(),
+        _ => return false,
+    };
+    if !allowed_extensions
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&extension))
+    {
+        return false;
+    }
+    let content_type = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|content_type| content_type.trim().to_lowercase());
+    !content_type
+        .is_some_and(|content_type| DISALLOWED_UPLOAD_TYPES.contains(&content_type.as_str()))
+}
+
+/// Whether the URL looks like a Discord webhook.
+fn is_discord_webhook_url(url: &str) -> bool {
+    let url = match reqwest::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    url.scheme() == "https"
+        && matches!(
+            url.host_str(),
+            Some("discord.com" | "discordapp.com" | "ptb.discord.com" | "canary.discord.com")
+        )
+        && url.path().starts_with("/api/webhooks/")
+}
+
+/// Describe the problems with the configured Discord webhooks, if any.
+///
+/// Required webhooks that are empty and any set to something that isn't a
+/// Discord webhook URL are reported, since either means lost notifications.
+pub fn webhook_problems(webhooks: &ConfigDiscordWebhooks) -> Vec<String> {
+    webhooks
+        .all()
+        .iter()
+        .filter_map(|(name, url, required)| {
+            if url.is_empty() {
+                required.then(|| {
+                    format!("discord.webhooks.{name} is not set; those notifications will be lost")
+                })
+            } else if !is_discord_webhook_url(url) {
+                Some(format!(
+                    "discord.webhooks.{name} is not a valid Discord webhook URL"
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Check that Discord recognizes the webhook, without posting anything to it.
+pub async fn ping_webhook(url: &str) -> Result<()> {
+    GENERAL_HTTP_CLIENT
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Whether a roster controller has initialed an SOP.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SopCoverage<'a> {
+    pub resource: &'a str,
+    pub cid: u32,
+    pub name: String,
+    /// When the controller initialed the SOP, if they have
+    pub initialed: Option<DateTime<Utc>>,
+}
+
+/// Which controllers have initialed each SOP, by SOP name and then controller CID.
+pub fn sop_coverage<'a>(
+    sops: &'a [Resource],
+    controllers: &[Controller],
+    initials: &[SopInitial],
+) -> Vec<SopCoverage<'a>> {
+    sops.iter()
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .flat_map(|sop| {
+            controllers
+                .iter()
+                .sorted_by_key(|controller| controller.cid)
+                .map(move |controller| SopCoverage {
+                    resource: &sop.name,
+                    cid: controller.cid,
+                    name: format!("{} {}", controller.first_name, controller.last_name),
+                    initialed: initials
+                        .iter()
+                        .find(|initial| {
+                            initial.resource_id == sop.id && initial.cid == controller.cid
+                        })
+                        .map(|initial| initial.created_date),
+                })
+        })
+        .collect()
+}
+
+/// Quote a CSV field if it has characters that would otherwise break the row.
+///
+/// Values that a spreadsheet would run as a formula get a leading `'` so
+/// they're shown as text instead.
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_owned()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Escape text for inclusion in an XML or HTML document.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Build an RSS 2.0 document from the facility's news entries.
+///
+/// The `base_url` is the scheme and host of the site, used in building links.
+pub fn build_news_feed(facility: &ConfigFacility, base_url: &str, entries: &[NewsEntry]) -> String {
+    let mut feed = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    feed.push_str(r#"<rss version="2.0"><channel>"#);
+    feed.push_str(&format!(
+        "<title>{} News</title>",
+        escape_xml(&facility.site_name())
+    ));
+    feed.push_str(&format!("<link>{}/news</link>", escape_xml(base_url)));
+    feed.push_str(&format!(
+        "<description>News from the {}</description>",
+        escape_xml(&facility.name)
+    ));
+    for entry in entries {
+        let link = escape_xml(&format!("{base_url}/news#{}", entry.id));
+        feed.push_str("<item>");
+        feed.push_str(&format!("<title>{}</title>", escape_xml(&entry.title)));
+        feed.push_str(&format!("<link>{link}</link>"));
+        feed.push_str(&format!(r#"<guid isPermaLink="true">{link}</guid>"#));
+        feed.push_str(&format!(
+            "<pubDate>{}</pubDate>",
+            entry.created_date.to_rfc2822()
+        ));
+        feed.push_str(&format!(
+            "<description>{}</description>",
+            escape_xml(&entry.body)
+        ));
+        feed.push_str("</item>");
+    }
+    feed.push_str("</channel></rss>");
+    feed
+}
+
+/// Escape text for a vCard property value.
+pub fn escape_vcard(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// Build a vCard 3.0 entry for a staff member.
+pub fn build_vcard(
+    org: &str,
+    first_name: &str,
+    last_name: &str,
+    title: &str,
+    email: Option<&str>,
+) -> String {
+    let mut card = String::from("BEGIN:VCARD\r\nVERSION:3.0\r\n");
+    card.push_str(&format!(
+        "N:{};{};;;\r\n",
+        escape_vcard(last_name),
+        escape_vcard(first_name)
+    ));
+    card.push_str(&format!(
+        "FN:{} {}\r\n",
+        escape_vcard(first_name),
+        escape_vcard(last_name)
+    ));
+    card.push_str(&format!("ORG:{}\r\n", escape_vcard(org)));
+    card.push_str(&format!("TITLE:{}\r\n", escape_vcard(title)));
+    if let Some(email) = email {
+        card.push_str(&format!("EMAIL;TYPE=INTERNET:{}\r\n", escape_vcard(email)));
+    }
+    card.push_str("END:VCARD\r\n");
+    card
+}
+
+/// Determine when a certification lapses.
+///
+/// Returns `None` if the certification isn't fully certified or if the
+/// facility has no recurrency requirement configured for it.
+pub fn certification_valid_until(config: &Config, cert: &Certification) -> Option<DateTime<Utc>> {
+    if cert.value != "Certified" {
+        return None;
+    }
+    config
+        .training
+        .certification_expiry
+        .iter()
+        .find(|expiry| expiry.name == cert.name)
+        .map(|expiry| cert.changed_on + Duration::days(expiry.valid_for_days as i64))
+}
+
+/// Latest session start on each usage-tracked certification's positions.
+///
+/// Sessions are VATSIM ATC sessions' callsigns and start times; ones whose
+/// start can't be parsed are skipped. Certifications without a matching
+/// session are left out.
+pub fn certification_last_used<'a>(
+    usage: &'a [ConfigCertificationUsage],
+    sessions: &[(&str, &str)],
+) -> HashMap<&'a str, DateTime<Utc>> {
+    let sessions: Vec<(&str, DateTime<Utc>)> = sessions
+        .iter()
+        .filter_map(|(callsign, start)| {
+            NaiveDateTime::parse_from_str(start.trim_end_matches('Z'), "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|start| (*callsign, start.and_utc()))
+        })
+        .collect();
+    usage
+        .iter()
+        .filter_map(|cert| {
+            sessions
+                .iter()
+                .filter(|(callsign, _)| {
+                    cert.position_suffixes
+                        .iter()
+                        .any(|suffix| callsign.ends_with(suffix.as_str()))
+                })
+                .map(|(_, start)| *start)
+                .max()
+                .map(|start| (cert.name.as_str(), start))
+        })
+        .collect()
+}
+
+/// Determine when a certification lapses from not being used.
+///
+/// Returns `None` if the certification isn't fully certified or isn't
+/// configured to lapse. Time is counted from the last use, or from when
+/// it was certified if that's more recent.
+pub fn certification_unused_until(config: &Config, cert: &Certification) -> Option<DateTime<Utc>> {
+    if cert.value != "Certified" {
+        return None;
+    }
+    let usage = config
+        .training
+        .certification_usage
+        .iter()
+        .find(|usage| usage.name == cert.name)?;
+    let since = cert
+        .last_used
+        .map_or(cert.changed_on, |used| used.max(cert.changed_on));
+    Some(since + Duration::days(usage.unused_days as i64))
+}
+
+/// Whether to remind about an upcoming date.
+///
+/// That's once the date is within the reminder window, unless a reminder was
+/// already sent for that same date. A window of 0 days turns reminders off.
+pub fn reminder_due(
+    date: DateTime<Utc>,
+    now: DateTime<Utc>,
+    reminder_days: u32,
+    reminded_for: Option<DateTime<Utc>>,
+) -> bool {
+    reminder_days > 0
+        && date > now
+        && date <= now + Duration::days(reminder_days as i64)
+        && reminded_for != Some(date)
+}
+
+/// Discord embed color for a feedback rating.
+///
+/// Unknown ratings get no color (0), which Discord renders as its default.
+pub fn feedback_embed_color(config: &Config, rating: &str) -> u32 {
+    let colors = &config.discord.feedback_colors;
+    match rating {
+        "excellent" => colors.excellent,
+        "good" => colors.good,
+        "fair" => colors.fair,
+        "poor" => colors.poor,
+        _ => 0,
+    }
+}
+
+/// Stored as feedback's controller when the pilot didn't know who was working.
+pub const UNCREDITED_CONTROLLER: &str = "0";
+
+/// Name to show for the controller the feedback is for.
+pub fn feedback_controller_name(controller: &str) -> &str {
+    if controller == UNCREDITED_CONTROLLER {
+        "Unknown"
+    } else {
+        controller
+    }
+}
+
+/// Suggest up to `count` unused operating initials for a controller, best first.
+///
+/// First and last initial is preferred, then other letters from the controller's
+/// name, then the first or last initial paired with any letter.
+pub fn ois_suggestions(
+    first_name: &str,
+    last_name: &str,
+    in_use: &[String],
+    count: usize,
+) -> Vec<String> {
+    let letters = |name: &str| -> Vec<char> {
+        name.chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    };
+    let first = letters(first_name);
+    let last = letters(last_name);
+    let alphabet: Vec<char> = ('A'..='Z').collect();
+    let mut candidates: Vec<(char, char)> = Vec::new();
+    if let (Some(&f), Some(&l)) = (first.first(), last.first()) {
+        candidates.push((f, l));
+        candidates.extend(last.iter().skip(1).map(|&c| (f, c)));
+        candidates.extend(first.iter().skip(1).map(|&c| (c, l)));
+    }
+    if let Some(&f) = first.first() {
+        candidates.extend(first.iter().skip(1).map(|&c| (f, c)));
+        candidates.extend(alphabet.iter().map(|&c| (f, c)));
+    }
+    if let Some(&l) = last.first() {
+        candidates.extend(alphabet.iter().map(|&c| (c, l)));
+    }
+    candidates
+        .into_iter()
+        .map(|(a, b)| format!("{a}{b}"))
+        .filter(|ois| !in_use.iter().any(|used| used.eq_ignore_ascii_case(ois)))
+        .unique()
+        .take(count)
+        .collect()
+}
+
+/// Names of the settings that differ between two configs but are only read at startup.
+///
+/// Changes to these need a restart to take effect, so they're ignored on a reload.
+pub fn config_restart_changes(current: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+    if current.facility != new.facility {
+        changes.push("facility");
+    }
+    if current.database.file != new.database.file {
+        changes.push("database.file");
+    }
+    if current.cache.capacity != new.cache.capacity {
+        changes.push("cache.capacity");
+    }
+    if current.resources.max_upload_mb != new.resources.max_upload_mb {
+        changes.push("resources.max_upload_mb");
+    }
+    if current.api.cors_allowed_origins != new.api.cors_allowed_origins {
+        changes.push("api.cors_allowed_origins");
+    }
+    changes
+}
+
+/// Certifications a visiting controller will need, and whether they already hold each.
+pub fn visitor_certification_checklist(
+    config: &Config,
+    certifications: &[Certification],
+) -> Vec<(String, bool)> {
+    let required = if config.visitors.required_certifications.is_empty() {
+        &config.training.certifications
+    } else {
+        &config.visitors.required_certifications
+    };
+    required
+        .iter()
+        .map(|name| {
+            let held = certifications
+                .iter()
+                .any(|cert| &cert.name == name && cert.value == "Certified");
+            (name.clone(), held)
+        })
+        .collect()
+}
+
+/// Whether feedback's controller, by CID or full name, is one staff have asked not to contact.
+pub fn feedback_contact_blocked(blocked: &[Controller], controller: &str) -> bool {
+    blocked.iter().any(|c| {
+        c.cid.to_string() == controller
+            || format!("{} {}", c.first_name, c.last_name).eq_ignore_ascii_case(controller)
+    })
+}
+
+/// Feedback received in a single month.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FeedbackMonth {
+    /// "YYYY-MM"
+    pub month: String,
+    pub total: u32,
+    pub by_rating: BTreeMap<String, u32>,
+}
+
+/// Aggregate feedback numbers for staff.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct FeedbackStats {
+    pub total: u32,
+    /// Count for each rating, most common first
+    pub by_rating: Vec<(String, u32)>,
+    /// Oldest month first
+    pub by_month: Vec<FeedbackMonth>,
+    /// Most-mentioned positions, most common first
+    pub top_positions: Vec<(String, u32)>,
+}
+
+/// Roll up grouped feedback counts into the numbers shown on the stats page.
+///
+/// Positions are free text from the form, so they're compared trimmed and uppercased,
+/// and blank ones are left out of the position ranking.
+pub fn feedback_stats(counts: &[FeedbackCount], top_positions: usize) -> FeedbackStats {
+    let mut by_rating: HashMap<&str, u32> = HashMap::new();
+    let mut by_month: BTreeMap<&str, FeedbackMonth> = BTreeMap::new();
+    let mut by_position: HashMap<String, u32> = HashMap::new();
+    for row in counts {
+        *by_rating.entry(&row.rating).or_default() += row.count;
+        let month = by_month.entry(&row.month).or_insert_with(|| FeedbackMonth {
+            month: row.month.clone(),
+            total: 0,
+            by_rating: BTreeMap::new(),
+        });
+        month.total += row.count;
+        *month.by_rating.entry(row.rating.clone()).or_default() += row.count;
+        let position = row.position.trim().to_uppercase();
+        if !position.is_empty() {
+            *by_position.entry(position).or_default() += row.count;
+        }
+    }
+    let ranked = |map: HashMap<String, u32>| -> Vec<(String, u32)> {
+        map.into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+            .collect()
+    };
+    FeedbackStats {
+        total: counts.iter().map(|row| row.count).sum(),
+        by_rating: ranked(
+            by_rating
+                .into_iter()
+                .map(|(rating, count)| (rating.to_owned(), count))
+                .collect(),
+        ),
+        by_month: by_month.into_values().collect(),
+        top_positions: ranked(by_position)
+            .into_iter()
+            .take(top_positions)
+            .collect(),
+    }
+}
+
+/// Share a piece of feedback in the configured Discord feedback channel.
+pub async fn post_feedback_to_discord(config: &Config, feedback: &Feedback) -> Result<()> {
+    GENERAL_HTTP_CLIENT
+        .post(&config.discord.webhooks.feedback)
+        .json(&serde_json::json!({
+            "content": "",
+            "embeds": [{
+                "title": "Feedback received",
+                "color": feedback_embed_color(config, &feedback.rating),
+                "fields": [
+                    {
+                        "name": "Controller",
+                        "value": feedback_controller_name(&feedback.controller)
+                    },
+                    {
+                        "name": "Position",
+                        "value": feedback.position
+                    },
+                    {
+                        "name": "Rating",
+                        "value": feedback.rating
+                    },
+                    {
+                        "name": "Comments",
+                        "value": truncate_chars(&feedback.comments, DISCORD_EMBED_FIELD_LIMIT - 3)
+                    }
+                ]
+            }]
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Check whether a solo cert matches the solo cert list's filters.
+///
+/// The position matches by prefix, e.g. "DEN" matches "DEN_TWR" and "DEN_APP".
+pub fn solo_cert_matches(
+    cert: &SoloCert,
+    position_prefix: &str,
+    reported: Option<bool>,
+    expiring_within_days: Option<i64>,
+    now: DateTime<Utc>,
+) -> bool {
+    if !cert
+        .position
+        .to_uppercase()
+        .starts_with(&position_prefix.trim().to_uppercase())
+    {
+        return false;
+    }
+    if reported.is_some_and(|reported| reported != cert.reported) {
+        return false;
+    }
+    if let Some(days) = expiring_within_days {
+        if cert.expiration_date > now + Duration::days(days) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Differences between the site's solo certs and VATUSA's.
+#[derive(Debug, Default, Serialize)]
+pub struct SoloCertDrift<'a> {
+    /// Marked as reported here, but VATUSA doesn't have it
+    pub missing_from_vatusa: Vec<&'a SoloCert>,
+    /// At VATUSA for one of the facility's positions, but not reported here
+    pub missing_locally: Vec<&'a vatusa::SoloCert>,
+}
+
+/// Compare unexpired local solo certs against VATUSA's list.
+///
+/// Certs are matched by CID and position. Only VATUSA certs on positions
+/// starting with one of the prefixes are considered the facility's.
+pub fn reconcile_solo_certs<'a>(
+    local: &'a [SoloCert],
+    remote: &'a [vatusa::SoloCert],
+    position_prefixes: &[String],
+    now: DateTime<Utc>,
+) -> SoloCertDrift<'a> {
+    let active: Vec<_> = local
+        .iter()
+        .filter(|cert| cert.expiration_date > now)
+        .collect();
+    let missing_from_vatusa = active
+        .iter()
+        .filter(|cert| cert.reported)
+        .filter(|cert| {
+            !remote
+                .iter()
+                .any(|r| r.cid == cert.cid && r.position == cert.position)
+        })
+        .copied()
+        .collect();
+    let missing_locally = remote
+        .iter()
+        .filter(|r| {
+            position_prefixes
+                .iter()
+                .any(|prefix| r.position.starts_with(prefix.as_str()))
+        })
+        .filter(|r| {
+            !active
+                .iter()
+                .any(|cert| cert.reported && cert.cid == r.cid && cert.position == r.position)
+        })
+        .collect();
+    SoloCertDrift {
+        missing_from_vatusa,
+        missing_locally,
+    }
+}
+
+/// Determine the Discord roles a controller should have from the configured mappings.
+///
+/// Controllers not on the roster get no roles.
+pub fn discord_roles_for(controller: &Controller, config: &Config) -> Vec<String> {
+    if !controller.is_on_roster {
+        return Vec::new();
+    }
+    let positions = determine_staff_positions(controller, config);
+    let rating = Controller::rating_name(controller.rating);
+    config
+        .discord
+        .role_mappings
+        .iter()
+        .filter(|mapping| {
+            mapping.roles.iter().any(|role| positions.contains(role))
+                || mapping.ratings.iter().any(|r| r == rating)
+        })
+        .map(|mapping| mapping.discord_role.clone())
+        .unique()
+        .collect()
+}
+
+/// Determine the staff position of the controller.
+///
+/// VATUSA does not differentiate between the official staff position (say, FE)
+/// and their assistants (e.g. AFE). At the VATUSA level, they're the same. Here,
+/// we do want to determine that difference.
+///
+/// This function will return all positions in the event the controller holds more
+/// than one, like being an Instructor and also the FE, or a Mentor and an AEC.
+pub fn determine_staff_positions(controller: &Controller, config: &Config) -> Vec<String> {
+    let mut ret_roles = Vec::new();
+    let db_roles: Vec<_> = controller.roles.split_terminator(',').collect();
+    for role in db_roles {
+        if IGNORE_MISSING_STAFF_POSITIONS_FOR.contains(&role) {
+            continue;
+        }
+        let ovr = config.staff.overrides.iter().find(|o| o.role == role);
+        if let Some(ovr) = ovr {
+            if ovr.cid == controller.cid {
+                ret_roles.push(role.to_owned());
+            } else {
+                ret_roles.push(format!("A{role}"));
+            }
+        } else {
+            ret_roles.push(role.to_owned());
+        }
+    }
+    if controller.home_facility == config.facility.code && [8, 9, 10].contains(&controller.rating) {
+        ret_roles.push("INS".to_owned());
+    }
+    ret_roles
+}
+
+/// Whether a controller should be emailed about being short of the activity requirement.
+///
+/// `minutes` are the controller's minutes in the current quarter, which ends with this
+/// month. Reminders start the configured number of days before the end of the month.
+pub fn should_remind_of_activity(
+    config: &ConfigCurrency,
+    minutes: u32,
+    now: DateTime<Utc>,
+    last_reminded: Option<DateTime<Utc>>,
+) -> bool {
+    if config.reminder_days == 0 {
+        return false;
+    }
+    let short = QUARTERLY_ACTIVITY_MINUTES.saturating_sub(minutes);
+    if short == 0 || short < config.min_minutes_short {
+        return false;
+    }
+    let today = now.date_naive();
+    let next_month = today
+        .with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .unwrap();
+    if (next_month - today).num_days() > config.reminder_days as i64 {
+        return false;
+    }
+    last_reminded
+        .is_none_or(|last| now - last >= Duration::days(config.reminder_interval_days as i64))
+}
+
+/// Whether the staff digest is due, given when it was last sent.
+pub fn digest_due(last_sent: Option<DateTime<Utc>>, now: DateTime<Utc>, days: u64) -> bool {
+    last_sent.is_none_or(|last| now - last >= Duration::days(days as i64))
+}
+
+/// ATIS entries older than this aren't current; vATIS sends an update at least hourly.
+pub const ATIS_MAX_AGE_MINUTES: i64 = 90;
+
+/// The latest ATIS of each type (combined, arrival, departure) for the facility.
+///
+/// Expects the entries to be sorted newest first, as from `GET_ALL_ATIS_ENTRIES`.
+pub fn current_atis(entries: Vec<Atis>, facility: &str, now: DateTime<Utc>) -> Vec<Atis> {
+    let cutoff = now - Duration::minutes(ATIS_MAX_AGE_MINUTES);
+    entries
+        .into_iter()
+        .filter(|atis| atis.facility.eq_ignore_ascii_case(facility) && atis.timestamp >= cutoff)
+        .unique_by(|atis| atis.atis_type.clone())
+        .sorted_by(|a, b| Ord::cmp(&a.atis_type, &b.atis_type))
+        .collect()
+}
+
+/// Delete ATIS entries older than the configured retention.
+///
+/// Returns the number of entries deleted.
+pub async fn clean_up_atis(config: &Config, db: &Pool<Sqlite>, now: DateTime<Utc>) -> Result<u64> {
+    let cutoff = now - Duration::hours(config.atis.retention_hours as i64);
+    let result = sqlx::query(sql::DELETE_ATIS_OLDER_THAN)
+        .bind(cutoff)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Whether a request path is still served while the site is in maintenance mode.
+///
+/// Logging in stays open so admins can get through, uploaded assets so pages
+/// elsewhere that link them keep working, and the ATIS API so vATIS updates
+/// aren't lost.
+pub fn maintenance_exempt(path: &str) -> bool {
+    path.starts_with("/auth/")
+        || path.starts_with("/assets/")
+        || path == "/api/atis"
+        || path.starts_with("/api/atis/")
+}
+
+/// Name of the API key in an `Authorization` header value, if it's a configured key.
+///
+/// Keys left empty in the config never match. Keys are compared in constant
+/// time so response timing doesn't leak how much of a key was right.
+pub fn api_key_name<'a>(keys: &'a [ConfigApiKey], authorization: &str) -> Option<&'a str> {
+    let given = authorization.strip_prefix("Bearer ")?.trim();
+    keys.iter()
+        .find(|key| !key.key.is_empty() && bool::from(key.key.as_bytes().ct_eq(given.as_bytes())))
+        .map(|key| key.name.as_str())
+}
+
+/// URL of another site's events API, if its base URL is an allowed HTTPS host.
+///
+/// Events staff enter the base URL, so only configured hosts are let through
+/// to keep the site from being used to reach internal addresses.
+pub fn event_import_url(allowed_hosts: &[String], base_url: &str) -> Result<reqwest::Url, String> {
+    let base_url = reqwest::Url::parse(base_url.trim()).map_err(|_| "Invalid URL")?;
+    if base_url.scheme() != "https" {
+        return Err("The URL must be https".to_owned());
+    }
+    let host = base_url.host_str().unwrap_or_default();
+    if base_url.port().is_some()
+        || !allowed_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return Err(format!("Importing from {host} isn't allowed"));
+    }
+    base_url
+        .join("/api/events")
+        .map_err(|_| "Invalid URL".to_owned())
+}
+
+/// Check a hex-encoded HMAC-SHA256 signature of a request body.
+///
+/// An empty secret never verifies, so an unconfigured endpoint rejects everything.
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
+    let signature = match hex::decode(signature.trim()) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Controllers who joined and left the roster between two lists of CIDs.
+///
+/// Returns the added and removed CIDs, in the order they appear.
+pub fn roster_diff(previous: &[u32], current: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let added = current
+        .iter()
+        .filter(|cid| !previous.contains(cid))
+        .copied()
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|cid| !current.contains(cid))
+        .copied()
+        .collect();
+    (added, removed)
+}
+
+/// Minimum minutes a controller needs in a quarter to meet the activity requirement.
+pub const QUARTERLY_ACTIVITY_MINUTES: u32 = 180;
+
+/// Parse a VATUSA date, like a roster member's facility join date.
+pub fn parse_vatusa_date(stamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(stamp)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|date| date.and_utc())
+        })
+}
+
+/// Whether the controller joined recently enough to be exempt from the activity requirement.
+pub fn is_new_controller(
+    config: &ConfigCurrency,
+    controller: &Controller,
+    now: DateTime<Utc>,
+) -> bool {
+    controller.join_date.is_some_and(|joined| {
+        now - joined < Duration::days(config.new_controller_grace_days as i64)
+    })
+}
+
+/// Whether the controller has a leave of absence that hasn't ended yet.
+pub fn is_on_loa(controller: &Controller, now: DateTime<Utc>) -> bool {
+    controller.loa_until.is_some_and(|until| until > now)
+}
+
+/// Whether the controller's minutes over the last quarter are an activity violation.
+///
+/// Controllers on an active LOA or still within the new controller grace
+/// period are never in violation.
+pub fn activity_violation(
+    config: &ConfigCurrency,
+    controller: &Controller,
+    minutes: u32,
+    now: DateTime<Utc>,
+) -> bool {
+    minutes < QUARTERLY_ACTIVITY_MINUTES
+        && !is_on_loa(controller, now)
+        && !is_new_controller(config, controller, now)
+}
+
+/// Months of activity fetched and kept, including the current one.
+///
+/// Enough for two full quarters, which the removal report looks at.
+pub const ACTIVITY_MONTHS: u32 = 6;
+
+/// First day of the oldest month of activity to fetch, as "YYYY-MM-DD".
+pub fn activity_fetch_start(now: DateTime<Utc>) -> String {
+    now.checked_sub_months(Months::new(ACTIVITY_MONTHS - 1))
+        .unwrap()
+        .format("%Y-%m-01")
+        .to_string()
+}
+
+/// The "YYYY-MM" months of the quarter `quarter` quarters before the current one.
+///
+/// Rolling quarters are the 3 months ending with the current month. Calendar
+/// quarters only include the months of the current quarter up to now.
+pub fn quarter_months(mode: QuarterMode, now: DateTime<Utc>, quarter: u32) -> Vec<String> {
+    let (latest, count) = match mode {
+        QuarterMode::Rolling => (quarter * 3, 3),
+        QuarterMode::Calendar => {
+            let into_quarter = now.month0() % 3;
+            if quarter == 0 {
+                (0, into_quarter + 1)
+            } else {
+                (into_quarter + 1 + (quarter - 1) * 3, 3)
+            }
+        }
+    };
+    (latest..latest + count)
+        .map(|offset| {
+            now.checked_sub_months(Months::new(offset))
+                .unwrap()
+                .format("%Y-%m")
+                .to_string()
+        })
+        .collect()
+}
+
+/// Minutes the controller controlled in each of the last `count` quarters, most recent first.
+///
+/// Months that aren't in the stored activity count as zero.
+pub fn quarterly_minutes(
+    activity: &[Activity],
+    cid: u32,
+    now: DateTime<Utc>,
+    count: u32,
+    mode: QuarterMode,
+) -> Vec<u32> {
+    (0..count)
+        .map(|quarter| {
+            let months = quarter_months(mode, now, quarter);
+            activity
+                .iter()
+                .filter(|a| a.cid == cid && months.contains(&a.month))
+                .map(|a| a.minutes)
+                .sum()
+        })
+        .collect()
+}
+
+/// Number of quarters, counting back from the most recent, below the activity requirement.
+pub fn consecutive_inactive_quarters(quarters: &[u32]) -> usize {
+    quarters
+        .iter()
+        .take_while(|&&minutes| minutes < QUARTERLY_ACTIVITY_MINUTES)
+        .count()
+}
+
+/// Describe the differences in the VATUSA-sourced fields of two copies of a controller.
+///
+/// Returns one "field: before -> after" entry per changed field.
+pub fn controller_changes(before: &Controller, after: &Controller) -> Vec<String> {
+    let mut changes = Vec::new();
+    let (before_name, after_name) = (
+        format!("{} {}", before.first_name, before.last_name),
+        format!("{} {}", after.first_name, after.last_name),
+    );
+    if before_name != after_name {
+        changes.push(format!("name: {before_name} -> {after_name}"));
+    }
+    if before.rating != after.rating {
+        changes.push(format!(
+            "rating: {} -> {}",
+            Controller::rating_name(before.rating),
+            Controller::rating_name(after.rating)
+        ));
+    }
+    if before.home_facility != after.home_facility {
+        changes.push(format!(
+            "home facility: {} -> {}",
+            before.home_facility, after.home_facility
+        ));
+    }
+    if before.is_on_roster != after.is_on_roster {
+        changes.push(format!(
+            "on roster: {} -> {}",
+            before.is_on_roster, after.is_on_roster
+        ));
+    }
+    changes
+}
+
+/// Position categories controllers can rank for event registration, by position name suffix.
+pub const POSITION_CATEGORIES: [&str; 6] = ["DEL", "GND", "TWR", "APP", "DEP", "CTR"];
+
+/// Category of an event position, which is its name's suffix, e.g. "DEN_APP" is "APP".
+pub fn position_category(name: &str) -> &str {
+    name.rsplit('_').next().unwrap_or(name)
+}
+
+/// The unassigned event position that best matches a controller's ranked categories.
+pub fn preferred_position(positions: &[EventPosition], preferences: &[String]) -> Option<u32> {
+    preferences.iter().find_map(|category| {
+        positions
+            .iter()
+            .filter(|position| position.cid.is_none())
+            .find(|position| position_category(&position.name).eq_ignore_ascii_case(category))
+            .map(|position| position.id)
+    })
+}
+
+/// Whether every position in the requested position's category is assigned to another controller.
+///
+/// Controllers who register for a full category are put on its waitlist.
+pub fn category_full(positions: &[EventPosition], position_id: u32, cid: u32) -> bool {
+    let category = match positions.iter().find(|position| position.id == position_id) {
+        Some(requested) => position_category(&requested.name),
+        None => return false,
+    };
+    positions
+        .iter()
+        .filter(|position| position_category(&position.name).eq_ignore_ascii_case(category))
+        .all(|position| position.cid.is_some_and(|assigned| assigned != cid))
+}
+
+/// Whether a registered controller is still waiting on a position.
+///
+/// That's when they haven't been assigned one and every position in the category
+/// they asked for is taken. Positions open up and get reassigned after registering,
+/// so this is worked out from the event's current positions.
+pub fn registration_waitlisted(positions: &[EventPosition], position_id: u32, cid: u32) -> bool {
+    !positions.iter().any(|position| position.cid == Some(cid))
+        && category_full(positions, position_id, cid)
+}
+
+/// Registered controllers to suggest for each open position, by position ID.
+///
+/// Controllers who haven't been assigned a position are suggested for open
+/// positions in the category they asked for, in the order they registered.
+/// Like `registration_waitlisted`, this is worked out from the event's current
+/// positions, so it doesn't matter whether the category was full when they
+/// registered.
+pub fn waitlist_candidates(
+    positions: &[EventPosition],
+    registrations: &[EventRegistration],
+) -> HashMap<u32, Vec<u32>> {
+    let mut candidates: HashMap<u32, Vec<u32>> = HashMap::new();
+    for registration in registrations.iter().sorted_by_key(|r| r.id) {
+        if positions
+            .iter()
+            .any(|position| position.cid == Some(registration.cid))
+        {
+            continue;
+        }
+        let category = match positions
+            .iter()
+            .find(|position| position.id == registration.position_id)
+        {
+            Some(requested) => position_category(&requested.name),
+            None => continue,
+        };
+        for position in positions.iter().filter(|position| {
+            position.cid.is_none()
+                && position_category(&position.name).eq_ignore_ascii_case(category)
+        }) {
+            candidates
+                .entry(position.id)
+                .or_default()
+                .push(registration.cid);
+        }
+    }
+    candidates
+}
+
+/// Facility staff roles that can be assigned through the site.
+///
+/// INS isn't one of them, as it comes from the controller's rating and the
+/// roster sync drops it from the roles VATUSA reports.
+pub const STAFF_ROLES: [&str; 10] = [
+    "ATM", "DATM", "TA", "EC", "AEC", "FE", "AFE", "WM", "AWM", "MTR",
+];
+
+/// Staff roles that a controller with the stored `roles` can assign to or remove from others.
+///
+/// The ATM, DATM, and WM can set any role; other staff can only set the
+/// roles of their own team.
+pub fn roles_to_set(roles: &str) -> Vec<&'static str> {
+    let mut allowed = Vec::new();
+    for role in roles.split([',', ' ']).filter(|role| !role.is_empty()) {
+        let team: &[&'static str] = match role {
+            "ATM" | "DATM" | "WM" => return STAFF_ROLES.to_vec(),
+            "TA" => &["MTR"],
+            "EC" => &["AEC"],
+            "FE" => &["AFE"],
+            _ => &[],
+        };
+        allowed.extend(team);
+    }
+    allowed.into_iter().unique().collect()
+}
+
+/// Add or remove a role from a controller's stored comma-separated roles.
+pub fn with_role(roles: &str, role: &str, add: bool) -> String {
+    let mut updated: Vec<_> = roles
+        .split(',')
+        .filter(|existing| !existing.is_empty() && *existing != role)
+        .collect();
+    if add {
+        updated.push(role);
+    }
+    updated.join(",")
+}
+
+/// Longest block of time a controller can post to the schedule.
+pub const SCHEDULE_MAX_HOURS: i64 = 12;
+/// How far ahead a controller can post to the schedule.
+pub const SCHEDULE_MAX_DAYS_AHEAD: i64 = 30;
+
+/// Check a block of time a controller wants to post to the schedule.
+///
+/// Returns the problem to show the controller, if there is one.
+pub fn schedule_block_problem(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    if end <= start {
+        Some("The end must be after the start".to_owned())
+    } else if end <= now {
+        Some("That time has already passed".to_owned())
+    } else if end - start > Duration::hours(SCHEDULE_MAX_HOURS) {
+        Some(format!(
+            "Blocks can be at most {SCHEDULE_MAX_HOURS} hours long"
+        ))
+    } else if start > now + Duration::days(SCHEDULE_MAX_DAYS_AHEAD) {
+        Some(format!(
+            "Blocks can be at most {SCHEDULE_MAX_DAYS_AHEAD} days ahead"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Format a stored UTC timestamp in the named IANA timezone.
+///
+/// Falls back to UTC when the timezone is unset or not recognized, and
+/// returns the input unchanged if it isn't an RFC 3339 timestamp.
+pub fn local_date(date: &str, timezone: Option<&str>) -> String {
+    let tz: Tz = timezone.and_then(|tz| tz.parse().ok()).unwrap_or(Tz::UTC);
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(parsed) => parsed
+            .with_timezone(&tz)
+            .format("%m/%d/%Y %H:%M:%S %Z")
+            .to_string(),
+        Err(_) => date.to_owned(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+pub mod tests {
+    use super::{
+        activity_fetch_start, activity_violation, airport_coordinates, api_key_name,
+        api_metrics::{ApiCallStats, CallOutcome},
+        asset_content_disposition, asset_content_type, asset_file_name, build_controller_timeline,
+        build_news_feed, build_vcard, category_full, certification_last_used,
+        certification_unused_until, certification_valid_until, clean_up_atis, compare_hours,
+        config_restart_changes, consecutive_inactive_quarters, controller_by_display_name,
+        controller_changes, csv_field, current_atis, determine_staff_positions, digest_due,
+        discord_roles_for, display_last_name, display_name, distance_nm, edit_distance,
+        escape_vcard, escape_xml, event_import_url, feedback_contact_blocked,
+        feedback_controller_name, feedback_embed_color, feedback_stats, find_orphaned_assets,
+        flight_relevance, format_log_line, glance_distance, is_new_controller, is_on_loa,
+        local_date, maintenance_exempt, matches_route, metar_retry_delay, normalize_position,
+        ois_suggestions,
+        pagination::{PageQuery, Pagination},
+        parse_metar, parse_vatsim_timestamp, parse_vatusa_date, point_in_polygon,
+        position_in_facility_airspace, preferred_position, quarter_months, quarterly_minutes,
+        read_last_lines, reconcile_solo_certs, registration_waitlisted, reminder_due, roles_to_set,
+        roster_diff, schedule_block_problem, set_operating_initials, should_remind_of_activity,
+        solo_cert_matches, sop_coverage, suggest_route, truncate_chars, upload_allowed,
+        validate_banner_url, vatusa, verify_signature, visitor_certification_checklist,
+        waitlist_candidates, webhook_problems, with_role, FeedbackMonth, FeedbackStats,
+        FlightRelevance, GlanceSort, LogCategory, WeatherConditions, GLANCE_MAX_DISTANCE,
+        QUARTERLY_ACTIVITY_MINUTES, STAFF_ROLES, UNCREDITED_CONTROLLER,
+    };
+    use crate::{
+        endpoints::PAGE_ROUTES,
+        shared::{
+            config::{
+                Airport, ConfigApiKey, ConfigCertificationExpiry, ConfigCertificationUsage,
+                ConfigCurrency, ConfigDiscordWebhooks, ConfigFacility, ConfigLogs, ConfigResources,
+                ConfigRoleMapping, ConfigStaff, ConfigStaffOverride, QuarterMode, REDACTED,
+            },
+            sql::{
+                self, Activity, Atis, Certification, Controller, EventPosition, EventRegistration,
+                Feedback, FeedbackCount, LogEntry, NewsEntry, Resource, SoloCert, SopInitial,
+            },
+            AppError, Config, REQUEST_ID,
+        },
+    };
+    use axum::{http::StatusCode, response::IntoResponse};
+    use chrono::{Duration, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use sqlx::{sqlite::SqlitePoolOptions, Executor, SqlitePool};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_parse_vatsim_timestamp() {
+        parse_vatsim_timestamp("2024-03-02T16:20:37.0439318Z").unwrap();
+    }
+
+    #[test]
+    fn test_parse_metar() {
+        let ret = parse_metar("KDEN 030253Z 22013KT 10SM SCT100 BKN160 13/M12 A2943 RMK AO2 PK WND 21036/0211 SLP924 T01331117 58005").unwrap();
+        assert_eq!(ret.name, "KDEN");
+        assert_eq!(ret.conditions, WeatherConditions::VFR);
+
+        let ret = parse_metar("KDEN 2SM BNK005").unwrap();
+        assert_eq!(ret.conditions, WeatherConditions::IFR);
+
+        let ret = parse_metar("KDEN 4SM OVC020").unwrap();
+        assert_eq!(ret.conditions, WeatherConditions::MVFR);
+
+        let ret = parse_metar("KDEN 1/2SM OVC001").unwrap();
+        assert_eq!(ret.conditions, WeatherConditions::LIFR);
+    }
+
+    #[test]
+    fn test_position_in_facility_airspace() {
+        let mut config = Config::default();
+        config.stats.position_prefixes.push("DEN".to_string());
+        config.stats.position_suffixes.push("_TWR".to_string());
+
+        assert!(position_in_facility_airspace(&config, "DEN_2_TWR"));
+        assert!(!position_in_facility_airspace(&config, "SAN_GND"));
+    }
+
+    #[test]
+    fn test_determine_staff_positions_empty() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        let config = Config::default();
+
+        assert!(determine_staff_positions(&controller, &config).is_empty());
+    }
+
+    #[test]
+    fn test_determine_staff_positions_shared() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.roles = "MTR".to_owned();
+        let config = Config::default();
+
+        assert_eq!(determine_staff_positions(&controller, &config), vec!["MTR"]);
+    }
+
+    #[test]
+    fn test_determine_staff_positions_single() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.roles = "FE".to_owned();
+        let config = Config::default();
+
+        assert_eq!(determine_staff_positions(&controller, &config), vec!["FE"]);
+    }
+
+    #[test]
+    fn test_determine_staff_positions_single_assistant() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.roles = "FE".to_owned();
+        let mut config = Config::default();
+        config.staff.overrides.push(ConfigStaffOverride {
+            role: "FE".to_owned(),
+            cid: 456,
+        });
+
+        assert_eq!(determine_staff_positions(&controller, &config), vec!["AFE"]);
+    }
+
+    #[test]
+    fn test_determine_staff_positions_multiple() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.roles = "FE,MTR".to_owned();
+        let mut config = Config::default();
+        config.staff.overrides.push(ConfigStaffOverride {
+            role: "FE".to_owned(),
+            cid: 456,
+        });
+
+        assert_eq!(
+            determine_staff_positions(&controller, &config),
+            vec!["AFE", "MTR"]
+        );
+    }
+
+    #[test]
+    fn test_determine_staff_positions_instructor() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.rating = 10;
+        controller.home_facility = "ZDV".to_owned();
+        let config = Config::default();
+
+        assert_eq!(determine_staff_positions(&controller, &config), vec!["INS"]);
+    }
+
+    #[test]
+    fn test_determine_staff_positions_instructor_other_facility() {
+        let mut controller = Controller {
+            cid: 123,
+            rating: 8,
+            home_facility: "ZLC".to_owned(),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        config.facility.code = "ZLC".to_owned();
+
+        assert_eq!(determine_staff_positions(&controller, &config), vec!["INS"]);
+        controller.home_facility = "ZDV".to_owned();
+        assert!(determine_staff_positions(&controller, &config).is_empty());
+    }
+
+    #[test]
+    fn test_determine_staff_positions_ingore() {
+        let mut controller = Controller::default();
+        controller.cid = 123;
+        controller.roles = "FACCBT".to_owned();
+        let config = Config::default();
+
+        assert!(determine_staff_positions(&controller, &config).is_empty());
+    }
+
+    #[test]
+    fn test_certification_valid_until() {
+        let mut config = Config::default();
+        config
+            .training
+            .certification_expiry
+            .push(ConfigCertificationExpiry {
+                name: "APP T1".to_owned(),
+                valid_for_days: 30,
+            });
+        let changed_on = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let mut cert = Certification {
+            id: 1,
+            cid: 123,
+            name: "APP T1".to_owned(),
+            value: "Certified".to_owned(),
+            changed_on,
+            set_by: 456,
+            valid_until: None,
+            last_used: None,
+        };
+
+        assert_eq!(
+            certification_valid_until(&config, &cert),
+            Some(changed_on + Duration::days(30))
+        );
+
+        cert.value = "Training".to_owned();
+        assert_eq!(certification_valid_until(&config, &cert), None);
+
+        cert.value = "Certified".to_owned();
+        cert.name = "GC T1".to_owned();
+        assert_eq!(certification_valid_until(&config, &cert), None);
+    }
+
+    #[test]
+    fn test_feedback_embed_color() {
+        let mut config = Config::default();
+        assert_eq!(feedback_embed_color(&config, "excellent"), 0x2ecc71);
+        assert_eq!(feedback_embed_color(&config, "good"), 0x3498db);
+        assert_eq!(feedback_embed_color(&config, "fair"), 0xf1c40f);
+        assert_eq!(feedback_embed_color(&config, "poor"), 0xe74c3c);
+        assert_eq!(feedback_embed_color(&config, "unknown"), 0);
+
+        config.discord.feedback_colors.poor = 0x123456;
+        assert_eq!(feedback_embed_color(&config, "poor"), 0x123456);
+    }
+
+    #[test]
+    fn test_build_controller_timeline() {
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+        let logs = vec![LogEntry {
+            id: 1,
+            message: "Added to roster".to_owned(),
+            category: "info".to_owned(),
+            cid: Some(123),
+            created_date: day(1),
+        }];
+        let certifications = vec![Certification {
+            id: 1,
+            cid: 123,
+            name: "GND".to_owned(),
+            value: "Certified".to_owned(),
+            changed_on: day(10),
+            set_by: 456,
+            valid_until: None,
+            last_used: None,
+        }];
+        let feedback = vec![Feedback {
+            id: 1,
+            controller: "123".to_owned(),
+            position: "DEN_GND".to_owned(),
+            rating: "excellent".to_owned(),
+            comments: String::new(),
+            created_date: day(5),
+            submitter_cid: 789,
+            reviewed_by_cid: 0,
+            reviewer_action: "pending".to_owned(),
+            posted_to_discord: false,
+        }];
+
+        let timeline = build_controller_timeline(&logs, &certifications, &feedback, &[]);
+        let categories: Vec<_> = timeline.iter().map(|entry| entry.category).collect();
+        assert_eq!(categories, vec!["Certification", "Feedback", "Log"]);
+        assert_eq!(timeline[0].description, "GND set to Certified by 456");
+        assert_eq!(timeline[2].date, day(1));
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let mut config = Config::default();
+        assert_eq!(config.cache.capacity, 100);
+        assert_eq!(config.cache.ttl("WEATHER_FULL"), 300);
+        assert_eq!(config.cache.ttl("ONLINE_CONTROLLERS"), 60);
+
+        config.cache.ttls.insert("WEATHER_FULL".to_owned(), 30);
+        assert_eq!(config.cache.ttl("WEATHER_FULL"), 30);
+    }
+
+    #[test]
+    fn test_config_redacted() {
+        let mut config = Config::default();
+        config.vatsim.oauth_client_id = "client".to_owned();
+        config.vatsim.oauth_client_secret = "secret".to_owned();
+        config.discord.webhooks.feedback = "https://discord.com/api/webhooks/1".to_owned();
+        config.email.password = "hunter2".to_owned();
+        config.api.keys = vec![ConfigApiKey {
+            name: "vATIS".to_owned(),
+            key: "abc123".to_owned(),
+        }];
+
+        let redacted = config.redacted();
+        assert_eq!(redacted.vatsim.oauth_client_id, "client");
+        assert_eq!(redacted.vatsim.oauth_client_secret, REDACTED);
+        assert_eq!(redacted.vatsim.vatusa_api_key, "");
+        assert_eq!(redacted.discord.webhooks.feedback, REDACTED);
+        assert_eq!(redacted.discord.webhooks.staffing_request, "");
+        assert_eq!(redacted.email.password, REDACTED);
+        assert_eq!(redacted.api.keys[0].name, "vATIS");
+        assert_eq!(redacted.api.keys[0].key, REDACTED);
+        assert_eq!(config.email.password, "hunter2");
+    }
+
+    #[test]
+    fn test_validate_banner_url() {
+        assert!(validate_banner_url(""));
+        assert!(validate_banner_url("  "));
+        assert!(validate_banner_url("https://example.com/banner.png"));
+        assert!(validate_banner_url(
+            "http://example.com/a/b/Banner.JPEG?size=large"
+        ));
+
+        assert!(!validate_banner_url("example.com/banner.png"));
+        assert!(!validate_banner_url("ftp://example.com/banner.png"));
+        assert!(!validate_banner_url("javascript:alert(1).png"));
+        assert!(!validate_banner_url("https://example.com/banner"));
+        assert!(!validate_banner_url("https://example.com/banner.html"));
+        assert!(!validate_banner_url("not a url"));
+    }
+
+    #[test]
+    fn test_roster_removals_exceed_limit() {
+        let mut config = Config::default();
+        assert!(!config.roster.removals_exceed_limit(0, 100));
+        assert!(!config.roster.removals_exceed_limit(10, 100));
+        assert!(config.roster.removals_exceed_limit(11, 200));
+        assert!(config.roster.removals_exceed_limit(6, 50));
+
+        config.roster.max_removals = 0;
+        assert!(!config.roster.removals_exceed_limit(50, 1000));
+        config.roster.max_removal_percent = 0.0;
+        assert!(!config.roster.removals_exceed_limit(500, 1000));
+    }
+
+    #[test]
+    fn test_roster_removal_due() {
+        let mut config = Config::default();
+        assert!(!config.roster.removal_due(1));
+        assert!(!config.roster.removal_due(2));
+        assert!(config.roster.removal_due(3));
+        assert!(config.roster.removal_due(4));
+
+        config.roster.removal_after_missed_syncs = 0;
+        assert!(config.roster.removal_due(1));
+    }
+
+    #[test]
+    fn test_discord_roles_for() {
+        let mut config = Config::default();
+        config.discord.role_mappings = vec![
+            ConfigRoleMapping {
+                discord_role: "Senior Staff".to_owned(),
+                roles: vec!["ATM".to_owned(), "DATM".to_owned()],
+                ratings: Vec::new(),
+            },
+            ConfigRoleMapping {
+                discord_role: "Events Team".to_owned(),
+                roles: vec!["EC".to_owned(), "AEC".to_owned()],
+                ratings: Vec::new(),
+            },
+            ConfigRoleMapping {
+                discord_role: "Controller".to_owned(),
+                roles: Vec::new(),
+                ratings: vec!["S1".to_owned(), "S2".to_owned(), "C1".to_owned()],
+            },
+        ];
+        config.staff.overrides.push(ConfigStaffOverride {
+            role: "EC".to_owned(),
+            cid: 1,
+        });
+        let mut controller = Controller {
+            cid: 2,
+            rating: 5,
+            roles: "ATM,EC".to_owned(),
+            home_facility: "ZDV".to_owned(),
+            is_on_roster: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            discord_roles_for(&controller, &config),
+            vec!["Senior Staff", "Events Team", "Controller"]
+        );
+
+        controller.roles = String::new();
+        controller.rating = 4;
+        assert!(discord_roles_for(&controller, &config).is_empty());
+
+        controller.rating = 2;
+        controller.is_on_roster = false;
+        assert!(discord_roles_for(&controller, &config).is_empty());
+    }
+
+    #[test]
+    fn test_solo_cert_matches() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let cert = SoloCert {
+            id: 1,
+            cid: 123,
+            issued_by: 456,
+            position: "DEN_APP".to_owned(),
+            reported: true,
+            created_date: now - Duration::days(20),
+            expiration_date: now + Duration::days(5),
+        };
+
+        assert!(solo_cert_matches(&cert, "", None, None, now));
+        assert!(solo_cert_matches(&cert, "den", None, None, now));
+        assert!(!solo_cert_matches(&cert, "COS", None, None, now));
+        assert!(solo_cert_matches(&cert, "", Some(true), None, now));
+        assert!(!solo_cert_matches(&cert, "", Some(false), None, now));
+        assert!(solo_cert_matches(&cert, "", None, Some(7), now));
+        assert!(!solo_cert_matches(&cert, "", None, Some(3), now));
+    }
+
+    #[test]
+    fn test_reconcile_solo_certs() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let local_cert = |id, cid, position: &str, reported, expires_in| SoloCert {
+            id,
+            cid,
+            issued_by: 1,
+            position: position.to_owned(),
+            reported,
+            created_date: now - Duration::days(10),
+            expiration_date: now + Duration::days(expires_in),
+        };
+        let remote_cert = |id, cid, position: &str| vatusa::SoloCert {
+            id,
+            cid,
+            position: position.to_owned(),
+            expires: "2024-03-15".to_owned(),
+        };
+        let local = vec![
+            local_cert(1, 10, "DEN_APP", true, 5),
+            local_cert(2, 11, "DEN_TWR", true, 5),
+            local_cert(3, 12, "COS_APP", false, 5),
+            local_cert(4, 13, "DEN_GND", true, -5),
+        ];
+        let remote = vec![
+            remote_cert(1, 10, "DEN_APP"),
+            remote_cert(2, 14, "DEN_CTR"),
+            remote_cert(3, 15, "ZLC_CTR"),
+        ];
+        let prefixes = vec!["DEN".to_owned(), "COS".to_owned()];
+
+        let drift = reconcile_solo_certs(&local, &remote, &prefixes, now);
+        let missing_from_vatusa: Vec<_> = drift.missing_from_vatusa.iter().map(|c| c.id).collect();
+        let missing_locally: Vec<_> = drift.missing_locally.iter().map(|c| c.id).collect();
+        assert_eq!(missing_from_vatusa, vec![2]);
+        assert_eq!(missing_locally, vec![2]);
+    }
+
+    #[test]
+    fn test_normalize_position() {
+        let mut config = Config::default();
+        assert_eq!(
+            normalize_position(&config, " den_twr "),
+            Some("DEN_TWR".to_owned())
+        );
+        assert_eq!(normalize_position(&config, "DEN TWR"), None);
+        assert_eq!(normalize_position(&config, "TWR"), None);
+
+        config.stats.position_prefixes = vec!["DEN".to_owned(), "COS".to_owned()];
+        config.stats.position_suffixes = vec!["_TWR".to_owned(), "_APP".to_owned()];
+        assert_eq!(
+            normalize_position(&config, "den_n_app"),
+            Some("DEN_N_APP".to_owned())
+        );
+        assert_eq!(
+            normalize_position(&config, "COS_TWR"),
+            Some("COS_TWR".to_owned())
+        );
+        assert_eq!(normalize_position(&config, "DEN_TWER"), None);
+        assert_eq!(normalize_position(&config, "LAX_TWR"), None);
+        assert_eq!(normalize_position(&config, "asdf"), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("/news", "/news"), 0);
+    }
+
+    #[test]
+    fn test_suggest_route() {
+        let routes = [
+            "/facility/roster",
+            "/facility/staff",
+            "/controller/:cid/events",
+            "/events/:id",
+        ];
+        assert_eq!(
+            suggest_route("/facility/rooster", &routes),
+            Some("/facility/roster".to_owned())
+        );
+        assert_eq!(
+            suggest_route("/facility/staf/", &routes),
+            Some("/facility/staff".to_owned())
+        );
+        assert_eq!(
+            suggest_route("/controllers/123/events", &routes),
+            Some("/controller/123/events".to_owned())
+        );
+        assert_eq!(suggest_route("/something/else/entirely", &routes), None);
+        assert_eq!(suggest_route("/controllers/<b>/events", &routes), None);
+    }
+
+    #[test]
+    fn test_matches_route() {
+        let routes = ["/", "/facility/roster", "/controller/:cid/events"];
+        assert!(matches_route("/", &routes));
+        assert!(matches_route("/facility/roster", &routes));
+        assert!(matches_route("/controller/123/events", &routes));
+        assert!(!matches_route("/facility/staff", &routes));
+        assert!(!matches_route("/controller//events", &routes));
+        assert!(!matches_route("//evil.com", &routes));
+        assert!(!matches_route("https://evil.com", &routes));
+        assert!(!matches_route("/controller/<b>/events", &routes));
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape_xml("plain"), "plain");
+    }
+
+    #[test]
+    fn test_build_news_feed() {
+        let entries = vec![NewsEntry {
+            id: 3,
+            title: "New <SOP> & LOA".to_owned(),
+            body: "Read it".to_owned(),
+            created_date: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+            posted_by: 123,
+        }];
+        let feed = build_news_feed(&ConfigFacility::default(), "https://example.com", &entries);
+
+        assert!(feed.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0">"#));
+        assert!(feed.contains("<title>vZDV News</title><link>https://example.com/news</link>"));
+        assert!(feed.contains(
+            "<item><title>New &lt;SOP&gt; &amp; LOA</title><link>https://example.com/news#3</link>"
+        ));
+        assert!(feed.contains("<pubDate>Fri, 1 Mar 2024 12:00:00 +0000</pubDate>"));
+        assert!(feed.contains("<description>Read it</description></item>"));
+        assert!(feed.ends_with("</channel></rss>"));
+    }
+
+    #[test]
+    fn test_build_news_feed_empty() {
+        let feed = build_news_feed(&ConfigFacility::default(), "https://example.com", &[]);
+        assert!(!feed.contains("<item>"));
+    }
+
+    /// Empty in-memory DB with the current schema.
+    ///
+    /// In-memory DBs are per-connection, so the pool only ever has the one.
+    pub(crate) async fn test_db() -> SqlitePool {
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        db.execute(sql::CREATE_TABLES).await.unwrap();
+        sqlx::query(sql::SET_SCHEMA_VERSION)
+            .bind(sql::MIGRATIONS.len() as u32)
+            .execute(&db)
+            .await
+            .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_upsert_activity() {
+        let db = test_db().await;
+        sqlx::query("INSERT INTO controller (cid, first_name, last_name) VALUES (1, 'A', 'B')")
+            .execute(&db)
+            .await
+            .unwrap();
+        for (month, minutes) in [("2024-01", 10), ("2024-02", 20), ("2024-01", 30)] {
+            sqlx::query(sql::UPSERT_ACTIVITY)
+                .bind(1)
+                .bind(month)
+                .bind(minutes)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        sqlx::query(sql::DELETE_ACTIVITY_NOT_IN)
+            .bind(1)
+            .bind(r#"["2024-01"]"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        let activity: Vec<Activity> = sqlx::query_as(sql::GET_ACTIVITY_FOR)
+            .bind(1)
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].month, "2024-01");
+        assert_eq!(activity[0].minutes, 30);
+    }
+
+    #[tokio::test]
+    async fn test_find_orphaned_assets() {
+        let db = test_db().await;
+        for (name, file_name, link) in [
+            ("SOP", Some("abc_sop.pdf"), None),
+            ("LOA", None, Some("https://example.com/loa.pdf")),
+        ] {
+            sqlx::query(
+                "INSERT INTO resource (category, name, file_name, link, updated) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind("General")
+            .bind(name)
+            .bind(file_name)
+            .bind(link)
+            .bind(Utc::now())
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        let files = vec!["abc_sop.pdf".to_owned(), "def_old.pdf".to_owned()];
+        let orphans = find_orphaned_assets(&db, &files).await.unwrap();
+        assert_eq!(orphans, vec!["def_old.pdf".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_set_operating_initials_race() {
+        let db = test_db().await;
+        for cid in [1, 2] {
+            sqlx::query(sql::UPSERT_USER_LOGIN)
+                .bind(cid)
+                .bind("First")
+                .bind("Last")
+                .bind("first.last@example.com")
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let (first, second) = tokio::join!(
+            set_operating_initials(&db, 1, "AB"),
+            set_operating_initials(&db, 2, "AB"),
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_ne!(first, second);
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM controller WHERE operating_initials='AB'")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(count, 1);
+
+        // re-setting your own OIs and clearing them are both fine
+        let holder = if first { 1 } else { 2 };
+        assert!(set_operating_initials(&db, holder, "AB").await.unwrap());
+        assert!(set_operating_initials(&db, holder, "").await.unwrap());
+        assert!(set_operating_initials(&db, 3 - holder, "AB").await.unwrap());
+    }
+
+    #[test]
+    fn test_log_category_parse() {
+        for category in LogCategory::ALL {
+            assert_eq!(LogCategory::parse(category.as_str()), Some(category));
+        }
+        assert_eq!(LogCategory::parse(""), None);
+        assert_eq!(LogCategory::parse("Security"), None);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("exactly", 7), "exactly");
+        assert_eq!(truncate_chars("abcdef", 3), "abc...");
+        assert_eq!(truncate_chars("", 0), "");
+
+        let comment = "Très bien ✈️ 👍 ".repeat(100);
+        let truncated = truncate_chars(&comment, 1_021);
+        assert_eq!(truncated.chars().count(), 1_024);
+        assert!(truncated.ends_with("..."));
+        assert_eq!(truncate_chars("日本語のテキスト", 3), "日本語...");
+    }
+
+    #[test]
+    fn test_truncate_chars_multibyte_boundary() {
+        // byte 100 lands in the middle of the 'é'
+        let comment = format!("{}é{}", "a".repeat(99), "b".repeat(50));
+        assert!(!comment.is_char_boundary(100));
+        let truncated = truncate_chars(&comment, 100);
+        assert_eq!(truncated, format!("{}é...", "a".repeat(99)));
+
+        let comment = "🛫".repeat(150);
+        let truncated = truncate_chars(&comment, 100);
+        assert_eq!(truncated.chars().count(), 103);
+        assert!(truncated.starts_with(&"🛫".repeat(100)));
+    }
+
+    #[test]
+    fn test_log_file_path() {
+        let logs = ConfigLogs {
+            directory: "/var/log/vzdv".into(),
+            files: vec!["site.log".to_owned(), "../secret".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            logs.path_for("site.log"),
+            Some("/var/log/vzdv/site.log".into())
+        );
+        assert_eq!(logs.path_for("tasks.log"), None);
+        assert_eq!(logs.path_for("../secret"), None);
+        assert_eq!(logs.path_for("/etc/passwd"), None);
+        assert_eq!(logs.path_for(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_last_lines() {
+        let path = std::env::temp_dir().join(format!("vzdv_test_log_{}.log", std::process::id()));
+        std::fs::write(&path, b"one\ntw\xffo\r\nthree\nfour").unwrap();
+
+        let lines = read_last_lines(&path, 3).await.unwrap();
+        assert_eq!(lines, vec!["four", "three", "tw\u{fffd}o"]);
+        let lines = read_last_lines(&path, 100).await.unwrap();
+        assert_eq!(lines.len(), 4);
+        assert!(read_last_lines(&path, 0).await.unwrap().is_empty());
+        assert!(read_last_lines(&path.with_extension("missing"), 10)
+            .await
+            .is_err());
+
+        let contents: String = (0..5_000).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(&path, contents).unwrap();
+        let lines = read_last_lines(&path, 2_000).await.unwrap();
+        assert_eq!(lines.len(), 2_000);
+        assert_eq!(lines[0], "line 4999");
+        assert_eq!(lines[1_999], "line 3000");
+        assert_eq!(read_last_lines(&path, 10_000).await.unwrap().len(), 5_000);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(display_name("John", "Doe", false, false), "John Doe");
+        assert_eq!(display_name("John", "Doe", false, true), "John Doe");
+        assert_eq!(display_name("John", "Doe", true, false), "John D.");
+        assert_eq!(display_name("John", "Doe", true, true), "John Doe");
+        assert_eq!(display_name("Zoë", "Øster", true, false), "Zoë Ø.");
+        assert_eq!(display_name("John", "", true, false), "John");
+        assert_eq!(display_last_name("Doe", true, false), "D.");
+        assert_eq!(display_last_name("Doe", true, true), "Doe");
+        assert_eq!(display_last_name("Doe", false, false), "Doe");
+    }
+
+    #[test]
+    fn test_controller_by_display_name() {
+        let controller = |cid, first: &str, last: &str, name_privacy| Controller {
+            cid,
+            first_name: first.to_owned(),
+            last_name: last.to_owned(),
+            name_privacy,
+            ..Default::default()
+        };
+        let controllers = vec![
+            controller(1, "John", "Doe", true),
+            controller(2, "Jane", "Smith", false),
+            controller(3, "Sam", "Jones", true),
+            controller(4, "Sam", "James", true),
+        ];
+        assert_eq!(
+            controller_by_display_name(&controllers, "john d.").map(|c| c.cid),
+            Some(1)
+        );
+        assert!(controller_by_display_name(&controllers, "Jane S.").is_none());
+        assert!(controller_by_display_name(&controllers, "Sam J.").is_none());
+        assert!(controller_by_display_name(&controllers, "John Doe").is_none());
+    }
+
+    #[test]
+    fn test_flight_relevance() {
+        let mut config = Config::default();
+        for code in ["KDEN", "KCOS"] {
+            config.airports.all.push(Airport {
+                code: code.to_owned(),
+                ..Default::default()
+            });
+        }
+        config.airports.boundary = vec![
+            (41.0, -109.0),
+            (41.0, -102.0),
+            (37.0, -102.0),
+            (37.0, -109.0),
+        ];
+        let inside = (39.8, -104.7);
+        let outside = (33.4, -112.0);
+
+        assert_eq!(
+            flight_relevance(&config, Some("KDEN"), Some("KPHX"), outside),
+            Some(FlightRelevance::Departing)
+        );
+        assert_eq!(
+            flight_relevance(&config, Some("KPHX"), Some("KDEN"), outside),
+            Some(FlightRelevance::Arriving)
+        );
+        assert_eq!(
+            flight_relevance(&config, Some("KDEN"), Some("KCOS"), inside),
+            Some(FlightRelevance::Local)
+        );
+        assert_eq!(
+            flight_relevance(&config, Some("KLAX"), Some("KORD"), inside),
+            Some(FlightRelevance::Overflight)
+        );
+        assert_eq!(
+            flight_relevance(&config, None, None, inside),
+            Some(FlightRelevance::Overflight)
+        );
+        assert_eq!(
+            flight_relevance(&config, Some("KLAX"), Some("KORD"), outside),
+            None
+        );
+
+        config.airports.boundary.clear();
+        assert_eq!(
+            flight_relevance(&config, Some("KLAX"), Some("KORD"), inside),
+            None
+        );
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        let triangle = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)];
+        assert!(point_in_polygon((2.0, 2.0), &triangle));
+        assert!(!point_in_polygon((8.0, 8.0), &triangle));
+        assert!(!point_in_polygon((-1.0, 2.0), &triangle));
+        assert!(!point_in_polygon((1.0, 1.0), &triangle[..2]));
+    }
+
+    #[test]
+    fn test_build_vcard() {
+        assert_eq!(
+            build_vcard(
+                "vZDV",
+                "John",
+                "Doe",
+                "Air Traffic Manager",
+                Some("atm@example.com")
+            ),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;John;;;\r\nFN:John Doe\r\nORG:vZDV\r\n\
+             TITLE:Air Traffic Manager\r\nEMAIL;TYPE=INTERNET:atm@example.com\r\nEND:VCARD\r\n"
+        );
+        let card = build_vcard("vZDV", "Jane", "Smith, Jr.", "Mentor", None);
+        assert!(card.contains("N:Smith\\, Jr.;Jane;;;\r\n"));
+        assert!(!card.contains("EMAIL"));
+        assert_eq!(escape_vcard("a;b\\c\nd"), "a\\;b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_quarterly_minutes() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap();
+        let activity: Vec<_> = [
+            ("2024-06", 30),
+            ("2024-04", 60),
+            ("2024-03", 200),
+            ("2023-12", 500),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, (month, minutes))| Activity {
+            id: id as u32,
+            cid: 1,
+            month: month.to_owned(),
+            minutes,
+        })
+        .collect();
+        assert_eq!(
+            quarterly_minutes(&activity, 1, now, 3, QuarterMode::Rolling),
+            vec![90, 200, 500]
+        );
+        assert_eq!(
+            quarterly_minutes(&activity, 2, now, 2, QuarterMode::Rolling),
+            vec![0, 0]
+        );
+        assert_eq!(
+            quarterly_minutes(&activity, 1, now, 3, QuarterMode::Calendar),
+            vec![90, 200, 500]
+        );
+        assert_eq!(consecutive_inactive_quarters(&[90, 200, 0]), 1);
+        assert_eq!(consecutive_inactive_quarters(&[90, 0, 500]), 2);
+        assert_eq!(consecutive_inactive_quarters(&[180, 0]), 0);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"what do ya want for nothing?";
+        let signature = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        assert!(verify_signature("Jefe", body, signature));
+        assert!(verify_signature("Jefe", body, &signature.to_uppercase()));
+        assert!(!verify_signature(
+            "Jefe",
+            b"what do ya want for something?",
+            signature
+        ));
+        assert!(!verify_signature("other", body, signature));
+        assert!(!verify_signature("Jefe", body, "not hex"));
+        assert!(!verify_signature("Jefe", body, ""));
+        assert!(!verify_signature("", body, signature));
+    }
+
+    #[test]
+    fn test_current_atis() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        let atis =
+            |id: u32, facility: &str, letter: &str, atis_type: &str, minutes_ago: i64| Atis {
+                id,
+                facility: facility.to_owned(),
+                letter: letter.to_owned(),
+                atis_type: atis_type.to_owned(),
+                airport_conditions: String::new(),
+                notams: String::new(),
+                timestamp: now - Duration::minutes(minutes_ago),
+            };
+        let entries = vec![
+            atis(5, "KDEN", "C", "departure", 5),
+            atis(4, "KDEN", "B", "arrival", 10),
+            atis(3, "KCOS", "Q", "combined", 15),
+            atis(2, "KDEN", "A", "arrival", 60),
+            atis(1, "KDEN", "Z", "combined", 200),
+        ];
+
+        let current = current_atis(entries, "kden", now);
+        assert_eq!(current.iter().map(|a| a.id).collect::<Vec<_>>(), vec![4, 5]);
+        assert!(current_atis(Vec::new(), "KDEN", now).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clean_up_atis() {
+        let mut config = Config::default();
+        config.atis.retention_hours = 6;
+        let db = test_db().await;
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        for (letter, hours_ago) in [("A", 10), ("B", 7), ("C", 5), ("D", 1)] {
+            sqlx::query(sql::INSERT_ATIS_ENTRY)
+                .bind("KDEN")
+                .bind(letter)
+                .bind("combined")
+                .bind("")
+                .bind("")
+                .bind(now - Duration::hours(hours_ago))
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let removed = clean_up_atis(&config, &db, now).await.unwrap();
+        assert_eq!(removed, 2);
+        let remaining: Vec<Atis> = sqlx::query_as(sql::GET_ALL_ATIS_ENTRIES)
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|a| a.letter.as_str())
+                .collect::<Vec<_>>(),
+            vec!["D", "C"]
+        );
+    }
+
+    #[test]
+    fn test_digest_due() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 20, 12, 0, 0).unwrap();
+        assert!(digest_due(None, now, 7));
+        assert!(!digest_due(Some(now - Duration::days(6)), now, 7));
+        assert!(digest_due(Some(now - Duration::days(7)), now, 7));
+        assert!(digest_due(Some(now - Duration::days(1)), now, 1));
+    }
+
+    #[test]
+    fn test_should_remind_of_activity() {
+        let config = ConfigCurrency {
+            reminder_days: 10,
+            min_minutes_short: 30,
+            reminder_interval_days: 7,
+            ..Default::default()
+        };
+        let late = Utc.with_ymd_and_hms(2024, 6, 25, 12, 0, 0).unwrap();
+        let early = Utc.with_ymd_and_hms(2024, 6, 5, 12, 0, 0).unwrap();
+
+        assert!(should_remind_of_activity(&config, 0, late, None));
+        assert!(should_remind_of_activity(&config, 150, late, None));
+        // not close enough to the end of the month
+        assert!(!should_remind_of_activity(&config, 0, early, None));
+        // met the requirement, or only a little short
+        assert!(!should_remind_of_activity(&config, 180, late, None));
+        assert!(!should_remind_of_activity(&config, 160, late, None));
+        // reminded recently
+        assert!(!should_remind_of_activity(
+            &config,
+            0,
+            late,
+            Some(late - Duration::days(3))
+        ));
+        assert!(should_remind_of_activity(
+            &config,
+            0,
+            late,
+            Some(late - Duration::days(7))
+        ));
+        // disabled
+        let disabled = ConfigCurrency {
+            reminder_days: 0,
+            ..config
+        };
+        assert!(!should_remind_of_activity(&disabled, 0, late, None));
+    }
+
+    #[test]
+    fn test_format_log_line() {
+        assert_eq!(
+            format_log_line(
+                r#"{"level":"WARN","message":"Something happened","target":"vzdv::endpoints","timestamp":"2024-06-15T12:00:00+00:00"}"#
+            ),
+            "2024-06-15T12:00:00+00:00 WARN  vzdv::endpoints > Something happened"
+        );
+        assert_eq!(
+            format_log_line(" INFO  vzdv > Plain line"),
+            " INFO  vzdv > Plain line"
+        );
+        assert_eq!(
+            format_log_line(r#"{"other":"json"}"#),
+            r#"{"other":"json"}"#
+        );
+    }
+
+    #[test]
+    fn test_last_session_date() {
+        let record = |facility: &str, date: &str| vatusa::TrainingRecord {
+            id: 1,
+            student_id: 1,
+            instructor_id: 2,
+            session_date: date.to_owned(),
+            facility_id: facility.to_owned(),
+            position: "DEN_APP".to_owned(),
+            duration: "01:00:00".to_owned(),
+            notes: String::new(),
+        };
+        let records = vec![
+            record("ZDV", "2024-03-01 18:00:00"),
+            record("ZLC", "2024-06-01 18:00:00"),
+            record("ZDV", "2024-05-01 18:00:00"),
+        ];
+        assert_eq!(
+            vatusa::last_session_date(&records, "ZDV"),
+            Some("2024-05-01 18:00:00")
+        );
+        assert_eq!(vatusa::last_session_date(&records, "ZAB"), None);
+    }
+
+    #[test]
+    fn test_training_record_filter() {
+        let record = |position: &str, instructor_id, date: &str| vatusa::TrainingRecord {
+            id: 1,
+            student_id: 1,
+            instructor_id,
+            session_date: date.to_owned(),
+            facility_id: "ZDV".to_owned(),
+            position: position.to_owned(),
+            duration: "01:00:00".to_owned(),
+            notes: String::new(),
+        };
+        let ground = record("DEN_GND", 10, "2024-03-01 18:00:00");
+        let approach = record("DEN_APP", 20, "2024-05-01 18:00:00");
+
+        let filter = vatusa::TrainingRecordFilter::default();
+        assert!(!filter.is_active());
+        assert!(filter.matches(&ground) && filter.matches(&approach));
+
+        let filter = vatusa::TrainingRecordFilter {
+            position: "gnd".to_owned(),
+            ..Default::default()
+        };
+        assert!(filter.is_active());
+        assert!(filter.matches(&ground));
+        assert!(!filter.matches(&approach));
+
+        let filter = vatusa::TrainingRecordFilter {
+            instructor: "20".to_owned(),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&ground));
+        assert!(filter.matches(&approach));
+
+        let filter = vatusa::TrainingRecordFilter {
+            from: "2024-03-01".to_owned(),
+            to: "2024-04-30".to_owned(),
+            ..Default::default()
+        };
+        assert!(filter.matches(&ground));
+        assert!(!filter.matches(&approach));
+    }
+
+    #[test]
+    fn test_roster_diff() {
+        let (added, removed) = roster_diff(&[1, 2, 3], &[2, 3, 4, 5]);
+        assert_eq!(added, vec![4, 5]);
+        assert_eq!(removed, vec![1]);
+
+        let (added, removed) = roster_diff(&[1, 2], &[2, 1]);
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn test_pagination() {
+        let query = |page, per_page| PageQuery { page, per_page };
+
+        let pagination = Pagination::new(&query(Some(2), None), 20, 45);
+        assert_eq!(pagination.pages, 3);
+        assert_eq!(pagination.page, 2);
+        assert_eq!(pagination.limit(), 20);
+        assert_eq!(pagination.offset(), 20);
+
+        assert_eq!(Pagination::new(&query(Some(10), None), 20, 45).page, 3);
+        assert_eq!(Pagination::new(&query(Some(0), None), 20, 45).page, 1);
+        let empty = Pagination::new(&query(None, None), 20, 0);
+        assert_eq!(empty.pages, 1);
+        assert_eq!(empty.offset(), 0);
+
+        let custom = Pagination::new(&query(Some(3), Some(10)), 20, 45);
+        assert_eq!(custom.pages, 5);
+        assert_eq!(custom.offset(), 20);
+        assert_eq!(Pagination::new(&query(None, Some(0)), 20, 45).per_page, 1);
+        assert_eq!(
+            Pagination::new(&query(None, Some(5_000)), 20, 45).per_page,
+            100
+        );
+    }
+
+    #[test]
+    fn test_local_date() {
+        let date = "2024-07-04T18:30:00Z";
+        assert_eq!(local_date(date, None), "07/04/2024 18:30:00 UTC");
+        assert_eq!(
+            local_date(date, Some("America/Denver")),
+            "07/04/2024 12:30:00 MDT"
+        );
+        assert_eq!(
+            local_date("2024-01-04T18:30:00Z", Some("America/Denver")),
+            "01/04/2024 11:30:00 MST"
+        );
+        assert_eq!(
+            local_date(date, Some("Not/AZone")),
+            "07/04/2024 18:30:00 UTC"
+        );
+        assert_eq!(local_date("yesterday", None), "yesterday");
+    }
+
+    #[test]
+    fn test_roles_to_set() {
+        assert_eq!(roles_to_set("DATM"), STAFF_ROLES.to_vec());
+        assert_eq!(roles_to_set("TA"), vec!["MTR"]);
+        assert_eq!(roles_to_set("EC,FE"), vec!["AEC", "AFE"]);
+        assert!(roles_to_set("MTR").is_empty());
+        assert!(roles_to_set("").is_empty());
+    }
+
+    #[test]
+    fn test_controller_changes() {
+        let before = Controller {
+            cid: 123,
+            first_name: "Jane".to_owned(),
+            last_name: "Doe".to_owned(),
+            rating: 3,
+            home_facility: "ZAB".to_owned(),
+            is_on_roster: true,
+            ..Default::default()
+        };
+        assert!(controller_changes(&before, &before.clone()).is_empty());
+
+        let after = Controller {
+            last_name: "Smith".to_owned(),
+            rating: 4,
+            home_facility: "ZDV".to_owned(),
+            ..before.clone()
+        };
+        assert_eq!(
+            controller_changes(&before, &after),
+            vec![
+                "name: Jane Doe -> Jane Smith",
+                "rating: S2 -> S3",
+                "home facility: ZAB -> ZDV",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preferred_position() {
+        let position = |id, name: &str, cid| EventPosition {
+            id,
+            event_id: 1,
+            name: name.to_owned(),
+            cid,
+        };
+        let positions = vec![
+            position(1, "DEN_GND", None),
+            position(2, "DEN_TWR", Some(123)),
+            position(3, "APA_TWR", None),
+            position(4, "DEN_APP", None),
+        ];
+        let prefs = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            preferred_position(&positions, &prefs(&["TWR", "GND"])),
+            Some(3)
+        );
+        assert_eq!(
+            preferred_position(&positions, &prefs(&["CTR", "app"])),
+            Some(4)
+        );
+        assert_eq!(preferred_position(&positions, &prefs(&["CTR"])), None);
+        assert_eq!(preferred_position(&positions, &[]), None);
+    }
+
+    #[test]
+    fn test_category_full() {
+        let position = |id, name: &str, cid| EventPosition {
+            id,
+            event_id: 1,
+            name: name.to_owned(),
+            cid,
+        };
+        let positions = vec![
+            position(1, "DEN_TWR", Some(123)),
+            position(2, "APA_TWR", Some(456)),
+            position(3, "DEN_APP", Some(123)),
+            position(4, "D01_APP", None),
+        ];
+        assert!(category_full(&positions, 1, 789));
+        assert!(!category_full(&positions, 2, 456));
+        assert!(!category_full(&positions, 3, 789));
+        assert!(!category_full(&positions, 99, 789));
+    }
+
+    #[test]
+    fn test_registration_waitlisted() {
+        let position = |id, name: &str, cid| EventPosition {
+            id,
+            event_id: 1,
+            name: name.to_owned(),
+            cid,
+        };
+        let mut positions = vec![
+            position(1, "DEN_TWR", Some(123)),
+            position(2, "APA_TWR", Some(456)),
+            position(3, "DEN_APP", None),
+        ];
+        assert!(registration_waitlisted(&positions, 1, 789));
+        assert!(!registration_waitlisted(&positions, 1, 123));
+        assert!(!registration_waitlisted(&positions, 3, 789));
+
+        // unassigning a position takes them off the waitlist
+        positions[1].cid = None;
+        assert!(!registration_waitlisted(&positions, 1, 789));
+
+        // as does being assigned somewhere else
+        positions[1].cid = Some(456);
+        positions[2].cid = Some(789);
+        assert!(!registration_waitlisted(&positions, 1, 789));
+    }
+
+    #[test]
+    fn test_waitlist_candidates() {
+        let position = |id, name: &str, cid| EventPosition {
+            id,
+            event_id: 1,
+            name: name.to_owned(),
+            cid,
+        };
+        let registration = |id, position_id, cid, waitlisted| EventRegistration {
+            id,
+            event_id: 1,
+            position_id,
+            cid,
+            waitlisted,
+        };
+        let positions = vec![
+            position(1, "DEN_TWR", None),
+            position(2, "APA_TWR", Some(100)),
+            position(3, "DEN_APP", None),
+        ];
+        let registrations = vec![
+            registration(4, 2, 300, false),
+            registration(1, 2, 100, false),
+            registration(2, 2, 200, true),
+            registration(3, 3, 100, true),
+            registration(5, 3, 400, false),
+        ];
+        let candidates = waitlist_candidates(&positions, &registrations);
+        assert_eq!(candidates.get(&1), Some(&vec![200, 300]));
+        assert_eq!(candidates.get(&2), None);
+        assert_eq!(candidates.get(&3), Some(&vec![400]));
+    }
+
+    #[test]
+    fn test_glance_distance() {
+        assert_eq!(glance_distance(20.0, None), 20.0);
+        assert_eq!(glance_distance(20.0, Some(75.5)), 75.5);
+        assert_eq!(glance_distance(20.0, Some(-5.0)), 20.0);
+        assert_eq!(glance_distance(20.0, Some(f64::NAN)), 20.0);
+        assert_eq!(glance_distance(20.0, Some(f64::INFINITY)), 20.0);
+        assert_eq!(glance_distance(20.0, Some(10_000.0)), GLANCE_MAX_DISTANCE);
+        assert_eq!(glance_distance(0.0, None), 1.0);
+    }
+
+    #[test]
+    fn test_distance_nm() {
+        let kden = (39.8617, -104.6731);
+        let kcos = (38.8058, -104.7008);
+        assert!((distance_nm(kden, kcos) - 63.4).abs() < 0.5);
+        assert_eq!(distance_nm(kden, kden), 0.0);
+    }
+
+    #[test]
+    fn test_glance_sort() {
+        assert_eq!(GlanceSort::parse("callsign"), Some(GlanceSort::Callsign));
+        assert_eq!(
+            GlanceSort::parse("controlling-time"),
+            Some(GlanceSort::ControllingTime)
+        );
+        assert_eq!(GlanceSort::parse("altitude"), None);
+        assert_eq!(GlanceSort::default(), GlanceSort::PilotingTime);
+        assert_eq!(
+            GlanceSort::parse(GlanceSort::Aircraft.as_str()),
+            Some(GlanceSort::Aircraft)
+        );
+    }
+
+    #[test]
+    fn test_compare_hours() {
+        let mut hours = vec![Some(5.0), None, Some(1.0), Some(20.0)];
+        hours.sort_by(|a, b| compare_hours(*a, *b, false));
+        assert_eq!(hours, vec![Some(1.0), Some(5.0), Some(20.0), None]);
+        hours.sort_by(|a, b| compare_hours(*a, *b, true));
+        assert_eq!(hours, vec![Some(20.0), Some(5.0), Some(1.0), None]);
+    }
+
+    #[test]
+    fn test_parse_vatusa_date() {
+        assert_eq!(
+            parse_vatusa_date("2024-03-02T16:20:37+00:00"),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 16, 20, 37).unwrap())
+        );
+        assert_eq!(
+            parse_vatusa_date("2024-03-02 16:20:37"),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 16, 20, 37).unwrap())
+        );
+        assert_eq!(parse_vatusa_date(""), None);
+    }
+
+    #[test]
+    fn test_activity_violation_new_controller() {
+        let config = ConfigCurrency::default();
+        let now = Utc::now();
+        let new = Controller {
+            join_date: Some(now - Duration::days(7)),
+            ..Default::default()
+        };
+        assert!(is_new_controller(&config, &new, now));
+        assert!(!activity_violation(&config, &new, 0, now));
+
+        let established = Controller {
+            join_date: Some(now - Duration::days(365)),
+            ..Default::default()
+        };
+        assert!(!is_new_controller(&config, &established, now));
+        assert!(activity_violation(&config, &established, 0, now));
+        assert!(!activity_violation(
+            &config,
+            &established,
+            QUARTERLY_ACTIVITY_MINUTES,
+            now
+        ));
+
+        // no known join date means no exemption
+        assert!(activity_violation(&config, &Controller::default(), 0, now));
+    }
+
+    #[test]
+    fn test_activity_violation_loa() {
+        let config = ConfigCurrency::default();
+        let now = Utc::now();
+        let on_loa = Controller {
+            loa_until: Some(now + Duration::days(30)),
+            ..Default::default()
+        };
+        assert!(is_on_loa(&on_loa, now));
+        assert!(!activity_violation(&config, &on_loa, 10, now));
+
+        let loa_ended = Controller {
+            loa_until: Some(now - Duration::days(1)),
+            ..Default::default()
+        };
+        assert!(!is_on_loa(&loa_ended, now));
+        assert!(activity_violation(&config, &loa_ended, 10, now));
+    }
+
+    #[test]
+    fn test_asset_file_name() {
+        let name = asset_file_name("ZDV SOP (2024).pdf");
+        assert!(name.ends_with("_ZDV_SOP__2024_.pdf"));
+        assert_eq!(name.len(), 36 + 1 + "ZDV_SOP__2024_.pdf".len());
+
+        let name = asset_file_name("../../etc/passwd");
+        assert!(name.ends_with("_passwd"));
+        let name = asset_file_name("C:\\Users\\me\\.hidden");
+        assert!(name.ends_with("_hidden"));
+    }
+
+    #[test]
+    fn test_upload_allowed() {
+        let allowed = ConfigResources::default().allowed_extensions;
+        assert!(upload_allowed("sop.pdf", Some("application/pdf"), &allowed));
+        assert!(upload_allowed("Map.PNG", Some("image/png"), &allowed));
+        assert!(upload_allowed("notes.txt", None, &allowed));
+        assert!(upload_allowed(
+            "notes.txt",
+            Some("text/plain; charset=utf-8"),
+            &allowed
+        ));
+
+        assert!(!upload_allowed("page.html", Some("text/html"), &allowed));
+        assert!(!upload_allowed("setup.exe", None, &allowed));
+        assert!(!upload_allowed(
+            "image.svg",
+            Some("image/svg+xml"),
+            &allowed
+        ));
+        assert!(!upload_allowed("README", None, &allowed));
+        assert!(!upload_allowed(".pdf", None, &allowed));
+        // allowed extension but the content is declared as markup
+        assert!(!upload_allowed("sop.pdf", Some("text/html"), &allowed));
+        assert!(!upload_allowed(
+            "sop.pdf",
+            Some("Text/HTML; charset=utf-8"),
+            &allowed
+        ));
+    }
+
+    #[test]
+    fn test_asset_content_disposition() {
+        assert_eq!(
+            asset_content_disposition("0b5e1d2c-3f4a-4b6c-8d9e-0f1a2b3c4d5e_ZDV_SOP.pdf"),
+            r#"inline; filename="ZDV_SOP.pdf""#
+        );
+        assert_eq!(
+            asset_content_disposition("0b5e1d2c-3f4a-4b6c-8d9e-0f1a2b3c4d5e_sectors.zip"),
+            r#"attachment; filename="sectors.zip""#
+        );
+        assert_eq!(
+            asset_content_disposition("old upload.PDF"),
+            r#"inline; filename="old_upload.PDF""#
+        );
+        assert_eq!(
+            asset_content_type("file.exe"),
+            ("application/octet-stream", false)
+        );
+    }
+
+    #[test]
+    fn test_webhook_problems() {
+        let mut webhooks = ConfigDiscordWebhooks {
+            staffing_request: "https://discord.com/api/webhooks/1/abc".to_owned(),
+            feedback: "https://discordapp.com/api/webhooks/2/def".to_owned(),
+            ..Default::default()
+        };
+        assert!(webhook_problems(&webhooks).is_empty());
+
+        webhooks.feedback = String::new();
+        webhooks.staff_notes = "http://discord.com/api/webhooks/3/ghi".to_owned();
+        webhooks.roster_alert = "not a url".to_owned();
+        assert_eq!(
+            webhook_problems(&webhooks),
+            vec![
+                "discord.webhooks.feedback is not set; those notifications will be lost",
+                "discord.webhooks.roster_alert is not a valid Discord webhook URL",
+                "discord.webhooks.staff_notes is not a valid Discord webhook URL",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_feedback_controller_name() {
+        assert_eq!(feedback_controller_name(UNCREDITED_CONTROLLER), "Unknown");
+        assert_eq!(feedback_controller_name("Jane Doe"), "Jane Doe");
+    }
+
+    #[test]
+    fn test_sop_coverage() {
+        let updated = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let sop = |id, name: &str| Resource {
+            id,
+            category: "SOP".to_owned(),
+            name: name.to_owned(),
+            file_name: None,
+            link: Some("https://example.com".to_owned()),
+            updated,
+        };
+        let sops = vec![sop(2, "Tower SOP"), sop(1, "Approach SOP")];
+        let controllers = vec![
+            Controller {
+                cid: 20,
+                first_name: "John".to_owned(),
+                last_name: "Smith".to_owned(),
+                ..Default::default()
+            },
+            Controller {
+                cid: 10,
+                first_name: "Jane".to_owned(),
+                last_name: "Doe".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let initials = vec![SopInitial {
+            id: 1,
+            cid: 20,
+            resource_id: 1,
+            created_date: updated,
+        }];
+
+        let coverage = sop_coverage(&sops, &controllers, &initials);
+        let rows: Vec<_> = coverage
+            .iter()
+            .map(|row| (row.resource, row.cid, row.initialed.is_some()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Approach SOP", 10, false),
+                ("Approach SOP", 20, true),
+                ("Tower SOP", 10, false),
+                ("Tower SOP", 20, false),
+            ]
+        );
+        assert_eq!(coverage[1].name, "John Smith");
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Jane Doe"), "Jane Doe");
+        assert_eq!(csv_field("SOP, Tower"), "\"SOP, Tower\"");
+        assert_eq!(csv_field("The \"new\" SOP"), "\"The \"\"new\"\" SOP\"");
+        assert_eq!(csv_field("=1+2"), "'=1+2");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("-2"), "'-2");
+        assert_eq!(csv_field("+1, 2"), "\"'+1, 2\"");
+    }
+
+    #[test]
+    fn test_quarter_months_rolling() {
+        let now = Utc.with_ymd_and_hms(2024, 4, 2, 0, 0, 0).unwrap();
+        assert_eq!(
+            quarter_months(QuarterMode::Rolling, now, 0),
+            vec!["2024-04", "2024-03", "2024-02"]
+        );
+        assert_eq!(
+            quarter_months(QuarterMode::Rolling, now, 1),
+            vec!["2024-01", "2023-12", "2023-11"]
+        );
+    }
+
+    #[test]
+    fn test_quarter_months_calendar() {
+        let start = Utc.with_ymd_and_hms(2024, 4, 2, 0, 0, 0).unwrap();
+        assert_eq!(
+            quarter_months(QuarterMode::Calendar, start, 0),
+            vec!["2024-04"]
+        );
+        assert_eq!(
+            quarter_months(QuarterMode::Calendar, start, 1),
+            vec!["2024-03", "2024-02", "2024-01"]
+        );
+
+        let end = Utc.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap();
+        assert_eq!(
+            quarter_months(QuarterMode::Calendar, end, 0),
+            vec!["2024-03", "2024-02", "2024-01"]
+        );
+        assert_eq!(
+            quarter_months(QuarterMode::Calendar, end, 2),
+            vec!["2023-09", "2023-08", "2023-07"]
+        );
+    }
+
+    #[test]
+    fn test_feedback_contact_blocked() {
+        let blocked = vec![Controller {
+            cid: 123,
+            first_name: "Jane".to_owned(),
+            last_name: "Doe".to_owned(),
+            no_feedback_contact: true,
+            ..Default::default()
+        }];
+        assert!(feedback_contact_blocked(&blocked, "123"));
+        assert!(feedback_contact_blocked(&blocked, "jane doe"));
+        assert!(!feedback_contact_blocked(&blocked, "John Doe"));
+        assert!(!feedback_contact_blocked(&[], "123"));
+    }
+
+    #[test]
+    fn test_ois_suggestions() {
+        assert_eq!(
+            ois_suggestions("Jane", "Doe", &[], 3),
+            vec!["JD", "JO", "JE"]
+        );
+        let in_use = vec!["JD".to_owned(), "jo".to_owned()];
+        assert_eq!(
+            ois_suggestions("Jane", "Doe", &in_use, 3),
+            vec!["JE", "AD", "ND"]
+        );
+        assert_eq!(
+            ois_suggestions("J.", "O'Neil", &["JO".to_owned()], 2),
+            vec!["JN", "JE"]
+        );
+        assert!(ois_suggestions("", "", &[], 3).is_empty());
+    }
+
+    #[test]
+    fn test_config_restart_changes() {
+        let current = Config::default();
+        let mut new = Config::default();
+        new.discord.webhooks.feedback = "https://example.com".to_owned();
+        new.cache.ttls.insert("ONLINE_PILOTS".to_owned(), 60);
+        assert!(config_restart_changes(&current, &new).is_empty());
+
+        new.database.file = "other.db".to_owned();
+        new.resources.max_upload_mb += 1;
+        new.api.cors_allowed_origins = vec!["https://example.com".to_owned()];
+        assert_eq!(
+            config_restart_changes(&current, &new),
+            vec![
+                "database.file",
+                "resources.max_upload_mb",
+                "api.cors_allowed_origins"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_training_contact() {
+        let mut staff = ConfigStaff {
+            email_domain: "example.com".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(staff.training_contact(), "ta@example.com");
+
+        staff.training_email = "training@example.com".to_owned();
+        assert_eq!(staff.training_contact(), "training@example.com");
+    }
+
+    #[test]
+    fn test_feedback_stats() {
+        let row = |month: &str, rating: &str, position: &str, count: u32| FeedbackCount {
+            month: month.to_owned(),
+            rating: rating.to_owned(),
+            position: position.to_owned(),
+            count,
+        };
+        let counts = vec![
+            row("2024-02", "good", "DEN_APP", 2),
+            row("2024-01", "excellent", "den_app ", 3),
+            row("2024-01", "poor", "DEN_GND", 1),
+            row("2024-02", "excellent", "", 4),
+            row("2024-02", "excellent", "DEN_TWR", 1),
+        ];
+        let stats = feedback_stats(&counts, 2);
+
+        assert_eq!(stats.total, 11);
+        assert_eq!(
+            stats.by_rating,
+            vec![
+                ("excellent".to_owned(), 8),
+                ("good".to_owned(), 2),
+                ("poor".to_owned(), 1)
+            ]
+        );
+        assert_eq!(
+            stats.by_month,
+            vec![
+                FeedbackMonth {
+                    month: "2024-01".to_owned(),
+                    total: 4,
+                    by_rating: BTreeMap::from([
+                        ("excellent".to_owned(), 3),
+                        ("poor".to_owned(), 1)
+                    ]),
+                },
+                FeedbackMonth {
+                    month: "2024-02".to_owned(),
+                    total: 7,
+                    by_rating: BTreeMap::from([
+                        ("excellent".to_owned(), 5),
+                        ("good".to_owned(), 2)
+                    ]),
+                },
+            ]
+        );
+        assert_eq!(
+            stats.top_positions,
+            vec![("DEN_APP".to_owned(), 5), ("DEN_GND".to_owned(), 1)]
+        );
+        assert_eq!(feedback_stats(&[], 5), FeedbackStats::default());
+    }
+
+    #[test]
+    fn test_visitor_certification_checklist() {
+        let mut config = Config::default();
+        config.training.certifications = vec!["GND".to_owned(), "TWR".to_owned(), "APP".to_owned()];
+        let cert = |name: &str, value: &str| Certification {
+            id: 0,
+            cid: 1,
+            name: name.to_owned(),
+            value: value.to_owned(),
+            changed_on: Utc::now(),
+            set_by: 0,
+            valid_until: None,
+            last_used: None,
+        };
+        let certifications = vec![cert("GND", "Certified"), cert("TWR", "Training")];
+        assert_eq!(
+            visitor_certification_checklist(&config, &certifications),
+            vec![
+                ("GND".to_owned(), true),
+                ("TWR".to_owned(), false),
+                ("APP".to_owned(), false)
+            ]
+        );
+
+        config.visitors.required_certifications = vec!["TWR".to_owned()];
+        assert_eq!(
+            visitor_certification_checklist(&config, &[]),
+            vec![("TWR".to_owned(), false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_page_request_id() {
+        let response = REQUEST_ID
+            .scope("abc-123".to_owned(), async {
+                AppError::from(anyhow::anyhow!("test")).into_response()
+            })
+            .await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<code>abc-123</code>"));
+    }
+
+    #[test]
+    fn test_error_page_renders() {
+        let response = AppError::from(anyhow::anyhow!("test")).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn test_api_key_name() {
+        let keys = vec![
+            ConfigApiKey {
+                name: "vATIS".to_owned(),
+                key: "abc123".to_owned(),
+            },
+            ConfigApiKey {
+                name: "unset".to_owned(),
+                key: String::new(),
+            },
+        ];
+        assert_eq!(api_key_name(&keys, "Bearer abc123"), Some("vATIS"));
+        assert_eq!(api_key_name(&keys, "Bearer wrong"), None);
+        assert_eq!(api_key_name(&keys, "abc123"), None);
+        assert_eq!(api_key_name(&keys, "Bearer "), None);
+        assert_eq!(api_key_name(&keys, ""), None);
+    }
+
+    #[test]
+    fn test_schedule_block_problem() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let hours = |h: i64| now + Duration::hours(h);
+        assert_eq!(schedule_block_problem(hours(1), hours(3), now), None);
+        // already started, but not over
+        assert_eq!(schedule_block_problem(hours(-1), hours(1), now), None);
+        assert_eq!(
+            schedule_block_problem(hours(3), hours(1), now),
+            Some("The end must be after the start".to_owned())
+        );
+        assert_eq!(
+            schedule_block_problem(hours(-3), hours(-1), now),
+            Some("That time has already passed".to_owned())
+        );
+        assert_eq!(
+            schedule_block_problem(hours(1), hours(14), now),
+            Some("Blocks can be at most 12 hours long".to_owned())
+        );
+        assert_eq!(
+            schedule_block_problem(hours(24 * 31), hours(24 * 31 + 1), now),
+            Some("Blocks can be at most 30 days ahead".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_certification_last_used() {
+        let usage = vec![
+            ConfigCertificationUsage {
+                name: "APP".to_owned(),
+                position_suffixes: vec!["_APP".to_owned(), "_DEP".to_owned()],
+                unused_days: 180,
+            },
+            ConfigCertificationUsage {
+                name: "CTR".to_owned(),
+                position_suffixes: vec!["_CTR".to_owned()],
+                unused_days: 180,
+            },
+        ];
+        let sessions = [
+            ("DEN_APP", "2024-01-05T10:00:00"),
+            ("DEN_DEP", "2024-02-01T08:30:00.123Z"),
+            ("DEN_TWR", "2024-03-01T00:00:00"),
+            ("DEN_APP", "not a date"),
+        ];
+        let last_used = certification_last_used(&usage, &sessions);
+        assert_eq!(
+            last_used,
+            HashMap::from([(
+                "APP",
+                Utc.with_ymd_and_hms(2024, 2, 1, 8, 30, 0).unwrap() + Duration::milliseconds(123)
+            )])
+        );
+    }
+
+    #[test]
+    fn test_certification_unused_until() {
+        let mut config = Config::default();
+        config.training.certification_usage = vec![ConfigCertificationUsage {
+            name: "APP".to_owned(),
+            position_suffixes: vec!["_APP".to_owned()],
+            unused_days: 30,
+        }];
+        let certified = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut cert = Certification {
+            id: 1,
+            cid: 1,
+            name: "APP".to_owned(),
+            value: "Certified".to_owned(),
+            changed_on: certified,
+            set_by: 0,
+            valid_until: None,
+            last_used: None,
+        };
+        assert_eq!(
+            certification_unused_until(&config, &cert),
+            Some(certified + Duration::days(30))
+        );
+        cert.last_used = Some(certified + Duration::days(10));
+        assert_eq!(
+            certification_unused_until(&config, &cert),
+            Some(certified + Duration::days(40))
+        );
+        cert.value = "Training".to_owned();
+        assert_eq!(certification_unused_until(&config, &cert), None);
+        cert.value = "Certified".to_owned();
+        cert.name = "TWR".to_owned();
+        assert_eq!(certification_unused_until(&config, &cert), None);
+    }
+
+    #[test]
+    fn test_reminder_due() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let date = now + Duration::days(14);
+        assert!(reminder_due(date, now, 14, None));
+        assert!(reminder_due(now + Duration::days(2), now, 14, None));
+        assert!(!reminder_due(now + Duration::days(15), now, 14, None));
+        assert!(!reminder_due(now - Duration::days(1), now, 14, None));
+        assert!(!reminder_due(date, now, 14, Some(date)));
+        assert!(reminder_due(date, now, 14, Some(date - Duration::days(90))));
+        assert!(!reminder_due(now, now, 0, None));
+    }
+
+    #[test]
+    fn test_call_outcome() {
+        assert_eq!(
+            CallOutcome::from_status(reqwest::StatusCode::OK),
+            CallOutcome::Success
+        );
+        assert_eq!(
+            CallOutcome::from_status(reqwest::StatusCode::NOT_FOUND),
+            CallOutcome::ClientError
+        );
+        assert_eq!(
+            CallOutcome::from_status(reqwest::StatusCode::BAD_GATEWAY),
+            CallOutcome::ServerError
+        );
+        let error = std::io::Error::other("connection reset");
+        assert_eq!(CallOutcome::from_error(&error), CallOutcome::Failed);
+    }
+
+    #[test]
+    fn test_api_call_stats() {
+        let mut stats = ApiCallStats::default();
+        assert_eq!(stats.failure_percent(), 0.0);
+        assert_eq!(stats.average_millis(), 0);
+
+        stats.record(CallOutcome::Success, std::time::Duration::from_millis(100));
+        stats.record(CallOutcome::Success, std::time::Duration::from_millis(200));
+        stats.record(CallOutcome::Timeout, std::time::Duration::from_millis(900));
+        stats.record(
+            CallOutcome::ServerError,
+            std::time::Duration::from_millis(400),
+        );
+        assert_eq!(stats.calls(), 4);
+        assert_eq!(stats.failures(), 2);
+        assert_eq!(stats.failure_percent(), 50.0);
+        assert_eq!(stats.average_millis(), 400);
+        assert_eq!(stats.max_millis, 900);
+    }
+
+    #[test]
+    fn test_metar_retry_delay() {
+        assert_eq!(metar_retry_delay(1), std::time::Duration::from_millis(250));
+        assert_eq!(metar_retry_delay(2), std::time::Duration::from_millis(500));
+        assert_eq!(
+            metar_retry_delay(3),
+            std::time::Duration::from_millis(1_000)
+        );
+    }
+
+    #[test]
+    fn test_activity_fetch_start() {
+        let now = Utc.with_ymd_and_hms(2024, 8, 20, 12, 0, 0).unwrap();
+        assert_eq!(activity_fetch_start(now), "2024-03-01");
+        // the fetch covers both quarters of the removal report
+        for mode in [QuarterMode::Rolling, QuarterMode::Calendar] {
+            for month in (0..2).flat_map(|quarter| quarter_months(mode, now, quarter)) {
+                assert!(month.as_str() >= "2024-03", "{month}");
+            }
+        }
+        let now = Utc.with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap();
+        assert_eq!(activity_fetch_start(now), "2023-10-01");
+    }
+
+    #[test]
+    fn test_email_digest_days() {
+        let mut config = Config::default();
+        assert_eq!(config.email.digest_days(), 7);
+        config.email.digest_interval_days = 14;
+        assert_eq!(config.email.digest_days(), 14);
+        config.email.digest_interval_days = 0;
+        assert_eq!(config.email.digest_days(), 1);
+    }
+
+    #[test]
+    fn test_airport_coordinates() {
+        let airports = vec![
+            Airport {
+                code: "KDEN".to_owned(),
+                latitude: Some(39.8617),
+                longitude: Some(-104.6732),
+                ..Default::default()
+            },
+            Airport {
+                code: "KSAN".to_owned(),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            airport_coordinates(&airports, "kden"),
+            Some((39.8617, -104.6732))
+        );
+        let ksan = airport_coordinates(&airports, "KSAN").unwrap();
+        assert!((ksan.0 - 32.73).abs() < 0.1 && (ksan.1 + 117.19).abs() < 0.1);
+        assert!(airport_coordinates(&airports, "ZZZZ").is_none());
+    }
+
+    #[test]
+    fn test_page_routes_registered() {
+        let sources = [
+            include_str!("../endpoints/mod.rs"),
+            include_str!("../endpoints/admin.rs"),
+            include_str!("../endpoints/airspace.rs"),
+            include_str!("../endpoints/api.rs"),
+            include_str!("../endpoints/auth.rs"),
+            include_str!("../endpoints/events.rs"),
+            include_str!("../endpoints/facility.rs"),
+            include_str!("../endpoints/homepage.rs"),
+            include_str!("../endpoints/news.rs"),
+            include_str!("../endpoints/user.rs"),
+        ];
+        let registered: Vec<&str> = sources
+            .iter()
+            .flat_map(|source| source.split(".route(").skip(1))
+            .filter_map(|call| call.trim_start().strip_prefix('"')?.split('"').next())
+            .collect();
+        for route in PAGE_ROUTES {
+            assert!(
+                registered.contains(&route),
+                "{route} isn't a registered route"
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_import_url() {
+        let allowed = vec!["zlcartcc.org".to_owned()];
+        assert_eq!(
+            event_import_url(&allowed, " https://ZLCARTCC.org/events ")
+                .unwrap()
+                .as_str(),
+            "https://zlcartcc.org/api/events"
+        );
+        assert!(event_import_url(&allowed, "http://zlcartcc.org").is_err());
+        assert!(event_import_url(&allowed, "https://zlcartcc.org:8080").is_err());
+        assert!(event_import_url(&allowed, "https://127.0.0.1").is_err());
+        assert!(event_import_url(&allowed, "https://zlcartcc.org.example.com").is_err());
+        assert!(event_import_url(&allowed, "not a url").is_err());
+        assert!(event_import_url(&[], "https://zlcartcc.org").is_err());
+    }
+
+    #[test]
+    fn test_maintenance_exempt() {
+        for path in [
+            "/auth/log_in",
+            "/assets/sop.pdf",
+            "/api/atis",
+            "/api/atis/KDEN",
+        ] {
+            assert!(maintenance_exempt(path), "{path}");
+        }
+        for path in ["/", "/admin/maintenance", "/api/events", "/facility/roster"] {
+            assert!(!maintenance_exempt(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_with_role() {
+        assert_eq!(with_role("", "MTR", true), "MTR");
+        assert_eq!(with_role("FE", "MTR", true), "FE,MTR");
+        assert_eq!(with_role("FE,MTR", "MTR", true), "FE,MTR");
+        assert_eq!(with_role("FE,MTR", "MTR", false), "FE");
+        assert_eq!(with_role("MTR", "MTR", false), "");
+        assert_eq!(with_role("FE", "MTR", false), "FE");
     }
 }
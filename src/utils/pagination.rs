@@ -0,0 +1,69 @@
+//! Splitting long lists of results into pages.
+
+use serde::{Deserialize, Serialize};
+
+/// Most results that can be requested for a single page.
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// Page and page size from a request's query string.
+///
+/// Pages are numbered from 1. Both values are clamped by [`Pagination::new`],
+/// so any value a visitor puts in the URL is safe to pass through.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Position within a list of results that's split into pages.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Pagination {
+    /// Current page, starting at 1
+    pub page: u32,
+    /// Total number of pages, always at least 1
+    pub pages: u32,
+    pub per_page: u32,
+    /// Total number of results across all pages
+    pub total: u32,
+}
+
+impl Pagination {
+    /// Paginate `total` results, using `default_per_page` when the query doesn't
+    /// specify a page size.
+    pub fn new(query: &PageQuery, default_per_page: u32, total: u32) -> Self {
+        let per_page = query
+            .per_page
+            .unwrap_or(default_per_page)
+            .clamp(1, MAX_PER_PAGE);
+        let pages = total.div_ceil(per_page).max(1);
+        Self {
+            page: query.page.unwrap_or(1).clamp(1, pages),
+            pages,
+            per_page,
+            total,
+        }
+    }
+
+    /// Number of results to return, for a query's `LIMIT`.
+    pub fn limit(&self) -> u32 {
+        self.per_page
+    }
+
+    /// Number of results to skip to reach the current page, for a query's `OFFSET`.
+    pub fn offset(&self) -> u32 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+/// A single page of results, along with where it falls in the full list.
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub pagination: Pagination,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, pagination: Pagination) -> Self {
+        Self { items, pagination }
+    }
+}
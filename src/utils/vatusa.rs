@@ -1,7 +1,8 @@
 use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::utils::GENERAL_HTTP_CLIENT;
+use crate::utils::{api_metrics, GENERAL_HTTP_CLIENT};
 
 const BASE_URL: &str = "https://api.vatusa.net/";
 
@@ -72,10 +73,11 @@ pub async fn get_roster(facility: &str, membership: MembershipType) -> Result<Ve
         MembershipType::Visit => "visit",
         MembershipType::Both => "both",
     };
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!("{BASE_URL}facility/{facility}/roster/{mem_str}"))
-        .send()
-        .await?;
+    let resp = api_metrics::send(
+        "vatusa_roster",
+        GENERAL_HTTP_CLIENT.get(format!("{BASE_URL}facility/{facility}/roster/{mem_str}")),
+    )
+    .await?;
     if !resp.status().is_success() {
         bail!(
             "Got status {} from VATUSA roster API at {}",
@@ -131,6 +133,56 @@ pub struct TrainingRecord {
     pub notes: String,
 }
 
+/// Date of the most recent training session at the facility, if any.
+pub fn last_session_date<'a>(records: &'a [TrainingRecord], facility: &str) -> Option<&'a str> {
+    records
+        .iter()
+        .filter(|record| record.facility_id == facility)
+        .map(|record| record.session_date.as_str())
+        .max()
+}
+
+/// Filters for narrowing down a controller's training records.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrainingRecordFilter {
+    /// Part of the position name, like "GND"; case-insensitive
+    pub position: String,
+    /// Instructor CID, left as text since the form can send it empty
+    pub instructor: String,
+    /// Earliest session date, as "YYYY-MM-DD"
+    pub from: String,
+    /// Latest session date, as "YYYY-MM-DD"
+    pub to: String,
+}
+
+impl TrainingRecordFilter {
+    /// Whether any of the filters are set.
+    pub fn is_active(&self) -> bool {
+        !(self.position.is_empty()
+            && self.instructor.is_empty()
+            && self.from.is_empty()
+            && self.to.is_empty())
+    }
+
+    /// Whether the record passes all the set filters.
+    pub fn matches(&self, record: &TrainingRecord) -> bool {
+        let date = record
+            .session_date
+            .get(..10)
+            .unwrap_or(&record.session_date);
+        (self.position.is_empty()
+            || record
+                .position
+                .to_uppercase()
+                .contains(&self.position.trim().to_uppercase()))
+            && (self.instructor.is_empty()
+                || self.instructor.trim() == record.instructor_id.to_string())
+            && (self.from.is_empty() || date >= self.from.as_str())
+            && (self.to.is_empty() || date <= self.to.as_str())
+    }
+}
+
 /// Get the controller's transfer checklist information.
 pub async fn transfer_checklist(api_key: &str, cid: u32) -> Result<TransferChecklist> {
     #[derive(Deserialize)]
@@ -138,11 +190,13 @@ pub async fn transfer_checklist(api_key: &str, cid: u32) -> Result<TransferCheck
         pub data: TransferChecklist,
     }
 
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!("{BASE_URL}/v2/user/{cid}/transfer/checklist"))
-        .query(&[("api_key", api_key)])
-        .send()
-        .await?;
+    let resp = api_metrics::send(
+        "vatusa_transfer_checklist",
+        GENERAL_HTTP_CLIENT
+            .get(format!("{BASE_URL}/v2/user/{cid}/transfer/checklist"))
+            .query(&[("api_key", api_key)]),
+    )
+    .await?;
     if !resp.status().is_success() {
         // not including the URL since it'll have the API key in it
         bail!(
@@ -161,10 +215,11 @@ pub async fn get_controller_info(cid: u32) -> Result<RosterMember> {
         pub data: RosterMember,
     }
 
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!("{BASE_URL}/user/{cid}"))
-        .send()
-        .await?;
+    let resp = api_metrics::send(
+        "vatusa_user",
+        GENERAL_HTTP_CLIENT.get(format!("{BASE_URL}/user/{cid}")),
+    )
+    .await?;
     if !resp.status().is_success() {
         bail!(
             "Got status {} from VATUSA controller API at {}",
@@ -183,11 +238,13 @@ pub async fn get_training_records(api_key: &str, cid: u32) -> Result<Vec<Trainin
         pub data: Vec<TrainingRecord>,
     }
 
-    let resp = GENERAL_HTTP_CLIENT
-        .get(format!("{BASE_URL}/user/{cid}/training/records"))
-        .query(&[("api_key", api_key)])
-        .send()
-        .await?;
+    let resp = api_metrics::send(
+        "vatusa_training_records",
+        GENERAL_HTTP_CLIENT
+            .get(format!("{BASE_URL}/user/{cid}/training/records"))
+            .query(&[("api_key", api_key)]),
+    )
+    .await?;
     if !resp.status().is_success() {
         // not including the URL since it'll have the API key in it
         bail!(
@@ -198,3 +255,168 @@ pub async fn get_training_records(api_key: &str, cid: u32) -> Result<Vec<Trainin
     let data: Wrapper = resp.json().await?;
     Ok(data.data)
 }
+
+/// Get all training records entered at the facility.
+pub async fn get_facility_training_records(
+    api_key: &str,
+    facility: &str,
+) -> Result<Vec<TrainingRecord>> {
+    #[derive(Deserialize)]
+    pub struct Wrapper {
+        pub data: Vec<TrainingRecord>,
+    }
+
+    let resp = api_metrics::send(
+        "vatusa_facility_training_records",
+        GENERAL_HTTP_CLIENT
+            .get(format!("{BASE_URL}/facility/{facility}/training/records"))
+            .query(&[("api_key", api_key)]),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        // not including the URL since it'll have the API key in it
+        bail!(
+            "Got status {} from VATUSA facility training records API",
+            resp.status().as_u16()
+        );
+    }
+    let data: Wrapper = resp.json().await?;
+    Ok(data.data)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RatingChange {
+    pub from: i8,
+    pub to: i8,
+    pub created_at: String,
+}
+
+/// Get a controller's rating history.
+pub async fn get_rating_history(cid: u32) -> Result<Vec<RatingChange>> {
+    #[derive(Deserialize)]
+    pub struct Wrapper {
+        pub data: Vec<RatingChange>,
+    }
+
+    let resp = api_metrics::send(
+        "vatusa_rating_history",
+        GENERAL_HTTP_CLIENT.get(format!("{BASE_URL}user/{cid}/rating/history")),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        bail!(
+            "Got status {} from VATUSA rating history API at {}",
+            resp.status().as_u16(),
+            resp.url()
+        );
+    }
+    let data: Wrapper = resp.json().await?;
+    Ok(data.data)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SoloCert {
+    pub id: u32,
+    pub cid: u32,
+    pub position: String,
+    pub expires: String,
+}
+
+/// Get all solo certifications known to VATUSA.
+///
+/// The API returns certs for all facilities; filter by position as needed.
+pub async fn get_facility_solo_certs() -> Result<Vec<SoloCert>> {
+    #[derive(Deserialize)]
+    pub struct Wrapper {
+        pub data: Vec<SoloCert>,
+    }
+
+    let resp = api_metrics::send(
+        "vatusa_solo_certs",
+        GENERAL_HTTP_CLIENT.get(format!("{BASE_URL}/solo")),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        bail!(
+            "Got status {} from VATUSA solo cert API at {}",
+            resp.status().as_u16(),
+            resp.url()
+        );
+    }
+    let data: Wrapper = resp.json().await?;
+    Ok(data.data)
+}
+
+/// Add or remove a staff role for a controller at a facility.
+pub async fn set_role(
+    api_key: &str,
+    cid: u32,
+    facility: &str,
+    role: &str,
+    add: bool,
+) -> Result<()> {
+    let url = format!("{BASE_URL}user/{cid}/roles/{facility}/{role}");
+    let request = if add {
+        GENERAL_HTTP_CLIENT.post(url)
+    } else {
+        GENERAL_HTTP_CLIENT.delete(url)
+    };
+    let resp = api_metrics::send("vatusa_roles", request.query(&[("api_key", api_key)])).await?;
+    if !resp.status().is_success() {
+        // not including the URL since it'll have the API key in it
+        bail!(
+            "Got status {} from VATUSA user roles API",
+            resp.status().as_u16()
+        );
+    }
+    Ok(())
+}
+
+/// Remove a controller's solo certification for a position from VATUSA.
+pub async fn delete_solo_cert(api_key: &str, cid: u32, position: &str) -> Result<()> {
+    let resp = api_metrics::send(
+        "vatusa_solo_certs",
+        GENERAL_HTTP_CLIENT
+            .delete(format!("{BASE_URL}/solo"))
+            .query(&[("api_key", api_key)])
+            .form(&[("cid", cid.to_string()), ("position", position.to_owned())]),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        // not including the URL since it'll have the API key in it
+        bail!(
+            "Got status {} from VATUSA solo cert API",
+            resp.status().as_u16()
+        );
+    }
+    Ok(())
+}
+
+/// Report a newly-issued solo certification to VATUSA.
+pub async fn report_solo_cert(
+    api_key: &str,
+    cid: u32,
+    position: &str,
+    expiration: DateTime<Utc>,
+) -> Result<()> {
+    let resp = api_metrics::send(
+        "vatusa_solo_certs",
+        GENERAL_HTTP_CLIENT
+            .post(format!("{BASE_URL}/solo"))
+            .query(&[("api_key", api_key)])
+            .form(&[
+                ("cid", cid.to_string()),
+                ("position", position.to_owned()),
+                ("expDate", expiration.format("%Y-%m-%d").to_string()),
+            ]),
+    )
+    .await?;
+    if !resp.status().is_success() {
+        // not including the URL since it'll have the API key in it
+        bail!(
+            "Got status {} from VATUSA solo cert API",
+            resp.status().as_u16()
+        );
+    }
+    Ok(())
+}